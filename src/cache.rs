@@ -0,0 +1,321 @@
+// Copyright 2025 Fernando Borretti
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fs::read_to_string;
+use std::path::Path;
+use std::path::PathBuf;
+use std::thread::available_parallelism;
+use std::thread::scope;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::db::Database;
+use crate::db::ParseCacheEntry;
+use crate::error::ErrorReport;
+use crate::error::Fallible;
+use crate::format::DeckFormat;
+use crate::parser::include_directive;
+use crate::parser::sort_cards;
+use crate::types::aliases::DeckName;
+use crate::types::card::Card;
+use crate::types::card::CardContent;
+use crate::types::card_hash::HashVersion;
+use crate::walk::deck_files;
+
+/// The version of the parser's output that cached cards were produced by.
+/// Cached cards from any other version are parsed again. Bump this whenever a
+/// change to the parser or to `Card` could change the cards parsed from a file.
+pub const PARSE_CACHE_VERSION: &str = "1";
+
+/// A card as stored in the parse cache. The file path is the cache key, and
+/// the hash is recomputed on load.
+#[derive(Serialize, Deserialize)]
+struct CachedCard {
+    deck_name: DeckName,
+    range: (usize, usize),
+    content: CardContent,
+    note: Option<String>,
+}
+
+/// A deck file whose contents have been read.
+struct SourceFile {
+    path: PathBuf,
+    format: DeckFormat,
+    text: String,
+    content_hash: String,
+}
+
+impl SourceFile {
+    /// Files that include other files can change without their own text
+    /// changing, so they are never cached.
+    fn cacheable(&self) -> bool {
+        !self
+            .text
+            .lines()
+            .any(|line| include_directive(line).is_some())
+    }
+
+    fn key(&self) -> Fallible<String> {
+        self.path
+            .to_str()
+            .map(String::from)
+            .ok_or_else(|| ErrorReport::new("invalid path"))
+    }
+}
+
+/// Like `parse_deck_checked`, without a check.
+#[cfg(test)]
+pub fn parse_deck_cached(directory: &Path, db: &mut Database) -> Fallible<Vec<Card>> {
+    parse_deck_checked(directory, db, |_| Ok(()))
+}
+
+/// Parse all the deck files in the given directory, reusing the cards cached
+/// in the database for files that haven't changed since they were last
+/// parsed. The remaining files are parsed in parallel, `check` is run over
+/// their cards, and the cache is updated with them. Cards taken from the cache
+/// passed the check when they were cached.
+pub fn parse_deck_checked(
+    directory: &Path,
+    db: &mut Database,
+    check: impl FnOnce(&[Card]) -> Fallible<()>,
+) -> Fallible<Vec<Card>> {
+    let hash_version = db.hash_version()?;
+    let mut files = Vec::new();
+    for path in deck_files(directory)? {
+        let Some(format) = DeckFormat::from_path(&path) else {
+            continue;
+        };
+        let text = read_to_string(&path)?;
+        let content_hash = blake3::hash(text.as_bytes()).to_hex().to_string();
+        files.push(SourceFile {
+            path,
+            format,
+            text,
+            content_hash,
+        });
+    }
+
+    let cache: HashMap<String, ParseCacheEntry> = db.parse_cache()?;
+    let mut parsed: Vec<Option<Vec<Card>>> = Vec::with_capacity(files.len());
+    let mut stale: Vec<usize> = Vec::new();
+    for (index, file) in files.iter().enumerate() {
+        let hit = cache.get(&file.key()?).filter(|entry| {
+            entry.content_hash == file.content_hash
+                && entry.hash_version == hash_version
+                && entry.parser_version == PARSE_CACHE_VERSION
+        });
+        match hit {
+            Some(entry) if file.cacheable() => {
+                parsed.push(Some(from_cache(entry, &file.path, hash_version)?));
+            }
+            _ => {
+                parsed.push(None);
+                stale.push(index);
+            }
+        }
+    }
+    log::debug!(
+        "Parsing {} of {} deck files; the rest are cached.",
+        stale.len(),
+        files.len()
+    );
+
+    let stale_files: Vec<&SourceFile> = stale.iter().map(|index| &files[*index]).collect();
+    let results = parse_parallel(&stale_files, hash_version);
+    let mut entries = Vec::new();
+    let mut fresh = Vec::new();
+    for (index, result) in stale.into_iter().zip(results) {
+        let cards = result?;
+        let file = &files[index];
+        fresh.extend(cards.iter().cloned());
+        if file.cacheable() {
+            entries.push(ParseCacheEntry {
+                file_path: file.key()?,
+                content_hash: file.content_hash.clone(),
+                hash_version,
+                parser_version: PARSE_CACHE_VERSION.to_string(),
+                cards: to_cache(&cards)?,
+            });
+        }
+        parsed[index] = Some(cards);
+    }
+    check(&fresh)?;
+
+    let keep: HashSet<String> = files
+        .iter()
+        .filter(|file| file.cacheable())
+        .map(SourceFile::key)
+        .collect::<Fallible<_>>()?;
+    db.update_parse_cache(&entries, &keep)?;
+
+    let mut all_cards: Vec<Card> = parsed.into_iter().flatten().flatten().collect();
+    sort_cards(&mut all_cards);
+    Ok(all_cards)
+}
+
+/// Parse the given files, spreading them over the available threads. The
+/// results are in the same order as the files.
+fn parse_parallel(files: &[&SourceFile], hash_version: HashVersion) -> Vec<Fallible<Vec<Card>>> {
+    if files.is_empty() {
+        return Vec::new();
+    }
+    let threads = available_parallelism().map(|n| n.get()).unwrap_or(1);
+    let chunk_size = files.len().div_ceil(threads);
+    scope(|s| {
+        let handles: Vec<_> = files
+            .chunks(chunk_size)
+            .map(|chunk| {
+                s.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|file| file.format.parse_file(&file.path, &file.text, hash_version))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("parser thread panicked"))
+            .collect()
+    })
+}
+
+fn to_cache(cards: &[Card]) -> Fallible<String> {
+    let cached: Vec<CachedCard> = cards
+        .iter()
+        .map(|card| CachedCard {
+            deck_name: card.deck_name().clone(),
+            range: card.range(),
+            content: card.content().clone(),
+            note: card.note().map(String::from),
+        })
+        .collect();
+    Ok(serde_json::to_string(&cached)?)
+}
+
+fn from_cache(
+    entry: &ParseCacheEntry,
+    path: &Path,
+    hash_version: HashVersion,
+) -> Fallible<Vec<Card>> {
+    let cached: Vec<CachedCard> = serde_json::from_str(&entry.cards)?;
+    Ok(cached
+        .into_iter()
+        .map(|card| {
+            Card::new(
+                card.deck_name,
+                path.to_path_buf(),
+                card.range,
+                card.content,
+                card.note,
+                hash_version,
+            )
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::write;
+
+    use super::*;
+    use crate::error::fail;
+    use crate::helper::create_tmp_directory;
+
+    fn hashes(cards: &[Card]) -> Vec<String> {
+        cards.iter().map(|card| card.hash().to_hex()).collect()
+    }
+
+    /// Cached parses give the same cards as a full parse, and edited or
+    /// deleted files are picked up.
+    #[test]
+    fn test_parse_deck_cached() -> Fallible<()> {
+        let directory = create_tmp_directory()?;
+        write(directory.join("a.md"), "Q: a\nA: 1\n")?;
+        write(directory.join("b.md"), "C: [b] is 2.\n")?;
+        write(directory.join("c.md"), "!include a.md\n\nQ: c\nA: 3\n")?;
+        let mut db = Database::new(":memory:")?;
+
+        let first = parse_deck_cached(&directory, &mut db)?;
+        let expected = parse_deck_cached(&directory, &mut Database::new(":memory:")?)?;
+        assert_eq!(hashes(&first), hashes(&expected));
+        // The file with an include is not cached.
+        assert_eq!(db.parse_cache()?.len(), 2);
+
+        let second = parse_deck_cached(&directory, &mut db)?;
+        assert_eq!(hashes(&second), hashes(&expected));
+        assert_eq!(second[0].file_path(), first[0].file_path());
+
+        write(directory.join("a.md"), "Q: a\nA: one\n")?;
+        std::fs::remove_file(directory.join("b.md"))?;
+        let third = parse_deck_cached(&directory, &mut db)?;
+        let expected = parse_deck_cached(&directory, &mut Database::new(":memory:")?)?;
+        assert_eq!(hashes(&third), hashes(&expected));
+        assert_eq!(db.parse_cache()?.len(), 1);
+        Ok(())
+    }
+
+    /// Only the cards of the files that were parsed are checked, and files
+    /// whose cards fail the check are not cached.
+    #[test]
+    fn test_parse_deck_checked() -> Fallible<()> {
+        let directory = create_tmp_directory()?;
+        write(directory.join("a.md"), "Q: a\nA: 1\n")?;
+        let mut db = Database::new(":memory:")?;
+
+        let result = parse_deck_checked(&directory, &mut db, |_| fail("bad card"));
+        assert!(result.is_err());
+        assert!(db.parse_cache()?.is_empty());
+
+        let mut checked = 0;
+        parse_deck_checked(&directory, &mut db, |cards| {
+            checked += cards.len();
+            Ok(())
+        })?;
+        let cards = parse_deck_checked(&directory, &mut db, |cards| {
+            checked += cards.len();
+            Ok(())
+        })?;
+        assert_eq!(cards.len(), 1);
+        assert_eq!(checked, 1);
+        Ok(())
+    }
+
+    /// Cards cached by another version of the parser are parsed again.
+    #[test]
+    fn test_parser_version_mismatch() -> Fallible<()> {
+        let directory = create_tmp_directory()?;
+        write(directory.join("a.md"), "Q: a\nA: 1\n")?;
+        let mut db = Database::new(":memory:")?;
+        parse_deck_cached(&directory, &mut db)?;
+
+        // Poison the cached entry as if an older parser had produced it.
+        let mut entries: Vec<ParseCacheEntry> = db.parse_cache()?.into_values().collect();
+        let keep: HashSet<String> = entries.iter().map(|e| e.file_path.clone()).collect();
+        for entry in &mut entries {
+            entry.parser_version = "0.0.0".to_string();
+            entry.cards = "[]".to_string();
+        }
+        db.update_parse_cache(&entries, &keep)?;
+
+        let cards = parse_deck_cached(&directory, &mut db)?;
+        assert_eq!(cards.len(), 1);
+        let entry = db.parse_cache()?.into_values().next().unwrap();
+        assert_eq!(entry.parser_version, PARSE_CACHE_VERSION);
+        Ok(())
+    }
+}
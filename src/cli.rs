@@ -18,13 +18,22 @@ use clap::Parser;
 use clap::Subcommand;
 use tokio::spawn;
 
+use crate::cmd::browse::server::BrowseConfig;
+use crate::cmd::browse::server::start_browse;
+use crate::cmd::card::print_card;
+use crate::cmd::check::DiagnosticFormat;
 use crate::cmd::check::check_collection;
 use crate::cmd::drill::server::AnswerControls;
 use crate::cmd::drill::server::ServerConfig;
 use crate::cmd::drill::server::start_server;
+use crate::cmd::drill::tui::start_tui;
 use crate::cmd::export::export_collection;
+use crate::cmd::fmt::format_collection;
+use crate::cmd::lint::lint_collection;
+use crate::cmd::lsp::start_language_server;
 use crate::cmd::orphans::delete_orphans;
 use crate::cmd::orphans::list_orphans;
+use crate::cmd::rehash::rehash_collection;
 use crate::cmd::stats::StatsFormat;
 use crate::cmd::stats::print_stats;
 use crate::error::Fallible;
@@ -34,10 +43,13 @@ use crate::utils::wait_for_server;
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 enum Command {
-    /// Drill cards through a web interface.
+    /// Drill cards through a web interface, or in the terminal with `--tui`.
     Drill {
         /// Path to the collection directory. By default, the current working directory is used.
         directory: Option<String>,
+        /// Path to the database. By default, the collection's `hashcards.db` if it exists, or else a database in the state directory.
+        #[arg(long)]
+        db: Option<String>,
         /// Maximum number of cards to drill in a session. By default, all cards due today are drilled.
         #[arg(long)]
         card_limit: Option<usize>,
@@ -62,19 +74,100 @@ enum Command {
         /// Whether or not to bury siblings. Default is true.
         #[arg(long)]
         bury_siblings: Option<bool>,
+        /// Whether to resume an unfinished session from earlier today. By default, you are asked.
+        #[arg(long)]
+        resume: Option<bool>,
+        /// Drill in the terminal instead of the browser.
+        #[arg(long)]
+        tui: bool,
     },
-    /// Check the integrity of a collection.
+    /// Browse the cards in a collection, and see how they render, through a web interface.
+    Browse {
+        /// Path to the collection directory. By default, the current working directory is used.
+        directory: Option<String>,
+        /// Path to the database. By default, the collection's `hashcards.db` if it exists, or else a database in the state directory.
+        #[arg(long)]
+        db: Option<String>,
+        /// The host address to bind to. Default is 127.0.0.1.
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+        /// The port to use for the web server. Default is 8000.
+        #[arg(long, default_value_t = 8000)]
+        port: u16,
+        /// Whether to open the browser automatically. Default is true.
+        #[arg(long)]
+        open_browser: Option<bool>,
+    },
+    /// Show a card's content, schedule, and review history.
+    Card {
+        /// The card's hash, or a prefix of it, or its location as `file:line`.
+        card: String,
+        /// Path to the collection directory. By default, the current working directory is used.
+        directory: Option<String>,
+        /// Path to the database. By default, the collection's `hashcards.db` if it exists, or else a database in the state directory.
+        #[arg(long)]
+        db: Option<String>,
+        /// Print JSON instead of text.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Check the integrity of a collection, reporting every problem found.
     Check {
         /// Path to the collection directory. By default, the current working directory is used.
         directory: Option<String>,
+        /// Path to the database. By default, the collection's `hashcards.db` if it exists, or else a database in the state directory.
+        #[arg(long)]
+        db: Option<String>,
+        /// Which output format to use.
+        #[arg(long, default_value_t = DiagnosticFormat::Text)]
+        format: DiagnosticFormat,
+    },
+    /// Rewrite deck files into the canonical layout, without changing any card.
+    Fmt {
+        /// Path to the collection directory. By default, the current working directory is used.
+        directory: Option<String>,
+        /// Path to the database. By default, the collection's `hashcards.db` if it exists, or else a database in the state directory.
+        #[arg(long)]
+        db: Option<String>,
+        /// Don't write anything, and fail if any file is not formatted.
+        #[arg(long)]
+        check: bool,
+    },
+    /// Warn about cards that are likely to be hard to learn.
+    Lint {
+        /// Path to the collection directory. By default, the current working directory is used.
+        directory: Option<String>,
+        /// Path to the database. By default, the collection's `hashcards.db` if it exists, or else a database in the state directory.
+        #[arg(long)]
+        db: Option<String>,
+        /// Which output format to use.
+        #[arg(long, default_value_t = DiagnosticFormat::Text)]
+        format: DiagnosticFormat,
+    },
+    /// Run a language server for deck files over stdio.
+    Lsp {
+        /// Path to the collection directory. By default, the current working directory is used.
+        directory: Option<String>,
+        /// Path to the database. By default, the collection's `hashcards.db` if it exists, or else a database in the state directory.
+        #[arg(long)]
+        db: Option<String>,
     },
     /// Print collection statistics.
     Stats {
         /// Path to the collection directory. By default, the current working directory is used.
         directory: Option<String>,
+        /// Path to the database. By default, the collection's `hashcards.db` if it exists, or else a database in the state directory.
+        #[arg(long)]
+        db: Option<String>,
         /// Which output format to use.
         #[arg(long, default_value_t = StatsFormat::Html)]
         format: StatsFormat,
+        /// Optional path to the output file. By default, the output is printed to stdout.
+        #[arg(long)]
+        output: Option<String>,
+        /// A window, in days, over which the JSON output reports review statistics. Can be given more than once; all-time statistics are always included.
+        #[arg(long = "window", value_name = "DAYS", default_values_t = [7, 30, 365], value_parser = clap::value_parser!(u32).range(1..))]
+        windows: Vec<u32>,
     },
     /// Commands relating to orphan cards.
     Orphans {
@@ -85,10 +178,21 @@ enum Command {
     Export {
         /// Path to the collection directory. By default, the current working directory is used.
         directory: Option<String>,
+        /// Path to the database. By default, the collection's `hashcards.db` if it exists, or else a database in the state directory.
+        #[arg(long)]
+        db: Option<String>,
         /// Optional path to the output file. By default, the output is printed to stdout.
         #[arg(long)]
         output: Option<String>,
     },
+    /// Switch a collection to normalized card hashes, which ignore whitespace and formatting.
+    Rehash {
+        /// Path to the collection directory. By default, the current working directory is used.
+        directory: Option<String>,
+        /// Path to the database. By default, the collection's `hashcards.db` if it exists, or else a database in the state directory.
+        #[arg(long)]
+        db: Option<String>,
+    },
 }
 
 #[derive(Subcommand)]
@@ -97,11 +201,17 @@ enum OrphanCommand {
     List {
         /// Path to the collection directory. By default, the current working directory is used.
         directory: Option<String>,
+        /// Path to the database. By default, the collection's `hashcards.db` if it exists, or else a database in the state directory.
+        #[arg(long)]
+        db: Option<String>,
     },
     /// Remove all orphan cards from the database.
     Delete {
         /// Path to the collection directory. By default, the current working directory is used.
         directory: Option<String>,
+        /// Path to the database. By default, the collection's `hashcards.db` if it exists, or else a database in the state directory.
+        #[arg(long)]
+        db: Option<String>,
     },
 }
 
@@ -110,6 +220,7 @@ pub async fn entrypoint() -> Fallible<()> {
     match cli {
         Command::Drill {
             directory,
+            db,
             card_limit,
             new_card_limit,
             host,
@@ -118,24 +229,15 @@ pub async fn entrypoint() -> Fallible<()> {
             open_browser,
             answer_controls,
             bury_siblings,
+            resume,
+            tui,
         } => {
-            if open_browser.unwrap_or(true) {
-                // Start a separate task to open the browser once the server is up.
-                let browser_host = host.clone();
-                spawn(async move {
-                    match wait_for_server(&browser_host, port).await {
-                        Ok(_) => {
-                            let _ = open::that(format!("http://{browser_host}:{port}/"));
-                        }
-                        Err(e) => {
-                            eprintln!("Failed to connect to server: {e}");
-                            exit(-1)
-                        }
-                    }
-                });
+            if !tui && open_browser.unwrap_or(true) {
+                open_browser_when_ready(&host, port);
             }
             let config = ServerConfig {
                 directory,
+                db,
                 host,
                 port,
                 session_started_at: Timestamp::now(),
@@ -145,15 +247,86 @@ pub async fn entrypoint() -> Fallible<()> {
                 shuffle: true,
                 answer_controls,
                 bury_siblings: bury_siblings.unwrap_or(true),
+                resume,
+            };
+            if tui {
+                start_tui(config)
+            } else {
+                start_server(config).await
+            }
+        }
+        Command::Browse {
+            directory,
+            db,
+            host,
+            port,
+            open_browser,
+        } => {
+            if open_browser.unwrap_or(true) {
+                open_browser_when_ready(&host, port);
+            }
+            let config = BrowseConfig {
+                directory,
+                db,
+                host,
+                port,
             };
-            start_server(config).await
+            start_browse(config).await
         }
-        Command::Check { directory } => check_collection(directory),
-        Command::Stats { directory, format } => print_stats(directory, format),
+        Command::Card {
+            card,
+            directory,
+            db,
+            json,
+        } => print_card(card, directory, db, json),
+        Command::Check {
+            directory,
+            db,
+            format,
+        } => check_collection(directory, db, format),
+        Command::Fmt {
+            directory,
+            db,
+            check,
+        } => format_collection(directory, db, check),
+        Command::Lint {
+            directory,
+            db,
+            format,
+        } => lint_collection(directory, db, format),
+        Command::Lsp { directory, db } => start_language_server(directory, db),
+        Command::Stats {
+            directory,
+            db,
+            format,
+            output,
+            windows,
+        } => print_stats(directory, db, format, output, windows),
         Command::Orphans { command } => match command {
-            OrphanCommand::List { directory } => list_orphans(directory),
-            OrphanCommand::Delete { directory } => delete_orphans(directory),
+            OrphanCommand::List { directory, db } => list_orphans(directory, db),
+            OrphanCommand::Delete { directory, db } => delete_orphans(directory, db),
         },
-        Command::Export { directory, output } => export_collection(directory, output),
+        Command::Export {
+            directory,
+            db,
+            output,
+        } => export_collection(directory, db, output),
+        Command::Rehash { directory, db } => rehash_collection(directory, db),
     }
 }
+
+/// Start a separate task to open the browser once the server is up.
+fn open_browser_when_ready(host: &str, port: u16) {
+    let host = host.to_string();
+    spawn(async move {
+        match wait_for_server(&host, port).await {
+            Ok(_) => {
+                let _ = open::that(format!("http://{host}:{port}/"));
+            }
+            Err(e) => {
+                eprintln!("Failed to connect to server: {e}");
+                exit(-1)
+            }
+        }
+    });
+}
@@ -0,0 +1,146 @@
+// Copyright 2025 Fernando Borretti
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod page;
+pub mod server;
+
+#[cfg(test)]
+mod tests {
+    use portpicker::pick_unused_port;
+    use reqwest::StatusCode;
+    use tokio::spawn;
+
+    use crate::cmd::browse::server::BrowseConfig;
+    use crate::cmd::browse::server::start_browse;
+    use crate::collection::Collection;
+    use crate::db::ReviewRecord;
+    use crate::error::Fallible;
+    use crate::fsrs::Grade;
+    use crate::helper::create_tmp_copy_of_test_directory;
+    use crate::helper::save_session;
+    use crate::types::card::CardType;
+    use crate::types::performance::Performance;
+    use crate::types::performance::update_performance;
+    use crate::types::timestamp::Timestamp;
+    use crate::utils::wait_for_server;
+
+    const TEST_HOST: &str = "127.0.0.1";
+
+    #[tokio::test]
+    async fn test_browse() -> Fallible<()> {
+        let directory = create_tmp_copy_of_test_directory()?;
+
+        // Review the basic card, so it has a history.
+        let basic = {
+            let mut coll = Collection::new(Some(directory.clone()), None)?;
+            let now = Timestamp::now();
+            coll.db
+                .insert_cards(coll.cards.iter().map(|c| c.hash()), now)?;
+            let card = coll
+                .cards
+                .iter()
+                .find(|c| c.card_type() == CardType::Basic)
+                .unwrap()
+                .clone();
+            let performance = update_performance(Performance::New, Grade::Good, now);
+            let review = ReviewRecord {
+                card_hash: card.hash(),
+                reviewed_at: now,
+                grade: Grade::Good,
+                stability: performance.stability,
+                difficulty: performance.difficulty,
+                interval_raw: performance.interval_raw,
+                interval_days: performance.interval_days,
+                due_date: performance.due_date,
+                duration_ms: Some(2500),
+            };
+            save_session(
+                &mut coll.db,
+                now,
+                now,
+                &[review],
+                &[(card.hash(), Performance::Reviewed(performance))],
+            )?;
+            card
+        };
+
+        let port = pick_unused_port().unwrap();
+        let config = BrowseConfig {
+            directory: Some(directory),
+            db: None,
+            host: TEST_HOST.to_string(),
+            port,
+        };
+        spawn(async move { start_browse(config).await });
+        wait_for_server(TEST_HOST, port).await?;
+        let url = format!("http://{TEST_HOST}:{port}");
+
+        // Both sides of every card are shown.
+        let response = reqwest::get(format!("{url}/")).await?;
+        assert!(response.status().is_success());
+        let html = response.text().await?;
+        assert!(html.contains("Showing 1–2 of 2 cards."));
+        assert!(html.contains("FOO"));
+        assert!(html.contains("BAR"));
+        assert!(html.contains("baz <span class='cloze'>.............</span>"));
+        assert!(html.contains("baz <span class='cloze-reveal'>quux</span>"));
+        assert!(html.contains("Deck.md:1"));
+        assert!(html.contains("Deck.md:4"));
+
+        // Filter by type and state.
+        let html = reqwest::get(format!("{url}/?type=cloze"))
+            .await?
+            .text()
+            .await?;
+        assert!(html.contains("Showing 1–1 of 1 cards."));
+        assert!(!html.contains("FOO"));
+        let html = reqwest::get(format!("{url}/?state=scheduled&deck=Deck&type="))
+            .await?
+            .text()
+            .await?;
+        assert!(html.contains("FOO"));
+        assert!(!html.contains("quux"));
+        let html = reqwest::get(format!("{url}/?deck=Nope"))
+            .await?
+            .text()
+            .await?;
+        assert!(html.contains("No cards match."));
+
+        // The card page shows the card's schedule and history.
+        let response = reqwest::get(format!("{url}/card/{}", basic.hash())).await?;
+        assert!(response.status().is_success());
+        let html = response.text().await?;
+        assert!(html.contains(&basic.hash().to_string()));
+        assert!(html.contains("scheduled"));
+        assert!(html.contains("Review History"));
+        assert!(html.contains("<td>good</td>"));
+        assert!(html.contains("<td>2.5</td>"));
+
+        // Unknown cards are not found.
+        let response = reqwest::get(format!("{url}/card/{}", "0".repeat(64))).await?;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = reqwest::get(format!("{url}/card/derp")).await?;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        // Assets.
+        let response = reqwest::get(format!("{url}/browse.css")).await?;
+        assert!(response.status().is_success());
+        assert_eq!(response.headers().get("content-type").unwrap(), "text/css");
+        let response = reqwest::get(format!("{url}/script.js")).await?;
+        assert!(response.status().is_success());
+        let response = reqwest::get(format!("{url}/file/foo.jpg")).await?;
+        assert!(response.status().is_success());
+        Ok(())
+    }
+}
@@ -0,0 +1,423 @@
+// Copyright 2025 Fernando Borretti
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use form_urlencoded::Serializer;
+use form_urlencoded::byte_serialize;
+use maud::Markup;
+use maud::html;
+use serde::Deserialize;
+
+use crate::cmd::browse::server::BrowseState;
+use crate::cmd::drill::get::card_render_config;
+use crate::db::ReviewRow;
+use crate::error::Fallible;
+use crate::types::card::Card;
+use crate::types::card::CardType;
+use crate::types::card_hash::CardHash;
+use crate::types::date::Date;
+use crate::types::performance::Performance;
+use crate::types::performance::ReviewedPerformance;
+
+/// The number of cards on each page of the list.
+const PAGE_SIZE: usize = 50;
+
+const CARD_TYPES: [CardType; 3] = [CardType::Basic, CardType::Cloze, CardType::List];
+
+const CARD_STATES: [CardState; 4] = [
+    CardState::New,
+    CardState::Due,
+    CardState::Scheduled,
+    CardState::Suspended,
+];
+
+/// Where a card is in its schedule.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CardState {
+    /// The card has never been reviewed.
+    New,
+    /// The card is due today, or overdue.
+    Due,
+    /// The card is due after today.
+    Scheduled,
+    /// The card is suspended, and is not drilled.
+    Suspended,
+}
+
+impl CardState {
+    pub fn new(performance: Option<&Performance>, suspended: bool, today: Date) -> Self {
+        if suspended {
+            return CardState::Suspended;
+        }
+        match performance {
+            None | Some(Performance::New) => CardState::New,
+            Some(Performance::Reviewed(p)) if p.due_date <= today => CardState::Due,
+            Some(Performance::Reviewed(_)) => CardState::Scheduled,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CardState::New => "new",
+            CardState::Due => "due",
+            CardState::Scheduled => "scheduled",
+            CardState::Suspended => "suspended",
+        }
+    }
+}
+
+/// The filters of the card list, from the query string. Empty values match
+/// everything.
+#[derive(Default, Deserialize)]
+pub struct Filter {
+    pub deck: Option<String>,
+    #[serde(rename = "type")]
+    pub card_type: Option<String>,
+    pub state: Option<String>,
+    pub page: Option<usize>,
+}
+
+impl Filter {
+    fn matches(&self, card: &Card, state: CardState) -> bool {
+        let matches = |filter: &Option<String>, value: &str| match filter.as_deref() {
+            None | Some("") => true,
+            Some(filter) => filter == value,
+        };
+        matches(&self.deck, card.deck_name())
+            && matches(&self.card_type, card.card_type().as_str())
+            && matches(&self.state, state.as_str())
+    }
+
+    /// The query string of this filter, on the given page.
+    fn query(&self, page: usize) -> String {
+        let mut query = Serializer::new(String::new());
+        for (key, value) in [
+            ("deck", &self.deck),
+            ("type", &self.card_type),
+            ("state", &self.state),
+        ] {
+            if let Some(value) = value.as_deref().filter(|value| !value.is_empty()) {
+                query.append_pair(key, value);
+            }
+        }
+        query.append_pair("page", &page.to_string());
+        query.finish()
+    }
+}
+
+/// A card in the collection, with its schedule.
+struct Entry {
+    card: Card,
+    state: CardState,
+    performance: Option<Performance>,
+}
+
+pub fn render_list_page(state: &BrowseState, filter: &Filter) -> Fallible<Markup> {
+    let cards = state.cards()?;
+    let (performance, suspended): (HashMap<CardHash, Performance>, HashSet<CardHash>) = {
+        let db = state.db.lock().unwrap();
+        (db.all_card_performance()?, db.suspended_card_hashes()?)
+    };
+    let today = Date::today();
+    let decks: BTreeSet<String> = cards.iter().map(|c| c.deck_name().clone()).collect();
+    let entries: Vec<Entry> = cards
+        .into_iter()
+        .map(|card| {
+            let performance = performance.get(&card.hash()).copied();
+            let state = CardState::new(
+                performance.as_ref(),
+                suspended.contains(&card.hash()),
+                today,
+            );
+            Entry {
+                card,
+                state,
+                performance,
+            }
+        })
+        .filter(|entry| filter.matches(&entry.card, entry.state))
+        .collect();
+
+    let total = entries.len();
+    let pages = total.div_ceil(PAGE_SIZE).max(1);
+    let page = filter.page.unwrap_or(1).clamp(1, pages);
+    let start = (page - 1) * PAGE_SIZE;
+    let shown: Vec<&Entry> = entries.iter().skip(start).take(PAGE_SIZE).collect();
+
+    let mut rendered = Vec::new();
+    for entry in shown {
+        rendered.push(render_entry(state, entry)?);
+    }
+
+    let selected = |filter: &Option<String>, value: &str| filter.as_deref() == Some(value);
+    Ok(html! {
+        div.root.browse {
+            div.header {
+                h1 { a href="/" { "hashcards" } }
+                form.filters action="/" method="get" {
+                    select name="deck" title="Deck" {
+                        option value="" { "All decks" }
+                        @for deck in &decks {
+                            option value=(deck) selected[selected(&filter.deck, deck)] { (deck) }
+                        }
+                    }
+                    select name="type" title="Type" {
+                        option value="" { "All types" }
+                        @for card_type in CARD_TYPES {
+                            option value=(card_type.as_str()) selected[selected(&filter.card_type, card_type.as_str())] {
+                                (card_type.as_str())
+                            }
+                        }
+                    }
+                    select name="state" title="State" {
+                        option value="" { "All states" }
+                        @for card_state in CARD_STATES {
+                            option value=(card_state.as_str()) selected[selected(&filter.state, card_state.as_str())] {
+                                (card_state.as_str())
+                            }
+                        }
+                    }
+                    input type="submit" value="Filter";
+                }
+            }
+            div.summary {
+                @if total == 0 {
+                    "No cards match."
+                } @else {
+                    "Showing " (start + 1) "–" (start + rendered.len()) " of " (total) " cards."
+                }
+            }
+            div.card-container {
+                @for entry in rendered {
+                    (entry)
+                }
+            }
+            @if pages > 1 {
+                div.pager {
+                    @if page > 1 {
+                        a href=(format!("/?{}", filter.query(page - 1))) { "Previous" }
+                    }
+                    span { "Page " (page) " of " (pages) }
+                    @if page < pages {
+                        a href=(format!("/?{}", filter.query(page + 1))) { "Next" }
+                    }
+                }
+            }
+        }
+    })
+}
+
+fn render_entry(state: &BrowseState, entry: &Entry) -> Fallible<Markup> {
+    let card = &entry.card;
+    let due_date = match entry.performance {
+        Some(Performance::Reviewed(p)) => Some(p.due_date),
+        _ => None,
+    };
+    Ok(html! {
+        div.card {
+            div.card-header {
+                h1 { (card.deck_name()) }
+                div.card-meta {
+                    span { (location(state, card)?) }
+                    span { (card.card_type().as_str()) }
+                    span { (entry.state.as_str()) }
+                    @if let Some(due_date) = due_date {
+                        span { "due " (due_date) }
+                    }
+                    a href=(format!("/card/{}", card.hash())) { "Details" }
+                }
+            }
+            (render_card(state, card)?)
+        }
+    })
+}
+
+/// Returns `None` if there is no card with the given hash.
+pub fn render_card_page(state: &BrowseState, hash: CardHash) -> Fallible<Option<Markup>> {
+    let Some(card) = state.cards()?.into_iter().find(|card| card.hash() == hash) else {
+        return Ok(None);
+    };
+    let (performance, suspended, reviews): (Option<Performance>, bool, Vec<ReviewRow>) = {
+        let db = state.db.lock().unwrap();
+        (
+            db.get_card_performance_opt(hash)?,
+            db.is_suspended(hash)?,
+            db.get_reviews_for_card(hash)?,
+        )
+    };
+    let card_state = CardState::new(performance.as_ref(), suspended, Date::today());
+    let reviewed: Option<ReviewedPerformance> = match performance {
+        Some(Performance::Reviewed(p)) => Some(p),
+        _ => None,
+    };
+    Ok(Some(html! {
+        div.root.browse {
+            div.header {
+                h1 { a href="/" { "hashcards" } }
+                a href=(format!("/?deck={}", encode(card.deck_name()))) { "All cards in " (card.deck_name()) }
+            }
+            div.card-container {
+                div.card {
+                    div.card-header {
+                        h1 { (card.deck_name()) }
+                    }
+                    (render_card(state, &card)?)
+                }
+                div.details {
+                    h2 { "Card" }
+                    table {
+                        tbody {
+                            tr { td.key { "Hash" } td.val { code { (card.hash()) } } }
+                            @if let Some(family) = card.family_hash() {
+                                tr { td.key { "Family" } td.val { code { (family) } } }
+                            }
+                            tr { td.key { "Location" } td.val { (location(state, &card)?) } }
+                            tr { td.key { "Type" } td.val { (card.card_type().as_str()) } }
+                            tr { td.key { "State" } td.val { (card_state.as_str()) } }
+                            @if let Some(p) = reviewed {
+                                tr { td.key { "Due" } td.val { (p.due_date) } }
+                                tr { td.key { "Last Reviewed" } td.val { (p.last_reviewed_at) } }
+                                tr { td.key { "Stability" } td.val { (format!("{:.2}", p.stability)) } }
+                                tr { td.key { "Difficulty" } td.val { (format!("{:.2}", p.difficulty)) } }
+                                tr { td.key { "Interval (days)" } td.val { (p.interval_days) } }
+                                tr { td.key { "Reviews" } td.val { (p.review_count) } }
+                            }
+                        }
+                    }
+                    h2 { "Review History" }
+                    @if reviews.is_empty() {
+                        p { "This card has not been reviewed." }
+                    } @else {
+                        table.history {
+                            thead {
+                                tr {
+                                    th { "Reviewed" }
+                                    th { "Grade" }
+                                    th { "Stability" }
+                                    th { "Difficulty" }
+                                    th { "Interval (days)" }
+                                    th { "Due" }
+                                    th { "Time (s)" }
+                                }
+                            }
+                            tbody {
+                                @for review in &reviews {
+                                    tr {
+                                        td { (review.data.reviewed_at) }
+                                        td { (review.data.grade.as_str()) }
+                                        td { (format!("{:.2}", review.data.stability)) }
+                                        td { (format!("{:.2}", review.data.difficulty)) }
+                                        td { (review.data.interval_days) }
+                                        td { (review.data.due_date) }
+                                        td {
+                                            @if let Some(ms) = review.data.duration_ms {
+                                                (format!("{:.1}", ms as f64 / 1000.0))
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }))
+}
+
+/// Render both sides of a card, and its note.
+fn render_card(state: &BrowseState, card: &Card) -> Fallible<Markup> {
+    let config = card_render_config(&state.directory, state.port, card)?;
+    Ok(html! {
+        div.card-content {
+            div .question .rich-text {
+                (card.html_front(&config)?)
+            }
+            div .answer .rich-text {
+                (card.html_back(&config)?)
+            }
+            @if let Some(note) = card.html_note(&config)? {
+                div .note .rich-text {
+                    (note)
+                }
+            }
+        }
+    })
+}
+
+/// Encode a value for a query string.
+fn encode(value: &str) -> String {
+    byte_serialize(value.as_bytes()).collect()
+}
+
+/// The card's deck file, relative to the collection, and its first line.
+fn location(state: &BrowseState, card: &Card) -> Fallible<String> {
+    let path = card.relative_file_path(&state.directory)?;
+    Ok(format!("{}:{}", path.display(), card.range().0 + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::timestamp::Timestamp;
+
+    #[test]
+    fn test_card_state() {
+        let now = Timestamp::now();
+        let today = now.date();
+        let reviewed = |due_date: Date| {
+            Performance::Reviewed(ReviewedPerformance {
+                last_reviewed_at: now,
+                stability: 1.0,
+                difficulty: 1.0,
+                interval_raw: 1.0,
+                interval_days: 1,
+                due_date,
+                review_count: 1,
+            })
+        };
+        let tomorrow = Date::new(today.into_inner().succ_opt().unwrap());
+        assert_eq!(CardState::new(None, false, today), CardState::New);
+        assert_eq!(
+            CardState::new(Some(&Performance::New), false, today),
+            CardState::New
+        );
+        assert_eq!(
+            CardState::new(Some(&reviewed(today)), false, today),
+            CardState::Due
+        );
+        assert_eq!(
+            CardState::new(Some(&reviewed(tomorrow)), false, today),
+            CardState::Scheduled
+        );
+        assert_eq!(
+            CardState::new(Some(&reviewed(today)), true, today),
+            CardState::Suspended
+        );
+    }
+
+    #[test]
+    fn test_filter_query() {
+        let filter = Filter {
+            deck: Some("Rust & C".to_string()),
+            card_type: Some(String::new()),
+            state: Some("due".to_string()),
+            page: Some(1),
+        };
+        assert_eq!(filter.query(2), "deck=Rust+%26+C&state=due&page=2");
+    }
+}
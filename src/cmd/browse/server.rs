@@ -0,0 +1,191 @@
+// Copyright 2025 Fernando Borretti
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use axum::Router;
+use axum::extract::Path;
+use axum::extract::Query;
+use axum::extract::State;
+use axum::http::HeaderName;
+use axum::http::StatusCode;
+use axum::http::header::CACHE_CONTROL;
+use axum::http::header::CONTENT_TYPE;
+use axum::response::Html;
+use axum::routing::get;
+use maud::Markup;
+use maud::html;
+use tokio::net::TcpListener;
+use tokio::signal;
+
+use crate::cmd::browse::page::Filter;
+use crate::cmd::browse::page::render_card_page;
+use crate::cmd::browse::page::render_list_page;
+use crate::cmd::drill::katex::KATEX_CSS_URL;
+use crate::cmd::drill::katex::KATEX_JS_URL;
+use crate::cmd::drill::katex::KATEX_MHCHEM_JS_URL;
+use crate::cmd::drill::katex::katex_css_handler;
+use crate::cmd::drill::katex::katex_font_handler;
+use crate::cmd::drill::katex::katex_js_handler;
+use crate::cmd::drill::katex::katex_mhchem_js_handler;
+use crate::cmd::drill::server::media_file;
+use crate::cmd::drill::server::script;
+use crate::cmd::drill::server::style_handler;
+use crate::cmd::drill::template::page_with_stylesheets;
+use crate::collection::collection_directory;
+use crate::collection::load_cards;
+use crate::collection::load_macros;
+use crate::collection::open_database;
+use crate::db::Database;
+use crate::error::Fallible;
+use crate::types::card::Card;
+use crate::types::card_hash::CardHash;
+use crate::utils::CACHE_CONTROL_IMMUTABLE;
+
+/// The browser's styles, on top of the drill UI's.
+const BROWSE_CSS_URL: &str = "/browse.css";
+
+pub struct BrowseConfig {
+    pub directory: Option<String>,
+    pub db: Option<String>,
+    pub host: String,
+    pub port: u16,
+}
+
+/// The state of the browser. The collection is loaded on every request, so
+/// that edits to the deck files show up when the page is reloaded.
+#[derive(Clone)]
+pub struct BrowseState {
+    pub directory: PathBuf,
+    pub port: u16,
+    pub db: Arc<Mutex<Database>>,
+}
+
+impl BrowseState {
+    /// Parse the cards in the collection.
+    pub fn cards(&self) -> Fallible<Vec<Card>> {
+        let mut db = self.db.lock().unwrap();
+        load_cards(&self.directory, &mut db)
+    }
+}
+
+pub async fn start_browse(config: BrowseConfig) -> Fallible<()> {
+    let directory: PathBuf = collection_directory(config.directory)?;
+    let db: Database = open_database(&directory, config.db)?;
+    let state = BrowseState {
+        directory,
+        port: config.port,
+        db: Arc::new(Mutex::new(db)),
+    };
+    // Fail early if the collection can't be loaded.
+    state.cards()?;
+
+    let app = Router::new();
+    let app = app.route("/", get(list_handler));
+    let app = app.route("/card/{hash}", get(card_handler));
+    let app = app.route("/script.js", get(script_handler));
+    let app = app.route("/style.css", get(style_handler));
+    let app = app.route(BROWSE_CSS_URL, get(browse_style_handler));
+    let app = app.route(KATEX_CSS_URL, get(katex_css_handler));
+    let app = app.route(KATEX_JS_URL, get(katex_js_handler));
+    let app = app.route(KATEX_MHCHEM_JS_URL, get(katex_mhchem_js_handler));
+    let app = app.route("/katex/fonts/{*path}", get(katex_font_handler));
+    let app = app.route("/file/{*path}", get(file_handler));
+    let app = app.fallback(not_found_handler);
+    let app = app.with_state(state);
+    let bind = format!("{}:{}", config.host, config.port);
+
+    log::debug!("Starting server on {bind}");
+    let listener = TcpListener::bind(bind).await?;
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async {
+            signal::ctrl_c()
+                .await
+                .expect("failed to install Ctrl+C handler");
+        })
+        .await?;
+    Ok(())
+}
+
+async fn list_handler(
+    State(state): State<BrowseState>,
+    Query(filter): Query<Filter>,
+) -> (StatusCode, Html<String>) {
+    respond(render_list_page(&state, &filter))
+}
+
+async fn card_handler(
+    State(state): State<BrowseState>,
+    Path(hash): Path<String>,
+) -> (StatusCode, Html<String>) {
+    let Ok(hash) = CardHash::from_hex(&hash) else {
+        return not_found_handler().await;
+    };
+    match render_card_page(&state, hash) {
+        Ok(None) => not_found_handler().await,
+        Ok(Some(body)) => respond(Ok(body)),
+        Err(e) => respond(Err(e)),
+    }
+}
+
+/// Wrap a page body in the page template, or show the error.
+fn respond(body: Fallible<Markup>) -> (StatusCode, Html<String>) {
+    let body = match body {
+        Ok(body) => body,
+        Err(e) => html! {
+            div.error {
+                h1 { "Error" }
+                p { (e) }
+            }
+        },
+    };
+    let html = page_with_stylesheets(body, &[BROWSE_CSS_URL]);
+    (StatusCode::OK, Html(html.into_string()))
+}
+
+async fn script_handler(
+    State(state): State<BrowseState>,
+) -> (StatusCode, [(HeaderName, &'static str); 1], String) {
+    let macros = load_macros(&state.directory).unwrap_or_default();
+    (
+        StatusCode::OK,
+        [(CONTENT_TYPE, "text/javascript")],
+        script(&macros),
+    )
+}
+
+async fn browse_style_handler() -> (StatusCode, [(HeaderName, &'static str); 2], &'static [u8]) {
+    let bytes = include_bytes!("style.css");
+    (
+        StatusCode::OK,
+        [
+            (CONTENT_TYPE, "text/css"),
+            (CACHE_CONTROL, CACHE_CONTROL_IMMUTABLE),
+        ],
+        bytes,
+    )
+}
+
+async fn file_handler(
+    State(state): State<BrowseState>,
+    Path(path): Path<String>,
+) -> (StatusCode, [(HeaderName, &'static str); 1], Vec<u8>) {
+    media_file(state.directory.clone(), &path).await
+}
+
+async fn not_found_handler() -> (StatusCode, Html<String>) {
+    (StatusCode::NOT_FOUND, Html("Not Found".to_string()))
+}
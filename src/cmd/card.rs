@@ -0,0 +1,412 @@
+// Copyright 2025 Fernando Borretti
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::Path;
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use crate::cmd::export::CardExport;
+use crate::cmd::export::ReviewExport;
+use crate::cmd::export::export_card;
+use crate::cmd::export::export_review;
+use crate::collection::Collection;
+use crate::db::ReviewRow;
+use crate::error::Fallible;
+use crate::error::fail;
+use crate::fsrs::Recall;
+use crate::fsrs::Stability;
+use crate::fsrs::retrievability;
+use crate::types::card::Card;
+use crate::types::card::CardContent;
+use crate::types::card::cards_at_line;
+use crate::types::date::Date;
+use crate::types::performance::Performance;
+use crate::types::performance::ReviewedPerformance;
+use crate::types::timestamp::Timestamp;
+
+const TS_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CardReport {
+    cards: Vec<CardInfo>,
+}
+
+/// A card, with its schedule and review history.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CardInfo {
+    #[serde(flatten)]
+    card: CardExport,
+    suspended: bool,
+    /// The probability of recalling the card today, if it has been reviewed.
+    retrievability: Option<Recall>,
+    reviews: Vec<ReviewInfo>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ReviewInfo {
+    #[serde(flatten)]
+    review: ReviewExport,
+    /// The days since the previous review, if there was one.
+    elapsed_days: Option<i64>,
+    /// The probability of recall predicted at the time of the review, from the
+    /// stability after the previous review.
+    retrievability: Option<Recall>,
+}
+
+/// Print a card, given by its hash (or a prefix of it) or its location as
+/// `file:line`. If several cards are at the location, all of them are
+/// printed.
+pub fn print_card(
+    query: String,
+    directory: Option<String>,
+    db: Option<String>,
+    json: bool,
+) -> Fallible<()> {
+    let coll = Collection::new(directory, db)?;
+    let cards: Vec<Card> = find_cards(&coll, &query)?;
+    let today = Timestamp::now().date();
+    let mut infos = Vec::new();
+    for card in cards.iter() {
+        infos.push(card_info(&coll, card, today)?);
+    }
+    if json {
+        let report = CardReport { cards: infos };
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        let texts: Vec<String> = cards
+            .iter()
+            .zip(infos.iter())
+            .map(|(card, info)| card_text(&coll.directory, card, info))
+            .collect::<Fallible<_>>()?;
+        println!("{}", texts.join("\n\n"));
+    }
+    Ok(())
+}
+
+/// Find the cards the query refers to.
+fn find_cards(coll: &Collection, query: &str) -> Fallible<Vec<Card>> {
+    let is_hash = query.len() >= 4 && query.chars().all(|c| c.is_ascii_hexdigit());
+    if is_hash {
+        let prefix = query.to_lowercase();
+        let cards: Vec<Card> = coll
+            .cards
+            .iter()
+            .filter(|card| card.hash().to_hex().starts_with(&prefix))
+            .cloned()
+            .collect();
+        return match cards.len() {
+            0 => fail(format!("no card with the hash {query}")),
+            1 => Ok(cards),
+            n => fail(format!(
+                "the hash {query} matches {n} cards, use a longer prefix"
+            )),
+        };
+    }
+    let Some((file, line)) = query.rsplit_once(':') else {
+        return fail(format!(
+            "expected a card hash or a location like Deck.md:12, got {query}"
+        ));
+    };
+    let line: usize = match line.parse() {
+        Ok(line) if line > 0 => line,
+        _ => return fail(format!("invalid line number: {line}")),
+    };
+    let path = resolve_path(&coll.directory, file)?;
+    let cards = cards_at_line(coll.cards.clone(), &path, line - 1);
+    if cards.is_empty() {
+        return fail(format!("no card at {query}"));
+    }
+    Ok(cards)
+}
+
+/// Resolve a deck file path, relative to the current directory or else to
+/// the collection.
+fn resolve_path(directory: &Path, file: &str) -> Fallible<PathBuf> {
+    let candidates = [PathBuf::from(file), directory.join(file)];
+    for candidate in candidates {
+        if candidate.is_file() {
+            return Ok(candidate.canonicalize()?);
+        }
+    }
+    fail(format!("no such file: {file}"))
+}
+
+fn card_info(coll: &Collection, card: &Card, today: Date) -> Fallible<CardInfo> {
+    let performance = coll.db.get_card_performance_opt(card.hash())?;
+    let retrievability = match performance {
+        Some(Performance::Reviewed(p)) => Some(current_retrievability(&p, today)),
+        _ => None,
+    };
+    let rows: Vec<ReviewRow> = coll.db.get_reviews_for_card(card.hash())?;
+    Ok(CardInfo {
+        card: export_card(coll, card)?,
+        suspended: coll.db.is_suspended(card.hash())?,
+        retrievability,
+        reviews: review_infos(rows),
+    })
+}
+
+/// The probability of recalling a card on the given day.
+fn current_retrievability(p: &ReviewedPerformance, today: Date) -> Recall {
+    let elapsed = (today.into_inner() - p.last_reviewed_at.date().into_inner()).num_days();
+    retrievability(elapsed.max(0) as f64, p.stability)
+}
+
+/// Replay a card's reviews, to find the retrievability predicted at each.
+fn review_infos(rows: Vec<ReviewRow>) -> Vec<ReviewInfo> {
+    let mut infos = Vec::new();
+    let mut previous: Option<(Date, Stability)> = None;
+    for row in rows {
+        let date = row.data.reviewed_at.date();
+        let (elapsed_days, retrievability) = match previous {
+            Some((last, stability)) => {
+                let elapsed = (date.into_inner() - last.into_inner()).num_days();
+                (
+                    Some(elapsed),
+                    Some(retrievability(elapsed as f64, stability)),
+                )
+            }
+            None => (None, None),
+        };
+        previous = Some((date, row.data.stability));
+        infos.push(ReviewInfo {
+            review: export_review(row),
+            elapsed_days,
+            retrievability,
+        });
+    }
+    infos
+}
+
+fn card_text(directory: &Path, card: &Card, info: &CardInfo) -> Fallible<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let location = format!(
+        "{}:{}",
+        card.relative_file_path(directory)?.display(),
+        card.range().0 + 1
+    );
+    lines.push(format!("Card {}", card.hash()));
+    lines.push(format!("Deck:           {}", card.deck_name()));
+    lines.push(format!("Location:       {location}"));
+    lines.push(format!("Type:           {}", card.card_type().as_str()));
+    if let Some(family) = card.family_hash() {
+        lines.push(format!("Family:         {family}"));
+    }
+    lines.push(String::new());
+    for (label, text) in content_sections(card) {
+        lines.push(format!("{label}:"));
+        for line in text.lines() {
+            lines.push(format!("  {line}"));
+        }
+    }
+    lines.push(String::new());
+    if info.suspended {
+        lines.push("Suspended.".to_string());
+    }
+    match &info.card.performance {
+        None => lines.push("New: never reviewed.".to_string()),
+        Some(p) => {
+            lines.push(format!("Due:            {}", p.due_date));
+            lines.push(format!(
+                "Last reviewed:  {}",
+                p.last_reviewed_at.into_inner().format(TS_FORMAT)
+            ));
+            lines.push(format!("Stability:      {:.2} days", p.stability));
+            lines.push(format!("Difficulty:     {:.2}", p.difficulty));
+            lines.push(format!(
+                "Interval:       {} days ({:.2} before rounding)",
+                p.interval_days, p.interval_raw
+            ));
+            if let Some(r) = info.retrievability {
+                lines.push(format!("Retrievability: {:.1}%", r * 100.0));
+            }
+            lines.push(format!("Reviews:        {}", p.review_count));
+        }
+    }
+    if !info.reviews.is_empty() {
+        lines.push(String::new());
+        lines.push(format!(
+            "{:<19}  {:<6}  {:>7}  {:>6}  {:>9}  {:>10}  {:>8}  {:<10}  {:>6}",
+            "Reviewed",
+            "Grade",
+            "Elapsed",
+            "R",
+            "Stability",
+            "Difficulty",
+            "Interval",
+            "Due",
+            "Time"
+        ));
+        for review in &info.reviews {
+            let r = &review.review;
+            let elapsed = review
+                .elapsed_days
+                .map(|d| format!("{d}d"))
+                .unwrap_or_default();
+            let recall = review
+                .retrievability
+                .map(|r| format!("{:.1}%", r * 100.0))
+                .unwrap_or_default();
+            let time = r
+                .duration_ms
+                .map(|ms| format!("{:.1}s", ms as f64 / 1000.0))
+                .unwrap_or_default();
+            lines.push(format!(
+                "{:<19}  {:<6}  {:>7}  {:>6}  {:>9.2}  {:>10.2}  {:>7}d  {:<10}  {:>6}",
+                r.reviewed_at.into_inner().format(TS_FORMAT),
+                r.grade.as_str(),
+                elapsed,
+                recall,
+                r.stability,
+                r.difficulty,
+                r.interval_days,
+                r.due_date.to_string(),
+                time
+            ));
+        }
+    }
+    Ok(lines.join("\n"))
+}
+
+/// The card's content, as Markdown, in labelled sections.
+fn content_sections(card: &Card) -> Vec<(&'static str, String)> {
+    let mut sections = match card.content() {
+        CardContent::Basic { question, answer } => {
+            vec![("Question", question.clone()), ("Answer", answer.clone())]
+        }
+        CardContent::Cloze { text, start, end } => {
+            let text = format!(
+                "{}[{}]{}",
+                &text[..*start],
+                &text[*start..*end + 1],
+                &text[*end + 1..]
+            );
+            vec![("Text", text)]
+        }
+        CardContent::List {
+            title,
+            items,
+            ordered,
+            index,
+        } => {
+            let items: Vec<String> = items
+                .iter()
+                .enumerate()
+                .map(|(i, item)| {
+                    let marker = if *ordered {
+                        format!("{}.", i + 1)
+                    } else {
+                        "-".to_string()
+                    };
+                    let asked = if Some(i) == *index { "  <-" } else { "" };
+                    format!("{marker} {item}{asked}")
+                })
+                .collect();
+            vec![("Title", title.clone()), ("Items", items.join("\n"))]
+        }
+    };
+    if let Some(note) = card.note() {
+        sections.push(("Note", note.to_string()));
+    }
+    sections
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+    use crate::db::ReviewRecord;
+    use crate::fsrs::Grade;
+    use crate::helper::create_tmp_copy_of_test_directory;
+    use crate::types::card::CardType;
+    use crate::types::card_hash::CardHash;
+
+    #[test]
+    fn test_find_cards() -> Fallible<()> {
+        let directory = create_tmp_copy_of_test_directory()?;
+        let coll = Collection::new(Some(directory.clone()), None)?;
+        let basic = coll
+            .cards
+            .iter()
+            .find(|card| card.card_type() == CardType::Basic)
+            .unwrap()
+            .clone();
+
+        // By hash, or a prefix of it.
+        let hash = basic.hash().to_hex();
+        let found = find_cards(&coll, &hash)?;
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].hash(), basic.hash());
+        let found = find_cards(&coll, &hash[..8].to_uppercase())?;
+        assert_eq!(found[0].hash(), basic.hash());
+        let err = find_cards(&coll, "0000").err().unwrap();
+        assert_eq!(err.to_string(), "error: no card with the hash 0000");
+
+        // By location, relative to the collection or the current directory.
+        let found = find_cards(&coll, "Deck.md:1")?;
+        assert_eq!(found[0].hash(), basic.hash());
+        let found = find_cards(&coll, "Deck.md:3")?;
+        assert_eq!(found[0].hash(), basic.hash());
+        let found = find_cards(&coll, "Deck.md:4")?;
+        assert_eq!(found[0].card_type(), CardType::Cloze);
+        let path = format!("{directory}/Deck.md:2");
+        assert_eq!(find_cards(&coll, &path)?[0].hash(), basic.hash());
+
+        // Errors.
+        assert!(find_cards(&coll, "Deck.md:0").is_err());
+        assert!(find_cards(&coll, "Deck.md:99").is_err());
+        assert!(find_cards(&coll, "Nope.md:1").is_err());
+        assert!(find_cards(&coll, "derp").is_err());
+
+        // Printing doesn't fail.
+        print_card(hash.clone(), Some(directory.clone()), None, false)?;
+        print_card(hash, Some(directory), None, true)?;
+        Ok(())
+    }
+
+    /// The retrievability at each review is predicted from the stability
+    /// after the one before.
+    #[test]
+    fn test_review_infos() {
+        let card_hash = CardHash::hash_bytes(b"a");
+        let first = Timestamp::now();
+        let second = Timestamp::new(first.into_inner() + Duration::days(3));
+        let row = |review_id: i64, reviewed_at: Timestamp, stability: f64| ReviewRow {
+            review_id,
+            data: ReviewRecord {
+                card_hash,
+                reviewed_at,
+                grade: Grade::Good,
+                stability,
+                difficulty: 5.0,
+                interval_raw: stability,
+                interval_days: stability as i64,
+                due_date: reviewed_at.date(),
+                duration_ms: None,
+            },
+        };
+        let infos = review_infos(vec![row(1, first, 3.0), row(2, second, 10.0)]);
+        assert_eq!(infos.len(), 2);
+        assert_eq!(infos[0].elapsed_days, None);
+        assert_eq!(infos[0].retrievability, None);
+        assert_eq!(infos[1].elapsed_days, Some(3));
+        assert_eq!(infos[1].retrievability, Some(retrievability(3.0, 3.0)));
+        assert!((infos[1].retrievability.unwrap() - 0.9).abs() < 0.01);
+    }
+}
@@ -12,30 +12,229 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::collection::Collection;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fs::read_to_string;
+use std::path::Path;
+use std::path::PathBuf;
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::collection::collection_directory;
+use crate::collection::open_existing_database;
+use crate::db::Database;
 use crate::error::Fallible;
+use crate::error::fail;
+use crate::format::DeckFormat;
+use crate::media::validate::find_missing_media;
+use crate::parser::check_deck;
+use crate::parser::reinterpreted_markdown_cards;
+use crate::types::card_hash::HashVersion;
+use crate::types::diagnostic::Diagnostic;
+use crate::types::diagnostic::Severity;
+use crate::walk::deck_files;
+
+#[derive(ValueEnum, Clone)]
+pub enum DiagnosticFormat {
+    /// Compiler-style text output.
+    Text,
+    /// JSON output.
+    Json,
+}
+
+impl Display for DiagnosticFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DiagnosticFormat::Text => write!(f, "text"),
+            DiagnosticFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct DiagnosticReport<'a> {
+    diagnostics: &'a [Diagnostic],
+}
+
+pub fn check_collection(
+    directory: Option<String>,
+    db: Option<String>,
+    format: DiagnosticFormat,
+) -> Fallible<()> {
+    let directory = collection_directory(directory)?;
+    let diagnostics = collect_diagnostics(directory.clone(), db)?;
+    print_diagnostics(&directory, &diagnostics, format)
+}
 
-pub fn check_collection(directory: Option<String>) -> Fallible<()> {
-    let _ = Collection::new(directory)?;
-    println!("ok");
-    Ok(())
+/// Print the diagnostics, with paths relative to the collection directory.
+/// Fails if any of them is an error.
+pub fn print_diagnostics(
+    directory: &Path,
+    diagnostics: &[Diagnostic],
+    format: DiagnosticFormat,
+) -> Fallible<()> {
+    match format {
+        DiagnosticFormat::Text => {
+            for diagnostic in diagnostics {
+                println!("{}", diagnostic.display_relative_to(directory));
+            }
+            if diagnostics.is_empty() {
+                println!("ok");
+            }
+        }
+        DiagnosticFormat::Json => {
+            let report = DiagnosticReport { diagnostics };
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        }
+    }
+    let errors = diagnostics
+        .iter()
+        .filter(|d| d.severity == Severity::Error)
+        .count();
+    match errors {
+        0 => Ok(()),
+        1 => fail("found 1 error."),
+        n => fail(format!("found {n} errors.")),
+    }
+}
+
+/// Collect the parser and media diagnostics for every file in the collection,
+/// sorted by location.
+fn collect_diagnostics(directory: PathBuf, db: Option<String>) -> Fallible<Vec<Diagnostic>> {
+    let db = open_existing_database(&directory, db)?;
+    let hash_version = match &db {
+        Some(db) => db.hash_version()?,
+        None => HashVersion::Raw,
+    };
+    let (cards, mut diagnostics) = check_deck(&directory, hash_version)?;
+    for missing in find_missing_media(&cards, &directory)? {
+        diagnostics.push(missing.to_diagnostic()?);
+    }
+    if let Some(db) = &db {
+        diagnostics.extend(reinterpreted_cards(&directory, db, hash_version)?);
+    }
+    diagnostics.sort();
+    diagnostics.dedup();
+    Ok(diagnostics)
+}
+
+/// Warn about reviewed cards that the note and list card tags split up. Such a
+/// card has a line starting with `N:`, `Extra:`, `L:` or `L+:`, which used to
+/// be part of its text, so it now reads as a different card, with a new hash,
+/// and its review history no longer applies.
+fn reinterpreted_cards(
+    directory: &Path,
+    db: &Database,
+    hash_version: HashVersion,
+) -> Fallible<Vec<Diagnostic>> {
+    let reviewed = db.card_hashes()?;
+    let mut diagnostics = Vec::new();
+    for path in deck_files(directory)? {
+        if DeckFormat::from_path(&path) != Some(DeckFormat::Markdown) {
+            continue;
+        }
+        // Files that can't be read are reported by `check_deck`.
+        let Ok(text) = read_to_string(&path) else {
+            continue;
+        };
+        for card in reinterpreted_markdown_cards(&path, &text, hash_version) {
+            if reviewed.contains(&card.hash()) {
+                diagnostics.push(Diagnostic::new(
+                    Severity::Warning,
+                    card.file_path(),
+                    card.range().0 + 1,
+                    1,
+                    "This card has a line starting with `N:`, `Extra:`, `L:` or `L+:`, which now starts a note or a list card instead of continuing the card. This changed the card's hash, so its review history no longer applies.",
+                ));
+            }
+        }
+    }
+    Ok(diagnostics)
 }
 
 #[cfg(test)]
 mod tests {
-    use super::check_collection;
-    use crate::error::Fallible;
+    use std::fs::write;
+
+    use super::*;
+    use crate::collection::open_database;
     use crate::helper::create_tmp_copy_of_test_directory;
+    use crate::types::timestamp::Timestamp;
 
     #[test]
     fn test_non_existent_directory() {
-        assert!(check_collection(Some("./derpherp".to_string())).is_err());
+        assert!(
+            check_collection(Some("./derpherp".to_string()), None, DiagnosticFormat::Text).is_err()
+        );
     }
 
     #[test]
     fn test_directory() -> Fallible<()> {
         let directory = create_tmp_copy_of_test_directory()?;
-        assert!(check_collection(Some(directory)).is_ok());
+        assert!(check_collection(Some(directory), None, DiagnosticFormat::Text).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_collects_all_diagnostics() -> Fallible<()> {
+        let directory = PathBuf::from(create_tmp_copy_of_test_directory()?);
+        write(
+            directory.join("a.md"),
+            "A: no question\n\nQ: foo\nA: bar\n\nC: no deletions\n\nQ: img\nA: ![](missing.png)",
+        )?;
+        write(
+            directory.join("b.md"),
+            "---\nname = \"B\"\n---\nQ: unfinished",
+        )?;
+        let diagnostics = collect_diagnostics(directory.clone(), None)?;
+        let summary: Vec<(String, usize, usize, Severity)> = diagnostics
+            .iter()
+            .map(|d| {
+                let file = d.file.strip_prefix(&directory).unwrap();
+                (file.display().to_string(), d.line, d.column, d.severity)
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("a.md".to_string(), 1, 1, Severity::Error),
+                ("a.md".to_string(), 6, 1, Severity::Error),
+                ("a.md".to_string(), 9, 8, Severity::Error),
+                ("b.md".to_string(), 4, 1, Severity::Error),
+            ]
+        );
+        assert!(
+            check_collection(
+                Some(directory.display().to_string()),
+                None,
+                DiagnosticFormat::Json
+            )
+            .is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_reinterpreted_cards() -> Fallible<()> {
+        let directory = PathBuf::from(create_tmp_copy_of_test_directory()?);
+        let path = directory.join("a.md");
+        let text = "Q: foo\nA: bar\nN: not a note\n\nQ: baz\nA: quux\nN: a note";
+        write(&path, text)?;
+
+        // Only the first card was reviewed under the old grammar.
+        let cards = reinterpreted_markdown_cards(&path, text, HashVersion::Raw);
+        assert_eq!(cards.len(), 2);
+        let db = open_database(&directory, None)?;
+        db.insert_card(cards[0].hash(), Timestamp::now())?;
+
+        let diagnostics = collect_diagnostics(directory.clone(), None)?;
+        let warnings: Vec<(PathBuf, usize)> = diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Warning)
+            .map(|d| (d.file.clone(), d.line))
+            .collect();
+        assert_eq!(warnings, vec![(path, 1)]);
         Ok(())
     }
 }
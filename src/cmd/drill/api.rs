@@ -0,0 +1,248 @@
+// Copyright 2025 Fernando Borretti
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A JSON API to the drill server, for clients other than the web UI. Routes
+//! are under a versioned prefix, so that the API can change without breaking
+//! existing clients.
+
+use axum::Json;
+use axum::Router;
+use axum::extract::State;
+use axum::http::HeaderMap;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::response::Response;
+use axum::routing::get;
+use axum::routing::post;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::cmd::drill::editor::is_same_origin;
+use crate::cmd::drill::get::render_config;
+use crate::cmd::drill::post::Action;
+use crate::cmd::drill::post::apply_action;
+use crate::cmd::drill::state::MutableState;
+use crate::cmd::drill::state::ServerState;
+use crate::error::ErrorReport;
+use crate::error::Fallible;
+use crate::fsrs::Grade;
+use crate::types::aliases::DeckName;
+use crate::types::card_hash::CardHash;
+use crate::types::timestamp::Timestamp;
+
+pub const API_PREFIX: &str = "/api/v1";
+
+/// The routes of the API, to be nested under [`API_PREFIX`].
+pub fn api_router() -> Router<ServerState> {
+    Router::new()
+        .route("/session", get(session_handler))
+        .route("/card", get(card_handler))
+        .route("/reveal", post(reveal_handler))
+        .route("/grade", post(grade_handler))
+        .route("/undo", post(undo_handler))
+        .route("/end", post(end_handler))
+        .route("/edit", post(edit_handler))
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SessionResponse {
+    total_cards: usize,
+    cards_done: usize,
+    cards_left: usize,
+    review_count: usize,
+    revealed: bool,
+    can_undo: bool,
+    started_at: Timestamp,
+    finished_at: Option<Timestamp>,
+    /// Changes when the collection is reloaded from disk, and the current
+    /// card should be fetched again.
+    revision: u64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CardResponse {
+    hash: CardHash,
+    deck_name: DeckName,
+    kind: &'static str,
+    revealed: bool,
+    /// The rendered HTML of the front of the card.
+    front: String,
+    /// The rendered HTML of the back of the card, once it is revealed.
+    back: Option<String>,
+    /// The rendered HTML of the card's note, once it is revealed.
+    note: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GradeRequest {
+    grade: String,
+    /// How long the user took to answer, in milliseconds.
+    duration_ms: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<ErrorReport> for ApiError {
+    fn from(e: ErrorReport) -> Self {
+        log::error!("error: {e}");
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = ErrorResponse {
+            error: self.message,
+        };
+        (self.status, Json(body)).into_response()
+    }
+}
+
+type ApiResult<T> = Result<Json<T>, ApiError>;
+
+async fn session_handler(State(state): State<ServerState>) -> ApiResult<SessionResponse> {
+    let mutable = state.mutable.lock().unwrap();
+    Ok(Json(session_response(&state, &mutable)))
+}
+
+async fn card_handler(State(state): State<ServerState>) -> ApiResult<CardResponse> {
+    let mutable = state.mutable.lock().unwrap();
+    if mutable.finished_at.is_some() {
+        return Err(ApiError::new(
+            StatusCode::NOT_FOUND,
+            "The session is finished.",
+        ));
+    }
+    Ok(Json(card_response(&state, &mutable)?))
+}
+
+async fn reveal_handler(State(state): State<ServerState>) -> ApiResult<SessionResponse> {
+    act(&state, Action::Reveal, None, "The card can't be revealed.")
+}
+
+async fn grade_handler(
+    State(state): State<ServerState>,
+    Json(request): Json<GradeRequest>,
+) -> ApiResult<SessionResponse> {
+    let grade = Grade::try_from(request.grade)
+        .map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, e.to_string()))?;
+    if request.duration_ms.is_some_and(|duration| duration < 0) {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "The duration can't be negative.",
+        ));
+    }
+    act(
+        &state,
+        Action::from(grade),
+        request.duration_ms,
+        "The card must be revealed before it is graded.",
+    )
+}
+
+async fn undo_handler(State(state): State<ServerState>) -> ApiResult<SessionResponse> {
+    act(&state, Action::Undo, None, "There is nothing to undo.")
+}
+
+async fn end_handler(State(state): State<ServerState>) -> ApiResult<SessionResponse> {
+    act(
+        &state,
+        Action::End,
+        None,
+        "The session is already finished.",
+    )
+}
+
+async fn edit_handler(
+    State(state): State<ServerState>,
+    headers: HeaderMap,
+) -> ApiResult<SessionResponse> {
+    if !is_same_origin(&headers) {
+        return Err(ApiError::new(
+            StatusCode::FORBIDDEN,
+            "The editor can't be opened by a cross-site request.",
+        ));
+    }
+    act(&state, Action::Edit, None, "The session is finished.")
+}
+
+/// Apply the action, and return the session's progress. If the action doesn't
+/// apply in the current state, fail with a conflict and the given message.
+fn act(
+    state: &ServerState,
+    action: Action,
+    duration_ms: Option<i64>,
+    conflict: &str,
+) -> ApiResult<SessionResponse> {
+    if !apply_action(state, action, duration_ms)? {
+        return Err(ApiError::new(StatusCode::CONFLICT, conflict));
+    }
+    let mutable = state.mutable.lock().unwrap();
+    Ok(Json(session_response(state, &mutable)))
+}
+
+fn session_response(state: &ServerState, mutable: &MutableState) -> SessionResponse {
+    SessionResponse {
+        total_cards: state.total_cards,
+        cards_done: state.total_cards - mutable.cards.len(),
+        cards_left: mutable.cards.len(),
+        review_count: mutable.reviews.len(),
+        revealed: mutable.reveal,
+        can_undo: mutable.finished_at.is_none() && !mutable.reviews.is_empty(),
+        started_at: state.session_started_at,
+        finished_at: mutable.finished_at,
+        revision: mutable.revision,
+    }
+}
+
+fn card_response(state: &ServerState, mutable: &MutableState) -> Fallible<CardResponse> {
+    let card = &mutable.cards[0];
+    let config = render_config(state, card)?;
+    let revealed = mutable.reveal;
+    let (back, note) = if revealed {
+        let note = card.html_note(&config)?.map(|note| note.into_string());
+        (Some(card.html_back(&config)?.into_string()), note)
+    } else {
+        (None, None)
+    };
+    Ok(CardResponse {
+        hash: card.hash(),
+        deck_name: card.deck_name().clone(),
+        kind: card.card_type().as_str(),
+        revealed,
+        front: card.html_front(&config)?.into_string(),
+        back,
+        note,
+    })
+}
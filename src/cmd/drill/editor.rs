@@ -0,0 +1,153 @@
+// Copyright 2025 Fernando Borretti
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::env::var;
+use std::path::Path;
+use std::process::Command;
+use std::thread::spawn;
+
+use axum::http::HeaderMap;
+use axum::http::header::HOST;
+use axum::http::header::ORIGIN;
+
+use crate::error::Fallible;
+use crate::error::fail;
+use crate::types::card::Card;
+
+/// The command to open a file at a line. With a template, like
+/// `code -g {file}:{line}`, `{file}` and `{line}` are replaced in each word,
+/// and the file is appended if the template doesn't mention it. Otherwise,
+/// `$VISUAL` or `$EDITOR` is run with `+<line> <file>`, which most terminal
+/// editors understand. Lines start at 1.
+pub fn editor_command(template: Option<&str>, file: &Path, line: usize) -> Fallible<Command> {
+    let editor: Option<String> = var("VISUAL")
+        .ok()
+        .or_else(|| var("EDITOR").ok())
+        .filter(|editor| !editor.trim().is_empty());
+    let template: String = match (template, editor) {
+        (Some(template), _) => template.to_string(),
+        (None, Some(editor)) => format!("{editor} +{{line}} {{file}}"),
+        (None, None) => {
+            return fail(
+                "no editor: set `editor` in hashcards.toml, or the $VISUAL or $EDITOR environment variable.",
+            );
+        }
+    };
+    let file = file.display().to_string();
+    let line = line.to_string();
+    let mut words = template.split_whitespace();
+    let Some(program) = words.next() else {
+        return fail("the `editor` command in hashcards.toml is empty.");
+    };
+    let mut command = Command::new(program);
+    for word in words {
+        command.arg(word.replace("{file}", &file).replace("{line}", &line));
+    }
+    if !template.contains("{file}") {
+        command.arg(&file);
+    }
+    Ok(command)
+}
+
+/// The command to open a card's deck file at the card's first line.
+pub fn card_editor_command(template: Option<&str>, card: &Card) -> Fallible<Command> {
+    editor_command(template, card.file_path(), card.range().0 + 1)
+}
+
+/// Open the card in the editor, without waiting for the editor to exit.
+pub fn open_in_editor(template: Option<&str>, card: &Card) -> Fallible<()> {
+    let mut child = card_editor_command(template, card)?.spawn()?;
+    // Reap the editor when it exits.
+    spawn(move || child.wait());
+    Ok(())
+}
+
+/// Whether a request comes from a page served by the drill server, or from a
+/// client that isn't a browser. Opening the editor runs a command, so another
+/// site the user visits must not be able to trigger it with a cross-site
+/// request. Browsers label those with `Sec-Fetch-Site`, or at least an
+/// `Origin` that doesn't match the server's host.
+pub fn is_same_origin(headers: &HeaderMap) -> bool {
+    if let Some(site) = headers.get("sec-fetch-site") {
+        return matches!(site.to_str(), Ok("same-origin" | "none"));
+    }
+    let Some(origin) = headers.get(ORIGIN) else {
+        return true;
+    };
+    let origin_host = origin
+        .to_str()
+        .ok()
+        .and_then(|origin| origin.split_once("://"))
+        .map(|(_, host)| host);
+    let host = headers.get(HOST).and_then(|host| host.to_str().ok());
+    origin_host.is_some() && origin_host == host
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn args(command: &Command) -> Vec<String> {
+        std::iter::once(command.get_program())
+            .chain(command.get_args())
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn test_editor_command() -> Fallible<()> {
+        let file = PathBuf::from("/notes/Deck.md");
+        let command = editor_command(Some("code -g {file}:{line}"), &file, 12)?;
+        assert_eq!(args(&command), vec!["code", "-g", "/notes/Deck.md:12"]);
+        let command = editor_command(Some("subl"), &file, 12)?;
+        assert_eq!(args(&command), vec!["subl", "/notes/Deck.md"]);
+        assert!(editor_command(Some("  "), &file, 12).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_same_origin() {
+        let headers = |pairs: &[(&'static str, &'static str)]| {
+            let mut headers = HeaderMap::new();
+            for (name, value) in pairs {
+                headers.insert(*name, value.parse().unwrap());
+            }
+            headers
+        };
+        assert!(is_same_origin(&headers(&[])));
+        assert!(is_same_origin(&headers(&[
+            ("host", "localhost:8000"),
+            ("origin", "http://localhost:8000")
+        ])));
+        assert!(!is_same_origin(&headers(&[
+            ("host", "localhost:8000"),
+            ("origin", "https://example.com")
+        ])));
+        assert!(!is_same_origin(&headers(&[
+            ("host", "localhost:8000"),
+            ("origin", "null")
+        ])));
+        assert!(is_same_origin(&headers(&[(
+            "sec-fetch-site",
+            "same-origin"
+        )])));
+        assert!(!is_same_origin(&headers(&[
+            ("sec-fetch-site", "cross-site"),
+            ("host", "localhost:8000"),
+            ("origin", "http://localhost:8000")
+        ])));
+    }
+}
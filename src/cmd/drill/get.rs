@@ -12,6 +12,8 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::path::Path;
+
 use axum::extract::State;
 use axum::http::StatusCode;
 use axum::response::Html;
@@ -56,22 +58,10 @@ fn render_session_page(state: &ServerState, mutable: &MutableState) -> Fallible<
     let undo_disabled = mutable.reviews.is_empty();
     let total_cards = state.total_cards;
     let cards_done = state.total_cards - mutable.cards.len();
-    let percent_done = if total_cards == 0 {
-        100
-    } else {
-        (cards_done * 100) / total_cards
-    };
+    let percent_done = (cards_done * 100).checked_div(total_cards).unwrap_or(100);
     let progress_bar_style = format!("width: {}%;", percent_done);
     let card = mutable.cards[0].clone();
-    let coll_path = state.directory.clone();
-    let deck_path = card.relative_file_path(&coll_path)?;
-    let config = MarkdownRenderConfig {
-        resolver: MediaResolverBuilder::new()
-            .with_collection_path(coll_path)?
-            .with_deck_path(deck_path)?
-            .build()?,
-        port: state.port,
-    };
+    let config = render_config(state, &card)?;
     let card_content = render_card(&card, mutable.reveal, &config)?;
     let card_controls = if mutable.reveal {
         let grades = match state.answer_controls {
@@ -101,7 +91,7 @@ fn render_session_page(state: &ServerState, mutable: &MutableState) -> Fallible<
         }
     };
     let html = html! {
-        div.root {
+        div.root data-revision=(mutable.revision) {
             div.header {
                 form.header-action action="/" method="post" {
                     (undo_button(undo_disabled))
@@ -109,6 +99,9 @@ fn render_session_page(state: &ServerState, mutable: &MutableState) -> Fallible<
                 div.progress-bar {
                     div.progress-fill style=(progress_bar_style) {}
                 }
+                form.header-action action="/" method="post" {
+                    (edit_button())
+                }
                 form.header-action action="/" method="post" {
                     (end_button())
                 }
@@ -131,6 +124,30 @@ fn render_session_page(state: &ServerState, mutable: &MutableState) -> Fallible<
     Ok(html)
 }
 
+/// The configuration to render a card's Markdown, with media resolved
+/// relative to its deck.
+pub fn render_config(state: &ServerState, card: &Card) -> Fallible<MarkdownRenderConfig> {
+    card_render_config(&state.directory, state.port, card)
+}
+
+/// The configuration to render a card's Markdown, for a server on the given
+/// port serving the collection in the given directory.
+pub fn card_render_config(
+    directory: &Path,
+    port: u16,
+    card: &Card,
+) -> Fallible<MarkdownRenderConfig> {
+    let coll_path = directory.to_path_buf();
+    let deck_path = card.relative_file_path(&coll_path)?;
+    Ok(MarkdownRenderConfig {
+        resolver: MediaResolverBuilder::new()
+            .with_collection_path(coll_path)?
+            .with_deck_path(deck_path)?
+            .build()?,
+        port,
+    })
+}
+
 fn render_card(card: &Card, reveal: bool, config: &MarkdownRenderConfig) -> Fallible<Markup> {
     let html = match card.card_type() {
         CardType::Basic => {
@@ -152,7 +169,7 @@ fn render_card(card: &Card, reveal: bool, config: &MarkdownRenderConfig) -> Fall
                 }
             }
         }
-        CardType::Cloze => {
+        CardType::Cloze | CardType::List => {
             if reveal {
                 html! {
                     div .prompt .rich-text {
@@ -168,9 +185,19 @@ fn render_card(card: &Card, reveal: bool, config: &MarkdownRenderConfig) -> Fall
             }
         }
     };
+    let note = if reveal {
+        card.html_note(config)?
+    } else {
+        None
+    };
     Ok(html! {
         div.card-content {
             (html)
+            @if let Some(note) = note {
+                div .note .rich-text {
+                    (note)
+                }
+            }
         }
     })
 }
@@ -258,6 +285,12 @@ fn undo_button(disabled: bool) -> Markup {
     }
 }
 
+fn edit_button() -> Markup {
+    html! {
+        input id="edit" type="submit" name="action" value="Edit" title="Open the card in your editor. Shortcut: o.";
+    }
+}
+
 fn end_button() -> Markup {
     html! {
         input id="end" type="submit" name="action" value="End" title="End the session (changes are saved)";
@@ -12,28 +12,41 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod api;
 mod cache;
-mod get;
-mod katex;
+mod editor;
+pub mod get;
+pub mod katex;
 mod post;
+mod reload;
+mod resume;
 pub mod server;
 mod state;
-mod template;
+pub mod template;
+mod terminal;
+pub mod tui;
 
 #[cfg(test)]
 mod tests {
-    use std::fs::create_dir_all;
+    use std::fs::write;
+    use std::path::PathBuf;
+    use std::time::Duration;
 
     use portpicker::pick_unused_port;
     use reqwest::StatusCode;
-    use tempfile::tempdir;
+    use serde_json::Value;
+    use serde_json::from_str;
     use tokio::spawn;
+    use tokio::time::sleep;
 
     use crate::cmd::drill::server::AnswerControls;
     use crate::cmd::drill::server::ServerConfig;
+    use crate::cmd::drill::server::load_session;
     use crate::cmd::drill::server::start_server;
+    use crate::db::Database;
     use crate::error::Fallible;
     use crate::helper::create_tmp_copy_of_test_directory;
+    use crate::helper::create_tmp_directory;
     use crate::types::timestamp::Timestamp;
     use crate::utils::wait_for_server;
 
@@ -45,6 +58,7 @@ mod tests {
         let session_started_at = Timestamp::now();
         let config = ServerConfig {
             directory: Some("./derpherp".to_string()),
+            db: None,
             host: TEST_HOST.to_string(),
             port,
             session_started_at,
@@ -54,6 +68,7 @@ mod tests {
             shuffle: false,
             answer_controls: AnswerControls::Full,
             bury_siblings: false,
+            resume: None,
         };
         let result = start_server(config).await;
         assert!(result.is_err());
@@ -65,12 +80,12 @@ mod tests {
     #[tokio::test]
     async fn test_start_server_with_no_cards_due() -> Fallible<()> {
         let port = pick_unused_port().unwrap();
-        let dir = tempdir()?.path().to_path_buf().canonicalize()?;
-        create_dir_all(&dir)?;
+        let dir = create_tmp_directory()?;
         let session_started_at = Timestamp::now();
         let dir = dir.canonicalize().unwrap().display().to_string();
         let config = ServerConfig {
             directory: Some(dir),
+            db: None,
             host: TEST_HOST.to_string(),
             port,
             session_started_at,
@@ -80,6 +95,7 @@ mod tests {
             shuffle: false,
             answer_controls: AnswerControls::Full,
             bury_siblings: false,
+            resume: None,
         };
         start_server(config).await?;
         Ok(())
@@ -92,6 +108,7 @@ mod tests {
         let session_started_at = Timestamp::now();
         let config = ServerConfig {
             directory: Some(directory),
+            db: None,
             host: TEST_HOST.to_string(),
             port,
             session_started_at,
@@ -101,6 +118,7 @@ mod tests {
             shuffle: false,
             answer_controls: AnswerControls::Full,
             bury_siblings: false,
+            resume: None,
         };
         spawn(async move { start_server(config).await });
         wait_for_server(TEST_HOST, port).await?;
@@ -194,6 +212,7 @@ mod tests {
         let session_started_at = Timestamp::now();
         let config = ServerConfig {
             directory: Some(directory),
+            db: None,
             host: TEST_HOST.to_string(),
             port,
             session_started_at,
@@ -203,6 +222,7 @@ mod tests {
             shuffle: false,
             answer_controls: AnswerControls::Full,
             bury_siblings: false,
+            resume: None,
         };
         spawn(async move { start_server(config).await });
         wait_for_server(TEST_HOST, port).await?;
@@ -243,6 +263,7 @@ mod tests {
         let session_started_at = Timestamp::now();
         let config = ServerConfig {
             directory: Some(directory),
+            db: None,
             host: TEST_HOST.to_string(),
             port,
             session_started_at,
@@ -252,6 +273,7 @@ mod tests {
             shuffle: false,
             answer_controls: AnswerControls::Full,
             bury_siblings: false,
+            resume: None,
         };
         spawn(async move { start_server(config).await });
         wait_for_server(TEST_HOST, port).await?;
@@ -274,6 +296,7 @@ mod tests {
         let session_started_at = Timestamp::now();
         let config = ServerConfig {
             directory: Some(directory),
+            db: None,
             host: TEST_HOST.to_string(),
             port,
             session_started_at,
@@ -283,6 +306,7 @@ mod tests {
             shuffle: false,
             answer_controls: AnswerControls::Full,
             bury_siblings: false,
+            resume: None,
         };
         spawn(async move { start_server(config).await });
         wait_for_server(TEST_HOST, port).await?;
@@ -305,6 +329,7 @@ mod tests {
         let session_started_at = Timestamp::now();
         let config = ServerConfig {
             directory: Some(directory),
+            db: None,
             host: TEST_HOST.to_string(),
             port,
             session_started_at,
@@ -314,6 +339,7 @@ mod tests {
             shuffle: false,
             answer_controls: AnswerControls::Full,
             bury_siblings: false,
+            resume: None,
         };
         spawn(async move { start_server(config).await });
         wait_for_server(TEST_HOST, port).await?;
@@ -354,6 +380,7 @@ mod tests {
         let session_started_at = Timestamp::now();
         let config = ServerConfig {
             directory: Some(directory),
+            db: None,
             host: TEST_HOST.to_string(),
             port,
             session_started_at,
@@ -363,6 +390,7 @@ mod tests {
             shuffle: false,
             answer_controls: AnswerControls::Full,
             bury_siblings: false,
+            resume: None,
         };
         spawn(async move { start_server(config).await });
         wait_for_server(TEST_HOST, port).await?;
@@ -379,4 +407,265 @@ mod tests {
 
         Ok(())
     }
+
+    /// A session interrupted after a review is resumed with the same queue
+    /// and undo stack.
+    #[tokio::test]
+    async fn test_resume() -> Fallible<()> {
+        let directory = create_tmp_copy_of_test_directory()?;
+        let config = |port: u16, resume: Option<bool>| ServerConfig {
+            directory: Some(directory.clone()),
+            db: None,
+            host: TEST_HOST.to_string(),
+            port,
+            session_started_at: Timestamp::now(),
+            card_limit: None,
+            new_card_limit: None,
+            deck_filter: None,
+            shuffle: false,
+            answer_controls: AnswerControls::Full,
+            bury_siblings: false,
+            resume,
+        };
+        let post = async |port: u16, action: &str| -> Fallible<String> {
+            let response = reqwest::Client::new()
+                .post(format!("http://{TEST_HOST}:{port}/"))
+                .form(&[("action", action)])
+                .send()
+                .await?;
+            Ok(response.text().await?)
+        };
+
+        // Forget the first card, which goes to the back of the queue, and
+        // leave the session unfinished.
+        let port = pick_unused_port().unwrap();
+        spawn(start_server(config(port, None)));
+        wait_for_server(TEST_HOST, port).await?;
+        post(port, "Reveal").await?;
+        let html = post(port, "Forgot").await?;
+        assert!(html.contains("FOO"));
+
+        // Resume it.
+        let port = pick_unused_port().unwrap();
+        spawn(start_server(config(port, Some(true))));
+        wait_for_server(TEST_HOST, port).await?;
+        let html = reqwest::get(format!("http://{TEST_HOST}:{port}/"))
+            .await?
+            .text()
+            .await?;
+        assert!(html.contains("FOO"));
+        post(port, "Reveal").await?;
+        let html = post(port, "Good").await?;
+        assert!(html.contains("baz <span class='cloze'>.............</span>"));
+        let html = post(port, "Undo").await?;
+        assert!(html.contains("FOO"));
+        let html = post(port, "Undo").await?;
+        assert!(html.contains("baz <span class='cloze'>.............</span>"));
+
+        Ok(())
+    }
+
+    /// A session whose queued cards are all gone is recovered instead of
+    /// resumed.
+    #[tokio::test]
+    async fn test_resume_without_cards() -> Fallible<()> {
+        let directory = create_tmp_copy_of_test_directory()?;
+        let config = |port: u16, resume: Option<bool>| ServerConfig {
+            directory: Some(directory.clone()),
+            db: None,
+            host: TEST_HOST.to_string(),
+            port,
+            session_started_at: Timestamp::now(),
+            card_limit: None,
+            new_card_limit: None,
+            deck_filter: None,
+            shuffle: false,
+            answer_controls: AnswerControls::Full,
+            bury_siblings: false,
+            resume,
+        };
+
+        // Review the first card, and leave the session unfinished.
+        let port = pick_unused_port().unwrap();
+        spawn(start_server(config(port, None)));
+        wait_for_server(TEST_HOST, port).await?;
+        for action in ["Reveal", "Good"] {
+            reqwest::Client::new()
+                .post(format!("http://{TEST_HOST}:{port}/"))
+                .form(&[("action", action)])
+                .send()
+                .await?;
+        }
+
+        // Replace the cards in the collection.
+        write(
+            PathBuf::from(&directory).join("Deck.md"),
+            "Q: New\nA: Card\n",
+        )?;
+        let state = load_session(&config(pick_unused_port().unwrap(), Some(true)), None)?
+            .expect("The new card is due.");
+        let mutable = state.mutable.lock().unwrap();
+        assert_eq!(mutable.cards.len(), 1);
+        assert_eq!(state.total_cards, 1);
+        assert!(mutable.reviews.is_empty());
+        assert_eq!(mutable.db.pending_sessions()?.len(), 1);
+        assert_eq!(mutable.db.get_all_sessions()?.len(), 1);
+        Ok(())
+    }
+
+    /// The edit action runs the editor command on the current card's file,
+    /// unless the request comes from another site.
+    #[tokio::test]
+    async fn test_edit() -> Fallible<()> {
+        let port = pick_unused_port().unwrap();
+        let directory = create_tmp_copy_of_test_directory()?;
+        write(
+            PathBuf::from(&directory).join("hashcards.toml"),
+            "editor = \"touch {file}.{line}\"\n",
+        )?;
+        let config = ServerConfig {
+            directory: Some(directory.clone()),
+            db: None,
+            host: TEST_HOST.to_string(),
+            port,
+            session_started_at: Timestamp::now(),
+            card_limit: None,
+            new_card_limit: None,
+            deck_filter: None,
+            shuffle: false,
+            answer_controls: AnswerControls::Full,
+            bury_siblings: false,
+            resume: None,
+        };
+        spawn(async move { start_server(config).await });
+        wait_for_server(TEST_HOST, port).await?;
+        // The first card is the cloze card, on the fourth line.
+        let opened = PathBuf::from(&directory).join("Deck.md.4");
+
+        let client = reqwest::Client::new();
+        client
+            .post(format!("http://{TEST_HOST}:{port}/"))
+            .header("Origin", "https://example.com")
+            .form(&[("action", "Edit")])
+            .send()
+            .await?;
+        let response = client
+            .post(format!("http://{TEST_HOST}:{port}/api/v1/edit"))
+            .header("Sec-Fetch-Site", "cross-site")
+            .send()
+            .await?;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        sleep(Duration::from_millis(200)).await;
+        assert!(!opened.exists());
+
+        let response = client
+            .post(format!("http://{TEST_HOST}:{port}/"))
+            .header("Origin", format!("http://{TEST_HOST}:{port}"))
+            .form(&[("action", "Edit")])
+            .send()
+            .await?;
+        assert!(response.status().is_success());
+        for _ in 0..50 {
+            if opened.exists() {
+                break;
+            }
+            sleep(Duration::from_millis(20)).await;
+        }
+        assert!(opened.exists());
+
+        Ok(())
+    }
+
+    /// The JSON API drills a session to completion, and grade durations are
+    /// recorded.
+    #[tokio::test]
+    async fn test_api() -> Fallible<()> {
+        let port = pick_unused_port().unwrap();
+        let directory = create_tmp_copy_of_test_directory()?;
+        let db_path = PathBuf::from(&directory).join("api.db");
+        let config = ServerConfig {
+            directory: Some(directory),
+            db: Some(db_path.display().to_string()),
+            host: TEST_HOST.to_string(),
+            port,
+            session_started_at: Timestamp::now(),
+            card_limit: None,
+            new_card_limit: None,
+            deck_filter: None,
+            shuffle: false,
+            answer_controls: AnswerControls::Full,
+            bury_siblings: false,
+            resume: None,
+        };
+        spawn(async move { start_server(config).await });
+        wait_for_server(TEST_HOST, port).await?;
+        let url = |path: &str| format!("http://{TEST_HOST}:{port}/api/v1/{path}");
+        let get = async |path: &str| -> Fallible<(StatusCode, Value)> {
+            let response = reqwest::get(url(path)).await?;
+            Ok((response.status(), from_str(&response.text().await?)?))
+        };
+        let post = async |path: &str, body: &str| -> Fallible<(StatusCode, Value)> {
+            let response = reqwest::Client::new()
+                .post(url(path))
+                .header("content-type", "application/json")
+                .body(body.to_string())
+                .send()
+                .await?;
+            Ok((response.status(), from_str(&response.text().await?)?))
+        };
+
+        let (status, session) = get("session").await?;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(session["totalCards"], 2);
+        assert_eq!(session["cardsLeft"], 2);
+        assert_eq!(session["canUndo"], false);
+
+        let (_, card) = get("card").await?;
+        assert_eq!(card["kind"], "cloze");
+        assert_eq!(card["revealed"], false);
+        assert!(card["back"].is_null());
+
+        // Grading before revealing is a conflict.
+        let (status, error) = post("grade", r#"{"grade":"good"}"#).await?;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert!(error["error"].is_string());
+
+        let (status, session) = post("reveal", "").await?;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(session["revealed"], true);
+        let (_, card) = get("card").await?;
+        assert!(
+            card["back"]
+                .as_str()
+                .unwrap()
+                .contains("<span class='cloze-reveal'>quux</span>")
+        );
+
+        let (status, _) = post("grade", r#"{"grade":"meh"}"#).await?;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, session) = post("grade", r#"{"grade":"good","durationMs":1500}"#).await?;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(session["cardsLeft"], 1);
+        assert_eq!(session["canUndo"], true);
+
+        let (_, session) = post("undo", "").await?;
+        assert_eq!(session["cardsLeft"], 2);
+        post("reveal", "").await?;
+        post("grade", r#"{"grade":"easy","durationMs":2500}"#).await?;
+
+        let (_, session) = post("end", "").await?;
+        assert!(session["finishedAt"].is_string());
+        let (status, _) = get("card").await?;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, _) = post("end", "").await?;
+        assert_eq!(status, StatusCode::CONFLICT);
+
+        let db = Database::new(&db_path.display().to_string())?;
+        let sessions = db.get_all_sessions()?;
+        let reviews = db.get_reviews_for_session(sessions[0].session_id)?;
+        assert_eq!(reviews.len(), 1);
+        assert_eq!(reviews[0].data.duration_ms, Some(2500));
+
+        Ok(())
+    }
 }
@@ -14,13 +14,15 @@
 
 use axum::Form;
 use axum::extract::State;
+use axum::http::HeaderMap;
 use axum::response::Redirect;
 use serde::Deserialize;
 
+use crate::cmd::drill::editor::is_same_origin;
+use crate::cmd::drill::editor::open_in_editor;
 use crate::cmd::drill::state::MutableState;
 use crate::cmd::drill::state::Review;
 use crate::cmd::drill::state::ServerState;
-use crate::db::ReviewRecord;
 use crate::error::Fallible;
 use crate::fsrs::Grade;
 use crate::types::card::Card;
@@ -30,11 +32,12 @@ use crate::types::performance::ReviewedPerformance;
 use crate::types::performance::update_performance;
 use crate::types::timestamp::Timestamp;
 
-#[derive(Debug, Deserialize)]
-enum Action {
+#[derive(Debug, PartialEq, Deserialize)]
+pub enum Action {
     Reveal,
     Undo,
     End,
+    Edit,
     Forgot,
     Hard,
     Good,
@@ -54,6 +57,17 @@ impl Action {
     }
 }
 
+impl From<Grade> for Action {
+    fn from(grade: Grade) -> Self {
+        match grade {
+            Grade::Forgot => Action::Forgot,
+            Grade::Hard => Action::Hard,
+            Grade::Good => Action::Good,
+            Grade::Easy => Action::Easy,
+        }
+    }
+}
+
 #[derive(Deserialize)]
 pub struct FormData {
     action: Action,
@@ -61,9 +75,14 @@ pub struct FormData {
 
 pub async fn post_handler(
     State(state): State<ServerState>,
+    headers: HeaderMap,
     Form(form): Form<FormData>,
 ) -> Redirect {
-    match action_handler(state, form.action).await {
+    if form.action == Action::Edit && !is_same_origin(&headers) {
+        log::warn!("Refused to open the editor for a cross-site request.");
+        return Redirect::to("/");
+    }
+    match apply_action(&state, form.action, None) {
         Ok(_) => {}
         Err(e) => {
             log::error!("error: {e}");
@@ -72,16 +91,24 @@ pub async fn post_handler(
     Redirect::to("/")
 }
 
-async fn action_handler(state: ServerState, action: Action) -> Fallible<()> {
+/// Apply an action to the session. For grades, `duration_ms` is how long the
+/// user took to answer, if known. Returns whether the action applied, e.g.
+/// grading a card before it is revealed does nothing.
+pub fn apply_action(
+    state: &ServerState,
+    action: Action,
+    duration_ms: Option<i64>,
+) -> Fallible<bool> {
     let mut mutable = state.mutable.lock().unwrap();
     match action {
         Action::Reveal => {
-            if !mutable.reveal {
+            if mutable.finished_at.is_none() && !mutable.reveal {
                 mutable.reveal = true;
+                return Ok(true);
             }
         }
         Action::Undo => {
-            if !mutable.reviews.is_empty() {
+            if mutable.finished_at.is_none() && !mutable.reviews.is_empty() {
                 let last_review: Review = mutable.reviews.pop().unwrap();
                 if last_review.should_repeat() {
                     // Remove the card from the back of the queue.
@@ -90,16 +117,30 @@ async fn action_handler(state: ServerState, action: Action) -> Fallible<()> {
                 let card: Card = last_review.card;
                 let hash: CardHash = card.hash();
                 mutable.cards.insert(0, card);
+                let queue = mutable.queue();
+                let pending_session_id = mutable.pending_session_id;
+                mutable
+                    .db
+                    .remove_last_pending_review(pending_session_id, &queue)?;
                 // Restore the performance cache to the value in the database
                 // if it exists.
                 let performance = mutable.db.get_card_performance(hash)?;
                 mutable.cache.update(hash, performance)?;
-                mutable.finished_at = None;
                 mutable.reveal = false;
+                return Ok(true);
             }
         }
         Action::End => {
-            finish_session(&mut mutable, &state)?;
+            if mutable.finished_at.is_none() {
+                finish_session(&mut mutable)?;
+                return Ok(true);
+            }
+        }
+        Action::Edit => {
+            if mutable.finished_at.is_none() {
+                open_in_editor(state.editor.as_deref(), &mutable.cards[0])?;
+                return Ok(true);
+            }
         }
         Action::Finish => {
             // Only allow finish (server shutdown) if session is finished
@@ -113,10 +154,11 @@ async fn action_handler(state: ServerState, action: Action) -> Fallible<()> {
                 if let Some(tx) = shutdown_tx.take() {
                     let _ = tx.send(());
                 }
+                return Ok(true);
             }
         }
         Action::Forgot | Action::Hard | Action::Good | Action::Easy => {
-            if mutable.reveal {
+            if mutable.finished_at.is_none() && mutable.reveal {
                 let reviewed_at: Timestamp = Timestamp::now();
                 let card: Card = mutable.cards.remove(0);
                 let hash: CardHash = card.hash();
@@ -133,6 +175,7 @@ async fn action_handler(state: ServerState, action: Action) -> Fallible<()> {
                     interval_raw: performance.interval_raw,
                     interval_days: performance.interval_days,
                     due_date: performance.due_date,
+                    duration_ms,
                 };
 
                 mutable
@@ -141,33 +184,42 @@ async fn action_handler(state: ServerState, action: Action) -> Fallible<()> {
                 if review.should_repeat() {
                     mutable.cards.push(card.clone());
                 }
+                let queue = mutable.queue();
+                let pending_session_id = mutable.pending_session_id;
+                mutable.db.add_pending_review(
+                    pending_session_id,
+                    &review.clone().into_record(),
+                    &queue,
+                )?;
                 mutable.reviews.push(review);
                 mutable.reveal = false;
 
                 // Was this the last card?
                 if mutable.cards.is_empty() {
-                    finish_session(&mut mutable, &state)?;
+                    finish_session(&mut mutable)?;
                 }
+                return Ok(true);
             }
         }
     }
-    Ok(())
+    Ok(false)
 }
 
-fn finish_session(mutable: &mut MutableState, state: &ServerState) -> Fallible<()> {
+/// Record the session and its reviews, which were journaled as they happened,
+/// and write the new performance of the cards, in a single transaction.
+fn finish_session(mutable: &mut MutableState) -> Fallible<()> {
     log::debug!("Session completed");
     let session_ended_at = Timestamp::now();
-    let reviews: Vec<Review> = mutable.reviews.clone();
-    let reviews: Vec<ReviewRecord> = reviews.into_iter().map(Review::into_record).collect();
+    let performance: Vec<(CardHash, Performance)> = mutable
+        .cache
+        .iter()
+        .map(|(card_hash, performance)| (*card_hash, *performance))
+        .collect();
+    let pending_session_id = mutable.pending_session_id;
     mutable
         .db
-        .save_session(state.session_started_at, session_ended_at, reviews)?;
+        .finish_pending_session(pending_session_id, session_ended_at, &performance)?;
     mutable.finished_at = Some(session_ended_at);
-    for (card_hash, performance) in mutable.cache.iter() {
-        mutable
-            .db
-            .update_card_performance(*card_hash, *performance)?;
-    }
     Ok(())
 }
 
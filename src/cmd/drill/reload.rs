@@ -0,0 +1,320 @@
+// Copyright 2025 Fernando Borretti
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Reloading the collection when its files change during a session.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::mpsc::channel;
+use std::thread::spawn;
+use std::time::Duration;
+
+use notify::Event;
+use notify::EventKind;
+use notify::RecommendedWatcher;
+use notify::RecursiveMode;
+use notify::Watcher;
+use notify::event::ModifyKind;
+use notify::recommended_watcher;
+
+use crate::cmd::drill::state::ServerState;
+use crate::collection::MACROS_FILE;
+use crate::collection::load_cards;
+use crate::collection::load_macros;
+use crate::config::CONFIG_FILE;
+use crate::db::Database;
+use crate::error::ErrorReport;
+use crate::error::Fallible;
+use crate::format::DeckFormat;
+use crate::markdown::IMAGE_EXTENSIONS;
+use crate::markdown::is_audio_file;
+use crate::types::card::Card;
+use crate::types::card_hash::CardHash;
+use crate::types::performance::Performance;
+use crate::types::timestamp::Timestamp;
+use crate::walk::IGNORE_FILE;
+
+/// How long to wait for more changes before reloading, since saving a file
+/// can take several writes.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Watch the collection directory, and reload the collection when a deck,
+/// media file, or `macros.tex` changes. The collection is watched until the
+/// watcher is dropped.
+pub fn watch_collection(state: ServerState) -> Fallible<RecommendedWatcher> {
+    let (tx, rx) = channel::<notify::Result<Event>>();
+    let mut watcher = recommended_watcher(tx)?;
+    watcher.watch(&state.directory, RecursiveMode::Recursive)?;
+    spawn(move || {
+        while let Ok(event) = rx.recv() {
+            if !event.is_ok_and(|event| is_relevant(&state.directory, &event)) {
+                continue;
+            }
+            while rx.recv_timeout(DEBOUNCE).is_ok() {}
+            match reload(&state) {
+                Ok(0) => log::debug!("Reloaded the collection."),
+                Ok(n) => log::debug!("Reloaded the collection, replacing {n} edited cards."),
+                Err(e) => log::warn!("Failed to reload the collection: {e}"),
+            }
+        }
+    });
+    Ok(watcher)
+}
+
+/// Whether an event changes the collection. Reading files, and writing the
+/// database, which may be in the collection directory, don't.
+fn is_relevant(directory: &Path, event: &Event) -> bool {
+    let changes = matches!(
+        event.kind,
+        EventKind::Create(_) | EventKind::Remove(_) | EventKind::Modify(_)
+    ) && !matches!(event.kind, EventKind::Modify(ModifyKind::Metadata(_)));
+    changes
+        && event.paths.iter().any(|path| {
+            let hidden = path.strip_prefix(directory).is_ok_and(|relative| {
+                relative
+                    .components()
+                    .any(|c| c.as_os_str().to_string_lossy().starts_with('.'))
+            });
+            let name = path
+                .file_name()
+                .map(|name| name.to_string_lossy())
+                .unwrap_or_default();
+            let extension = path
+                .extension()
+                .map(|extension| extension.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            name == IGNORE_FILE
+                || (!hidden
+                    && (DeckFormat::from_path(path).is_some()
+                        || name == MACROS_FILE
+                        || name == CONFIG_FILE
+                        || IMAGE_EXTENSIONS.contains(&extension.as_str())
+                        || is_audio_file(&name)))
+        })
+}
+
+/// Parse the collection again, and update the session: cards left to drill
+/// get their new text, and cards that were edited, and so have a new hash,
+/// are replaced by the new card. Returns the number of cards replaced.
+///
+/// An edited card is matched to the new card, not seen before, in the same
+/// file, nearest to where the old card was. Cards that were deleted stay in
+/// the session.
+pub fn reload(state: &ServerState) -> Fallible<usize> {
+    let macros = load_macros(&state.directory)?;
+
+    // Parse the collection with a database connection of its own, so the
+    // session isn't locked while the collection is parsed.
+    let database = state
+        .database
+        .to_str()
+        .ok_or_else(|| ErrorReport::new("invalid path"))?;
+    let mut db = Database::new(database)?;
+    let cards: Vec<Card> = load_cards(&state.directory, &mut db)?;
+    let by_hash: HashMap<CardHash, &Card> = cards.iter().map(|card| (card.hash(), card)).collect();
+
+    // Cards not in the database are new, or new versions of edited cards.
+    let mut unseen: Vec<&Card> = Vec::new();
+    for card in &cards {
+        if db.get_card_performance_opt(card.hash())?.is_none() {
+            unseen.push(card);
+        }
+    }
+    db.insert_cards(unseen.iter().map(|card| card.hash()), Timestamp::now())?;
+
+    let mut mutable = state.mutable.lock().unwrap();
+
+    // Match each edited card to its new version once: a card that was
+    // forgotten is in the queue twice, and both copies are replaced.
+    let mut replacements: HashMap<CardHash, Card> = HashMap::new();
+    let mut used: HashSet<CardHash> = HashSet::new();
+    for old in &mutable.cards {
+        if by_hash.contains_key(&old.hash()) || replacements.contains_key(&old.hash()) {
+            continue;
+        }
+        let nearest: Option<&Card> = unseen
+            .iter()
+            .filter(|card| card.file_path() == old.file_path() && !used.contains(&card.hash()))
+            .min_by_key(|card| card.range().0.abs_diff(old.range().0))
+            .copied();
+        if let Some(card) = nearest {
+            used.insert(card.hash());
+            replacements.insert(old.hash(), card.clone());
+        }
+    }
+    for card in replacements.values() {
+        mutable.cache.insert(card.hash(), Performance::New)?;
+    }
+    if let Some(first) = mutable.cards.first() {
+        if replacements.contains_key(&first.hash()) {
+            mutable.reveal = false;
+        }
+    }
+    for card in mutable.cards.iter_mut() {
+        if let Some(new) = by_hash.get(&card.hash()) {
+            *card = (*new).clone();
+        } else if let Some(new) = replacements.get(&card.hash()) {
+            *card = new.clone();
+        }
+    }
+    for review in mutable.reviews.iter_mut() {
+        if let Some(card) = by_hash.get(&review.card.hash()) {
+            review.card = (*card).clone();
+        }
+    }
+    if !replacements.is_empty() {
+        let queue = mutable.queue();
+        let pending_session_id = mutable.pending_session_id;
+        mutable.db.set_pending_queue(pending_session_id, &queue)?;
+    }
+    mutable.macros = macros;
+    mutable.revision += 1;
+    Ok(replacements.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::write;
+    use std::path::PathBuf;
+
+    use notify::event::CreateKind;
+    use notify::event::DataChange;
+
+    use super::*;
+    use crate::cmd::drill::server::AnswerControls;
+    use crate::cmd::drill::server::ServerConfig;
+    use crate::cmd::drill::server::load_session;
+    use crate::helper::create_tmp_copy_of_test_directory;
+    use crate::types::card::CardType;
+
+    #[test]
+    fn test_reload() -> Fallible<()> {
+        let directory = create_tmp_copy_of_test_directory()?;
+        let config = ServerConfig {
+            directory: Some(directory.clone()),
+            db: Some(
+                PathBuf::from(&directory)
+                    .join("test.db")
+                    .display()
+                    .to_string(),
+            ),
+            host: "127.0.0.1".to_string(),
+            port: 0,
+            session_started_at: Timestamp::now(),
+            card_limit: None,
+            new_card_limit: None,
+            deck_filter: None,
+            shuffle: false,
+            answer_controls: AnswerControls::Full,
+            bury_siblings: false,
+            resume: Some(false),
+        };
+        let state = load_session(&config, None)?.unwrap();
+        let before = state.mutable.lock().unwrap().queue();
+
+        // Nothing changed.
+        assert_eq!(reload(&state)?, 0);
+        assert_eq!(state.mutable.lock().unwrap().queue(), before);
+
+        // Edit the basic card, and the macros.
+        let deck = PathBuf::from(&directory).join("Deck.md");
+        write(&deck, "Q: FOO?\nA: BAR\n\nC: baz [quux]\n")?;
+        write(
+            PathBuf::from(&directory).join("macros.tex"),
+            "\\R \\mathbb{R}\n",
+        )?;
+        assert_eq!(reload(&state)?, 1);
+        let mutable = state.mutable.lock().unwrap();
+        let after = mutable.queue();
+        assert_eq!(after.len(), before.len());
+        let new: Vec<&CardHash> = after.iter().filter(|hash| !before.contains(hash)).collect();
+        assert_eq!(new.len(), 1);
+        assert!(mutable.cache.get(*new[0])?.is_new());
+        assert_eq!(mutable.db.pending_sessions()?[0].queue, after);
+        assert_eq!(
+            mutable.macros,
+            vec![("\\R".to_string(), "\\mathbb{R}".to_string())]
+        );
+        assert_eq!(mutable.revision, 2);
+        Ok(())
+    }
+
+    /// A card that is in the queue twice, because it was forgotten, is
+    /// replaced everywhere when it's edited.
+    #[test]
+    fn test_reload_forgotten_card() -> Fallible<()> {
+        let directory = create_tmp_copy_of_test_directory()?;
+        let config = ServerConfig {
+            directory: Some(directory.clone()),
+            db: None,
+            host: "127.0.0.1".to_string(),
+            port: 0,
+            session_started_at: Timestamp::now(),
+            card_limit: None,
+            new_card_limit: None,
+            deck_filter: None,
+            shuffle: false,
+            answer_controls: AnswerControls::Full,
+            bury_siblings: false,
+            resume: Some(false),
+        };
+        let state = load_session(&config, None)?.unwrap();
+        let basic = {
+            let mut mutable = state.mutable.lock().unwrap();
+            let basic = mutable
+                .cards
+                .iter()
+                .find(|card| card.card_type() == CardType::Basic)
+                .unwrap()
+                .clone();
+            mutable.cards.push(basic.clone());
+            basic
+        };
+
+        let deck = PathBuf::from(&directory).join("Deck.md");
+        write(&deck, "Q: FOO?\nA: BAR\n\nC: baz [quux]\n")?;
+        assert_eq!(reload(&state)?, 1);
+        let mutable = state.mutable.lock().unwrap();
+        let basics: Vec<CardHash> = mutable
+            .cards
+            .iter()
+            .filter(|card| card.card_type() == CardType::Basic)
+            .map(Card::hash)
+            .collect();
+        assert_eq!(basics.len(), 2);
+        assert_eq!(basics[0], basics[1]);
+        assert_ne!(basics[0], basic.hash());
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_relevant() {
+        let directory = PathBuf::from("/collection");
+        let event = |kind: EventKind, path: &str| Event::new(kind).add_path(directory.join(path));
+        let modify = EventKind::Modify(ModifyKind::Data(DataChange::Content));
+        assert!(is_relevant(&directory, &event(modify, "deck.md")));
+        assert!(is_relevant(&directory, &event(modify, "macros.tex")));
+        assert!(is_relevant(
+            &directory,
+            &event(EventKind::Create(CreateKind::File), "images/cat.png")
+        ));
+        assert!(!is_relevant(&directory, &event(modify, "hashcards.db")));
+        assert!(!is_relevant(&directory, &event(modify, ".git/index.md")));
+        assert!(!is_relevant(
+            &directory,
+            &event(EventKind::Access(notify::event::AccessKind::Any), "deck.md")
+        ));
+    }
+}
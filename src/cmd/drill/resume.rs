@@ -0,0 +1,128 @@
+// Copyright 2025 Fernando Borretti
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::hash_map::Entry;
+use std::io::IsTerminal;
+use std::io::Write;
+use std::io::stdin;
+use std::io::stdout;
+
+use crate::cmd::drill::cache::Cache;
+use crate::cmd::drill::state::Review;
+use crate::db::Database;
+use crate::db::PendingSession;
+use crate::error::Fallible;
+use crate::types::card::Card;
+use crate::types::card_hash::CardHash;
+use crate::types::date::Date;
+use crate::types::performance::Performance;
+
+/// The state of a session, new or resumed.
+pub struct SessionState {
+    /// The cards left to drill, in order.
+    pub cards: Vec<Card>,
+    /// The reviews that can be undone, in order.
+    pub reviews: Vec<Review>,
+    pub cache: Cache,
+}
+
+/// The most recent pending session that started today and still has cards
+/// left in the collection, if any. A session whose cards have all been edited
+/// or deleted since can't be resumed.
+pub fn resumable_session(
+    db: &Database,
+    cards: &[Card],
+    today: Date,
+) -> Fallible<Option<PendingSession>> {
+    let hashes: HashSet<CardHash> = cards.iter().map(Card::hash).collect();
+    let session = db.pending_sessions()?.into_iter().rev().find(|session| {
+        session.started_at.date() == today && session.queue.iter().any(|hash| hashes.contains(hash))
+    });
+    Ok(session)
+}
+
+/// Ask whether to resume the given session. If there is no terminal to ask
+/// in, the session is resumed.
+pub fn ask_to_resume(session: &PendingSession) -> Fallible<bool> {
+    if !stdin().is_terminal() {
+        return Ok(true);
+    }
+    print!(
+        "Found an unfinished session from {}, with {} of {} cards left. Resume it? [Y/n] ",
+        session.started_at.into_inner().format("%H:%M"),
+        session.queue.len(),
+        session.total_cards
+    );
+    stdout().flush()?;
+    let mut answer = String::new();
+    stdin().read_line(&mut answer)?;
+    let answer = answer.trim().to_lowercase();
+    Ok(answer != "n" && answer != "no")
+}
+
+/// Rebuild the state of a pending session from the database and the cards in
+/// the collection. Cards that are no longer in the collection are left out of
+/// the queue, and reviews of them, and the reviews before those, can't be
+/// undone.
+pub fn resume_session(
+    db: &Database,
+    session: &PendingSession,
+    cards: &[Card],
+    today: Date,
+) -> Fallible<SessionState> {
+    let by_hash: HashMap<CardHash, &Card> = cards.iter().map(|card| (card.hash(), card)).collect();
+    let queue: Vec<Card> = session
+        .queue
+        .iter()
+        .filter_map(|hash| by_hash.get(hash).map(|card| (*card).clone()))
+        .collect();
+    let mut reviews: Vec<Review> = Vec::new();
+    for record in &session.reviews {
+        match by_hash.get(&record.card_hash) {
+            Some(card) => reviews.push(Review::from_record((*card).clone(), record)),
+            None => reviews.clear(),
+        }
+    }
+
+    // The cards in the session were due today, and their performance in the
+    // database doesn't change until the session finishes, unless they were
+    // reviewed.
+    let due: HashMap<CardHash, Performance> = db.due_today(today)?;
+    let mut performance: HashMap<CardHash, Performance> = db
+        .pending_performance(session.pending_session_id)?
+        .into_iter()
+        .map(|(hash, performance)| (hash, Performance::Reviewed(performance)))
+        .collect();
+    for card in &queue {
+        if let Entry::Vacant(entry) = performance.entry(card.hash()) {
+            let card_performance = match due.get(&card.hash()) {
+                Some(card_performance) => *card_performance,
+                None => db.get_card_performance(card.hash())?,
+            };
+            entry.insert(card_performance);
+        }
+    }
+    let mut cache = Cache::new();
+    for (hash, performance) in performance {
+        cache.insert(hash, performance)?;
+    }
+
+    Ok(SessionState {
+        cards: queue,
+        reviews,
+        cache,
+    })
+}
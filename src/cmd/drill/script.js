@@ -33,10 +33,9 @@ document.addEventListener("DOMContentLoaded", function () {
   if (typeof hljs !== "undefined") {
     hljs.highlightAll();
   }
-  const cardContent = document.querySelector(".card-content");
-  if (cardContent) {
-    cardContent.style.opacity = "1";
-  }
+  document.querySelectorAll(".card-content").forEach(function (element) {
+    element.style.opacity = "1";
+  });
 });
 
 document.addEventListener("keydown", function (event) {
@@ -48,6 +47,7 @@ document.addEventListener("keydown", function (event) {
   const keybindings = {
     " ": "reveal", // Space
     u: "undo",
+    o: "edit",
     1: "forgot",
     2: "hard",
     3: "good",
@@ -67,3 +67,20 @@ document.addEventListener("keydown", function (event) {
     }
   }
 });
+
+// Refresh the page when the collection is reloaded from disk.
+const root = document.querySelector(".root[data-revision]");
+if (root) {
+  setInterval(function () {
+    fetch("/api/v1/session")
+      .then(function (response) {
+        return response.ok ? response.json() : null;
+      })
+      .then(function (session) {
+        if (session && String(session.revision) !== root.dataset.revision) {
+          window.location.reload();
+        }
+      })
+      .catch(function () {});
+  }, 2000);
+}
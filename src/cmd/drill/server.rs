@@ -12,6 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::HashMap;
 use std::collections::HashSet;
 use std::fmt::Display;
 use std::fmt::Formatter;
@@ -36,8 +37,11 @@ use tokio::net::TcpListener;
 use tokio::select;
 use tokio::signal;
 use tokio::sync::oneshot::Receiver;
+use tokio::sync::oneshot::Sender;
 use tokio::sync::oneshot::channel;
 
+use crate::cmd::drill::api::API_PREFIX;
+use crate::cmd::drill::api::api_router;
 use crate::cmd::drill::cache::Cache;
 use crate::cmd::drill::get::get_handler;
 use crate::cmd::drill::katex::KATEX_CSS_URL;
@@ -48,10 +52,18 @@ use crate::cmd::drill::katex::katex_font_handler;
 use crate::cmd::drill::katex::katex_js_handler;
 use crate::cmd::drill::katex::katex_mhchem_js_handler;
 use crate::cmd::drill::post::post_handler;
+use crate::cmd::drill::reload::watch_collection;
+use crate::cmd::drill::resume::SessionState;
+use crate::cmd::drill::resume::ask_to_resume;
+use crate::cmd::drill::resume::resumable_session;
+use crate::cmd::drill::resume::resume_session;
 use crate::cmd::drill::state::MutableState;
 use crate::cmd::drill::state::ServerState;
 use crate::collection::Collection;
+use crate::collection::database_path;
+use crate::config::CollectionConfig;
 use crate::db::Database;
+use crate::db::PendingSession;
 use crate::error::Fallible;
 use crate::error::fail;
 use crate::media::load::MediaLoader;
@@ -60,6 +72,7 @@ use crate::rng::shuffle;
 use crate::types::card::Card;
 use crate::types::card_hash::CardHash;
 use crate::types::date::Date;
+use crate::types::performance::Performance;
 use crate::types::timestamp::Timestamp;
 use crate::utils::CACHE_CONTROL_IMMUTABLE;
 
@@ -82,6 +95,7 @@ impl Display for AnswerControls {
 
 pub struct ServerConfig {
     pub directory: Option<String>,
+    pub db: Option<String>,
     pub host: String,
     pub port: u16,
     pub session_started_at: Timestamp,
@@ -91,92 +105,28 @@ pub struct ServerConfig {
     pub shuffle: bool,
     pub answer_controls: AnswerControls,
     pub bury_siblings: bool,
+    /// Whether to resume an unfinished session from earlier today. If `None`,
+    /// the user is asked.
+    pub resume: Option<bool>,
 }
 
 pub async fn start_server(config: ServerConfig) -> Fallible<()> {
-    let Collection {
-        directory,
-        db,
-        cards,
-        macros,
-    } = Collection::new(config.directory)?;
-
-    let today: Date = config.session_started_at.date();
-
-    let db_hashes: HashSet<CardHash> = db.card_hashes()?;
-    // If a card is in the directory, but not in the DB, it is new. Add it to
-    // the database.
-    for card in cards.iter() {
-        if !db_hashes.contains(&card.hash()) {
-            db.insert_card(card.hash(), config.session_started_at)?;
-        }
-    }
-
-    // Find cards due today.
-    let due_today: HashSet<CardHash> = db.due_today(today)?;
-    let due_today: Vec<Card> = cards
-        .into_iter()
-        .filter(|card| due_today.contains(&card.hash()))
-        .collect::<Vec<_>>();
-
-    let due_today: Vec<Card> = filter_deck(
-        &db,
-        due_today,
-        config.card_limit,
-        config.new_card_limit,
-        config.deck_filter,
-    )?;
-
-    let due_today: Vec<Card> = if config.bury_siblings {
-        bury_siblings(due_today)
-    } else {
-        due_today
-    };
+    // Create shutdown channel
+    let (shutdown_tx, shutdown_rx) = channel();
 
-    if due_today.is_empty() {
+    let Some(state) = load_session(&config, Some(shutdown_tx))? else {
         println!("No cards due today.");
         return Ok(());
-    }
-
-    // Finally, shuffle the cards.
-    let due_today: Vec<Card> = if config.shuffle {
-        let seed = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_nanos() as u64;
-        let mut rng = TinyRng::from_seed(seed);
-        shuffle(due_today, &mut rng)
-    } else {
-        due_today
     };
-
-    // For all cards due today, fetch their performance from the database and store it in the cache.
-    let mut cache = Cache::new();
-    for card in due_today.iter() {
-        let performance = db.get_card_performance(card.hash())?;
-        cache.insert(card.hash(), performance)?;
-    }
-
-    // Create shutdown channel
-    let (shutdown_tx, shutdown_rx) = channel();
-
-    let state = ServerState {
-        port: config.port,
-        directory,
-        macros,
-        total_cards: due_today.len(),
-        session_started_at: config.session_started_at,
-        mutable: Arc::new(Mutex::new(MutableState {
-            reveal: false,
-            db,
-            cache,
-            cards: due_today,
-            reviews: Vec::new(),
-            finished_at: None,
-        })),
-        shutdown_tx: Arc::new(Mutex::new(Some(shutdown_tx))),
-        answer_controls: config.answer_controls,
+    // Reload the collection when its files change.
+    let _watcher = match watch_collection(state.clone()) {
+        Ok(watcher) => Some(watcher),
+        Err(e) => {
+            log::warn!("Not watching the collection for changes: {e}");
+            None
+        }
     };
+
     let app = Router::new();
     let app = app.route("/", get(get_handler));
     let app = app.route("/", post(post_handler));
@@ -187,6 +137,7 @@ pub async fn start_server(config: ServerConfig) -> Fallible<()> {
     let app = app.route(KATEX_MHCHEM_JS_URL, get(katex_mhchem_js_handler));
     let app = app.route("/katex/fonts/{*path}", get(katex_font_handler));
     let app = app.route("/file/{*path}", get(file_handler));
+    let app = app.nest(API_PREFIX, api_router());
     let app = app.fallback(not_found_handler);
     let app = app.with_state(state.clone());
     let bind = format!("{}:{}", config.host, config.port);
@@ -198,30 +149,133 @@ pub async fn start_server(config: ServerConfig) -> Fallible<()> {
         .with_graceful_shutdown(shutdown_signal(shutdown_rx))
         .await?;
 
-    // Check if session was complete when server shut down
+    session_result(&state)
+}
+
+/// Load the collection, and resume an unfinished session or start a new one.
+/// Returns `None` if there is nothing to drill.
+pub fn load_session(
+    config: &ServerConfig,
+    shutdown_tx: Option<Sender<()>>,
+) -> Fallible<Option<ServerState>> {
+    let Collection {
+        directory,
+        mut db,
+        cards,
+        macros,
+    } = Collection::new(config.directory.clone(), config.db.clone())?;
+
+    let database: PathBuf = database_path(&directory, config.db.clone())?;
+    let editor: Option<String> = CollectionConfig::load(&directory)?.editor;
+    let today: Date = config.session_started_at.date();
+
+    // If a card is in the directory, but not in the DB, it is new. Add it to
+    // the database.
+    db.insert_cards(cards.iter().map(Card::hash), config.session_started_at)?;
+
+    // Offer to resume an unfinished session from earlier today.
+    let resumable: Option<PendingSession> = match config.resume {
+        Some(false) => None,
+        _ => match resumable_session(&db, &cards, today)? {
+            Some(session) if config.resume == Some(true) || ask_to_resume(&session)? => {
+                Some(session)
+            }
+            _ => None,
+        },
+    };
+
+    // Record the reviews of other sessions that were interrupted.
+    let keep = resumable.as_ref().map(|session| session.pending_session_id);
+    let recovered = db.recover_pending_sessions(keep)?;
+    if recovered > 0 {
+        println!("Recovered {recovered} reviews from an interrupted session.");
+    }
+
+    let (session_started_at, pending_session_id, total_cards, session) = match resumable {
+        Some(pending) => {
+            let session = resume_session(&db, &pending, &cards, today)?;
+            (
+                pending.started_at,
+                pending.pending_session_id,
+                pending.total_cards,
+                session,
+            )
+        }
+        None => {
+            let Some(session) = new_session(&db, cards, today, config)? else {
+                return Ok(None);
+            };
+            // Reviews are journaled in the database as they happen.
+            let queue: Vec<CardHash> = session.cards.iter().map(Card::hash).collect();
+            let pending_session_id =
+                db.start_pending_session(config.session_started_at, &queue, queue.len())?;
+            (
+                config.session_started_at,
+                pending_session_id,
+                queue.len(),
+                session,
+            )
+        }
+    };
+
+    Ok(Some(ServerState {
+        port: config.port,
+        directory,
+        database,
+        total_cards,
+        session_started_at,
+        mutable: Arc::new(Mutex::new(MutableState {
+            reveal: false,
+            db,
+            pending_session_id,
+            cache: session.cache,
+            cards: session.cards,
+            reviews: session.reviews,
+            finished_at: None,
+            macros,
+            revision: 0,
+        })),
+        shutdown_tx: Arc::new(Mutex::new(shutdown_tx)),
+        answer_controls: config.answer_controls,
+        editor,
+    }))
+}
+
+/// Succeed if the session was finished, and fail if it was interrupted.
+pub fn session_result(state: &ServerState) -> Fallible<()> {
     let mutable = state.mutable.lock().unwrap();
     if mutable.finished_at.is_some() {
-        // Session was complete, exit with code 0
         Ok(())
     } else {
-        // Session was not complete, exit with error code
-        fail("Session interrupted before completion")
+        fail(
+            "Session interrupted before completion. Its reviews were saved, and will be recorded the next time you drill.",
+        )
     }
 }
 
 async fn script_handler(
     State(state): State<ServerState>,
 ) -> (StatusCode, [(HeaderName, &'static str); 1], String) {
+    let macros = state.mutable.lock().unwrap().macros.clone();
+    (
+        StatusCode::OK,
+        [(CONTENT_TYPE, "text/javascript")],
+        script(&macros),
+    )
+}
+
+/// The page script, with the collection's KaTeX macros.
+pub fn script(macros: &[(String, String)]) -> String {
     let mut content = String::new();
     content.push_str("let MACROS = {};\n");
-    for (name, definition) in &state.macros {
+    for (name, definition) in macros {
         let name = escape_js_string_literal(name);
         let definition = escape_js_string_literal(definition);
         content.push_str(&format!("MACROS['{name}'] = '{definition}';\n"));
     }
     content.push('\n');
     content.push_str(include_str!("script.js"));
-    (StatusCode::OK, [(CONTENT_TYPE, "text/javascript")], content)
+    content
 }
 
 fn escape_js_string_literal(s: &str) -> String {
@@ -230,7 +284,7 @@ fn escape_js_string_literal(s: &str) -> String {
         .replace('$', "\\$")
 }
 
-async fn style_handler() -> (StatusCode, [(HeaderName, &'static str); 2], &'static [u8]) {
+pub async fn style_handler() -> (StatusCode, [(HeaderName, &'static str); 2], &'static [u8]) {
     let bytes = include_bytes!("style.css");
     (
         StatusCode::OK,
@@ -250,8 +304,16 @@ async fn file_handler(
     State(state): State<ServerState>,
     Path(path): Path<String>,
 ) -> (StatusCode, [(HeaderName, &'static str); 1], Vec<u8>) {
-    let loader = MediaLoader::new(state.directory.clone());
-    let validated_path: PathBuf = match loader.validate(&path) {
+    media_file(state.directory.clone(), &path).await
+}
+
+/// Serve a media file from the collection.
+pub async fn media_file(
+    directory: PathBuf,
+    path: &str,
+) -> (StatusCode, [(HeaderName, &'static str); 1], Vec<u8>) {
+    let loader = MediaLoader::new(directory);
+    let validated_path: PathBuf = match loader.validate(path) {
         Ok(p) => p,
         Err(_) => {
             return (
@@ -310,13 +372,71 @@ async fn shutdown_signal(shutdown_rx: Receiver<()>) {
     }
 }
 
-fn filter_deck(
+/// Start a new session with the cards due today, in the order they will be
+/// drilled. Returns `None` if no cards are due.
+fn new_session(
     db: &Database,
+    cards: Vec<Card>,
+    today: Date,
+    config: &ServerConfig,
+) -> Fallible<Option<SessionState>> {
+    // Find cards due today, and their performance.
+    let performance: HashMap<CardHash, Performance> = db.due_today(today)?;
+    let due_today: Vec<Card> = cards
+        .into_iter()
+        .filter(|card| performance.contains_key(&card.hash()))
+        .collect::<Vec<_>>();
+
+    let due_today: Vec<Card> = filter_deck(
+        &performance,
+        due_today,
+        config.card_limit,
+        config.new_card_limit,
+        config.deck_filter.clone(),
+    );
+
+    let due_today: Vec<Card> = if config.bury_siblings {
+        bury_siblings(due_today)
+    } else {
+        due_today
+    };
+
+    if due_today.is_empty() {
+        return Ok(None);
+    }
+
+    // Finally, shuffle the cards.
+    let due_today: Vec<Card> = if config.shuffle {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos() as u64;
+        let mut rng = TinyRng::from_seed(seed);
+        shuffle(due_today, &mut rng)
+    } else {
+        due_today
+    };
+
+    // Store the performance of the cards due today in the cache.
+    let mut cache = Cache::new();
+    for card in due_today.iter() {
+        cache.insert(card.hash(), performance[&card.hash()])?;
+    }
+
+    Ok(Some(SessionState {
+        cards: due_today,
+        reviews: Vec::new(),
+        cache,
+    }))
+}
+
+fn filter_deck(
+    performance: &HashMap<CardHash, Performance>,
     deck: Vec<Card>,
     card_limit: Option<usize>,
     new_card_limit: Option<usize>,
     deck_filter: Option<String>,
-) -> Fallible<Vec<Card>> {
+) -> Vec<Card> {
     // Apply the deck filter.
     let deck = match deck_filter {
         Some(filter) => deck
@@ -333,12 +453,12 @@ fn filter_deck(
     };
 
     // Apply the new card limit.
-    let deck = match new_card_limit {
+    match new_card_limit {
         Some(limit) => {
             let mut new_count = 0;
             let mut result = Vec::new();
             for card in deck.into_iter() {
-                if db.get_card_performance(card.hash())?.is_new() {
+                if performance[&card.hash()].is_new() {
                     if new_count < limit {
                         result.push(card);
                         new_count += 1;
@@ -350,9 +470,7 @@ fn filter_deck(
             result
         }
         None => deck,
-    };
-
-    Ok(deck)
+    }
 }
 
 fn bury_siblings(deck: Vec<Card>) -> Vec<Card> {
@@ -26,6 +26,7 @@ use crate::fsrs::Difficulty;
 use crate::fsrs::Grade;
 use crate::fsrs::Stability;
 use crate::types::card::Card;
+use crate::types::card_hash::CardHash;
 use crate::types::date::Date;
 use crate::types::timestamp::Timestamp;
 
@@ -33,21 +34,32 @@ use crate::types::timestamp::Timestamp;
 pub struct ServerState {
     pub port: u16,
     pub directory: PathBuf,
-    pub macros: Vec<(String, String)>,
+    /// The path of the collection's database, to open other connections to
+    /// it.
+    pub database: PathBuf,
     pub total_cards: usize,
     pub session_started_at: Timestamp,
     pub mutable: Arc<Mutex<MutableState>>,
     pub shutdown_tx: Arc<Mutex<Option<Sender<()>>>>,
     pub answer_controls: AnswerControls,
+    /// The command template to open cards in an editor, from the collection's
+    /// configuration.
+    pub editor: Option<String>,
 }
 
 pub struct MutableState {
     pub reveal: bool,
     pub db: Database,
+    /// The pending session in the database, where reviews are journaled as
+    /// they happen.
+    pub pending_session_id: i64,
     pub cache: Cache,
     pub cards: Vec<Card>,
     pub reviews: Vec<Review>,
     pub finished_at: Option<Timestamp>,
+    pub macros: Vec<(String, String)>,
+    /// Incremented every time the collection is reloaded from disk.
+    pub revision: u64,
 }
 
 #[derive(Clone)]
@@ -60,9 +72,32 @@ pub struct Review {
     pub interval_raw: f64,
     pub interval_days: i64,
     pub due_date: Date,
+    pub duration_ms: Option<i64>,
+}
+
+impl MutableState {
+    /// The hashes of the cards left to drill, in order.
+    pub fn queue(&self) -> Vec<CardHash> {
+        self.cards.iter().map(Card::hash).collect()
+    }
 }
 
 impl Review {
+    /// A review recorded in the database, of the given card.
+    pub fn from_record(card: Card, record: &ReviewRecord) -> Self {
+        Self {
+            card,
+            reviewed_at: record.reviewed_at,
+            grade: record.grade,
+            stability: record.stability,
+            difficulty: record.difficulty,
+            interval_raw: record.interval_raw,
+            interval_days: record.interval_days,
+            due_date: record.due_date,
+            duration_ms: record.duration_ms,
+        }
+    }
+
     pub fn should_repeat(&self) -> bool {
         self.grade == Grade::Forgot || self.grade == Grade::Hard
     }
@@ -77,6 +112,7 @@ impl Review {
             interval_raw: self.interval_raw,
             interval_days: self.interval_days,
             due_date: self.due_date,
+            duration_ms: self.duration_ms,
         }
     }
 }
@@ -26,6 +26,11 @@ const HIGHLIGHT_CSS_URL: &str =
     "https://cdnjs.cloudflare.com/ajax/libs/highlight.js/11.9.0/styles/github.min.css";
 
 pub fn page_template(body: Markup) -> Markup {
+    page_with_stylesheets(body, &[])
+}
+
+/// A page with extra stylesheets, loaded after the drill UI's.
+pub fn page_with_stylesheets(body: Markup, stylesheets: &[&str]) -> Markup {
     html! {
         (DOCTYPE)
         html lang="en" {
@@ -39,6 +44,9 @@ pub fn page_template(body: Markup) -> Markup {
                 script defer src=(KATEX_MHCHEM_JS_URL) {};
                 script defer src=(HIGHLIGHT_JS_URL) {};
                 link rel="stylesheet" href="/style.css";
+                @for stylesheet in stylesheets {
+                    link rel="stylesheet" href=(stylesheet);
+                }
                 style { ".card-content { opacity: 0; }" }
                 noscript { style { ".card-content { opacity: 1; }" }}
             }
@@ -0,0 +1,841 @@
+// Copyright 2025 Fernando Borretti
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Rendering cards as styled terminal text, for the terminal UI. Cards are
+//! rendered from the HTML that both deck formats produce, so the terminal
+//! shows the same thing as the web page, minus images and typeset math.
+
+use crossterm::style::Attribute;
+use crossterm::style::Color;
+use crossterm::style::ContentStyle;
+
+/// A run of text in a single style.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Span {
+    pub text: String,
+    pub style: ContentStyle,
+}
+
+pub type Line = Vec<Span>;
+
+/// Render HTML to lines of styled text.
+pub fn html_to_lines(html: &str) -> Vec<Line> {
+    let mut renderer = Renderer::default();
+    let mut rest = html;
+    while !rest.is_empty() {
+        match rest.find('<') {
+            Some(0) => match rest.find('>') {
+                Some(end) => {
+                    renderer.tag(&rest[1..end]);
+                    rest = &rest[end + 1..];
+                }
+                None => {
+                    renderer.text(rest);
+                    rest = "";
+                }
+            },
+            Some(start) => {
+                renderer.text(&decode_entities(&rest[..start]));
+                rest = &rest[start..];
+            }
+            None => {
+                renderer.text(&decode_entities(rest));
+                rest = "";
+            }
+        }
+    }
+    renderer.finish()
+}
+
+/// An open element, and the style it applies to its contents.
+struct Open {
+    name: String,
+    style: ContentStyle,
+    math: bool,
+}
+
+#[derive(Default)]
+struct Renderer {
+    lines: Vec<Line>,
+    current: Line,
+    /// Whether the current line only holds a list item's marker.
+    marker_only: bool,
+    open: Vec<Open>,
+    /// The open lists: `None` for unordered lists, and the next number for
+    /// ordered lists.
+    lists: Vec<Option<usize>>,
+    pre: bool,
+    /// The TeX of the math element being read, and whether it is display math.
+    math: Option<(String, bool)>,
+    /// Whether a blank line goes before the next block.
+    blank: bool,
+    /// Whether the next table cell is the first in its row.
+    first_cell: bool,
+}
+
+impl Renderer {
+    fn tag(&mut self, tag: &str) {
+        let closing = tag.starts_with('/');
+        let tag = tag.trim_start_matches('/').trim_end_matches('/');
+        let name: String = tag
+            .split(|c: char| c.is_whitespace())
+            .next()
+            .unwrap_or("")
+            .to_lowercase();
+        if closing {
+            self.close(&name);
+        } else {
+            self.open(&name, tag);
+        }
+    }
+
+    fn open(&mut self, name: &str, tag: &str) {
+        match name {
+            "p" | "div" | "blockquote" | "table" => self.start_block(),
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.start_block();
+                self.push_style(name, style(None, &[Attribute::Bold, Attribute::Underlined]));
+            }
+            "strong" | "b" => self.push_style(name, style(None, &[Attribute::Bold])),
+            "em" | "i" => self.push_style(name, style(None, &[Attribute::Italic])),
+            "del" | "s" => self.push_style(name, style(None, &[Attribute::CrossedOut])),
+            "a" => self.push_style(name, style(None, &[Attribute::Underlined])),
+            "code" if !self.pre => self.push_style(name, style(Some(Color::Yellow), &[])),
+            "pre" => {
+                self.start_block();
+                self.pre = true;
+                self.push_style(name, style(Some(Color::Yellow), &[]));
+            }
+            "span" => {
+                let class = attribute(tag, "class").unwrap_or_default();
+                let classes: Vec<&str> = class.split_whitespace().collect();
+                let math = classes.contains(&"math-inline") || classes.contains(&"math-display");
+                if math {
+                    self.math = Some((String::new(), classes.contains(&"math-display")));
+                }
+                let style = if classes.contains(&"cloze") {
+                    style(Some(Color::Cyan), &[Attribute::Bold])
+                } else if classes.contains(&"cloze-reveal") {
+                    style(Some(Color::Green), &[Attribute::Bold])
+                } else {
+                    ContentStyle::default()
+                };
+                self.open.push(Open {
+                    name: name.to_string(),
+                    style,
+                    math,
+                });
+            }
+            "br" => self.flush(),
+            "hr" => {
+                self.start_block();
+                self.push_text("────────────────");
+                self.end_block();
+            }
+            "ul" | "ol" => {
+                if self.lists.is_empty() {
+                    self.start_block();
+                } else if !self.current.is_empty() {
+                    self.flush();
+                }
+                let start = attribute(tag, "start").and_then(|start| start.parse().ok());
+                self.lists
+                    .push((name == "ol").then_some(start.unwrap_or(1)));
+            }
+            "li" => {
+                if !self.current.is_empty() {
+                    self.flush();
+                }
+                let depth = self.lists.len().saturating_sub(1);
+                let marker = match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        format!("{}. ", *number - 1)
+                    }
+                    _ => "• ".to_string(),
+                };
+                self.push_text(&format!("{}{marker}", "  ".repeat(depth)));
+                self.marker_only = true;
+            }
+            "tr" => {
+                if !self.current.is_empty() {
+                    self.flush();
+                }
+                self.first_cell = true;
+            }
+            "td" | "th" => {
+                if !self.first_cell {
+                    self.push_text(" │ ");
+                }
+                self.first_cell = false;
+                if name == "th" {
+                    self.push_style(name, style(None, &[Attribute::Bold]));
+                }
+            }
+            "img" => {
+                let alt = attribute(tag, "alt").filter(|alt| !alt.is_empty());
+                let src = attribute(tag, "src").unwrap_or_default();
+                let label = format!("[image: {}]", alt.unwrap_or(src));
+                self.push_span(label, style(Some(Color::DarkGrey), &[]));
+            }
+            "audio" => {
+                let src = attribute(tag, "src").unwrap_or_default();
+                self.push_span(format!("[audio: {src}]"), style(Some(Color::DarkGrey), &[]));
+            }
+            _ => {}
+        }
+    }
+
+    fn close(&mut self, name: &str) {
+        match name {
+            "p" | "div" | "blockquote" | "table" => self.end_block(),
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.pop_style(name);
+                self.end_block();
+            }
+            "pre" => {
+                self.pop_style(name);
+                self.pre = false;
+                self.end_block();
+            }
+            "ul" | "ol" => {
+                self.lists.pop();
+                if self.lists.is_empty() {
+                    self.end_block();
+                } else if !self.current.is_empty() {
+                    self.flush();
+                }
+            }
+            "span" => {
+                if let Some(open) = self.pop_style(name) {
+                    if open.math {
+                        self.finish_math();
+                    }
+                }
+            }
+            _ => {
+                self.pop_style(name);
+            }
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        if let Some((tex, _)) = &mut self.math {
+            tex.push_str(text);
+        } else if self.pre {
+            for (i, line) in text.split('\n').enumerate() {
+                if i > 0 {
+                    self.flush();
+                }
+                self.push_text(line);
+            }
+        } else {
+            let text = collapse_whitespace(text);
+            let text = if self.current.is_empty() || self.marker_only {
+                text.trim_start()
+            } else {
+                &text
+            };
+            if !text.is_empty() {
+                self.push_text(text);
+            }
+        }
+    }
+
+    fn finish_math(&mut self) {
+        let Some((tex, display)) = self.math.take() else {
+            return;
+        };
+        let text = tex_to_unicode(tex.trim());
+        let style = style(Some(Color::Magenta), &[]);
+        if display {
+            self.start_block();
+            self.push_span(format!("    {text}"), style);
+            self.end_block();
+        } else {
+            self.push_span(text, style);
+        }
+    }
+
+    fn push_style(&mut self, name: &str, style: ContentStyle) {
+        self.open.push(Open {
+            name: name.to_string(),
+            style,
+            math: false,
+        });
+    }
+
+    /// Close the innermost open element with the given name, and any elements
+    /// inside it that weren't closed.
+    fn pop_style(&mut self, name: &str) -> Option<Open> {
+        let index = self.open.iter().rposition(|open| open.name == name)?;
+        self.open.drain(index..).next()
+    }
+
+    fn current_style(&self) -> ContentStyle {
+        let mut current = ContentStyle::default();
+        for open in &self.open {
+            if open.style.foreground_color.is_some() {
+                current.foreground_color = open.style.foreground_color;
+            }
+            current.attributes.extend(open.style.attributes);
+        }
+        current
+    }
+
+    fn push_text(&mut self, text: &str) {
+        self.push_span(text.to_string(), self.current_style());
+    }
+
+    fn push_span(&mut self, text: String, style: ContentStyle) {
+        if text.is_empty() {
+            return;
+        }
+        self.marker_only = false;
+        match self.current.last_mut() {
+            Some(last) if last.style == style => last.text.push_str(&text),
+            _ => self.current.push(Span { text, style }),
+        }
+    }
+
+    fn flush(&mut self) {
+        let mut line = std::mem::take(&mut self.current);
+        if !self.pre {
+            if let Some(last) = line.last_mut() {
+                last.text.truncate(last.text.trim_end().len());
+            }
+        }
+        self.lines.push(line);
+        self.marker_only = false;
+    }
+
+    fn start_block(&mut self) {
+        if self.marker_only {
+            return;
+        }
+        if !self.current.is_empty() {
+            self.flush();
+        }
+        if self.blank && !self.lines.is_empty() {
+            self.lines.push(Vec::new());
+        }
+        self.blank = false;
+    }
+
+    fn end_block(&mut self) {
+        if !self.current.is_empty() {
+            self.flush();
+        }
+        // Blocks inside list items aren't separated.
+        self.blank = self.lists.is_empty();
+    }
+
+    fn finish(mut self) -> Vec<Line> {
+        if !self.current.is_empty() {
+            self.flush();
+        }
+        while self.lines.last().is_some_and(|line| line.is_empty()) {
+            self.lines.pop();
+        }
+        self.lines
+    }
+}
+
+/// Wrap lines to the given width, breaking at spaces where possible.
+pub fn wrap(lines: &[Line], width: usize) -> Vec<Line> {
+    let width = width.max(1);
+    let mut wrapped = Vec::new();
+    for line in lines {
+        let mut chars: Vec<(char, ContentStyle)> = line
+            .iter()
+            .flat_map(|span| span.text.chars().map(|c| (c, span.style)))
+            .collect();
+        loop {
+            if chars.len() <= width {
+                wrapped.push(join(&chars));
+                break;
+            }
+            // Break at the last space that fits, or mid-word if there is none.
+            match chars[..=width].iter().rposition(|(c, _)| *c == ' ') {
+                Some(space) if space > 0 => {
+                    wrapped.push(join(&chars[..space]));
+                    chars.drain(..=space);
+                }
+                _ => {
+                    wrapped.push(join(&chars[..width]));
+                    chars.drain(..width);
+                }
+            }
+        }
+    }
+    wrapped
+}
+
+fn join(chars: &[(char, ContentStyle)]) -> Line {
+    let mut line: Line = Vec::new();
+    for (c, style) in chars {
+        match line.last_mut() {
+            Some(last) if last.style == *style => last.text.push(*c),
+            _ => line.push(Span {
+                text: c.to_string(),
+                style: *style,
+            }),
+        }
+    }
+    line
+}
+
+fn style(color: Option<Color>, attributes: &[Attribute]) -> ContentStyle {
+    let mut style = ContentStyle::new();
+    style.foreground_color = color;
+    for attribute in attributes {
+        style.attributes.set(*attribute);
+    }
+    style
+}
+
+/// The value of an attribute in the text of a tag.
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let start = tag.find(&format!(" {name}="))? + name.len() + 2;
+    let rest = &tag[start..];
+    let quote = rest.chars().next()?;
+    let value = if quote == '"' || quote == '\'' {
+        let end = rest[1..].find(quote)?;
+        &rest[1..end + 1]
+    } else {
+        rest.split_whitespace().next()?
+    };
+    Some(decode_entities(value))
+}
+
+fn decode_entities(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let decoded = rest.find(';').and_then(|end| {
+            let entity = &rest[1..end];
+            let c = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some('\u{a0}'),
+                _ => match entity.strip_prefix("#x").or(entity.strip_prefix("#X")) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok().and_then(char::from_u32),
+                    None => entity
+                        .strip_prefix('#')
+                        .and_then(|decimal| decimal.parse().ok())
+                        .and_then(char::from_u32),
+                },
+            };
+            c.map(|c| (c, end))
+        });
+        match decoded {
+            Some((c, end)) => {
+                result.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                result.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Replace every run of whitespace with a single space.
+fn collapse_whitespace(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut last_was_space = false;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            if !last_was_space {
+                result.push(' ');
+            }
+            last_was_space = true;
+        } else {
+            result.push(c);
+            last_was_space = false;
+        }
+    }
+    result
+}
+
+const SYMBOLS: &[(&str, &str)] = &[
+    ("alpha", "α"),
+    ("beta", "β"),
+    ("gamma", "γ"),
+    ("delta", "δ"),
+    ("epsilon", "ε"),
+    ("varepsilon", "ε"),
+    ("zeta", "ζ"),
+    ("eta", "η"),
+    ("theta", "θ"),
+    ("vartheta", "ϑ"),
+    ("iota", "ι"),
+    ("kappa", "κ"),
+    ("lambda", "λ"),
+    ("mu", "μ"),
+    ("nu", "ν"),
+    ("xi", "ξ"),
+    ("pi", "π"),
+    ("rho", "ρ"),
+    ("sigma", "σ"),
+    ("tau", "τ"),
+    ("upsilon", "υ"),
+    ("phi", "φ"),
+    ("varphi", "φ"),
+    ("chi", "χ"),
+    ("psi", "ψ"),
+    ("omega", "ω"),
+    ("Gamma", "Γ"),
+    ("Delta", "Δ"),
+    ("Theta", "Θ"),
+    ("Lambda", "Λ"),
+    ("Xi", "Ξ"),
+    ("Pi", "Π"),
+    ("Sigma", "Σ"),
+    ("Phi", "Φ"),
+    ("Psi", "Ψ"),
+    ("Omega", "Ω"),
+    ("times", "×"),
+    ("cdot", "·"),
+    ("div", "÷"),
+    ("pm", "±"),
+    ("mp", "∓"),
+    ("leq", "≤"),
+    ("le", "≤"),
+    ("geq", "≥"),
+    ("ge", "≥"),
+    ("neq", "≠"),
+    ("ne", "≠"),
+    ("approx", "≈"),
+    ("equiv", "≡"),
+    ("sim", "∼"),
+    ("propto", "∝"),
+    ("infty", "∞"),
+    ("partial", "∂"),
+    ("nabla", "∇"),
+    ("sum", "∑"),
+    ("prod", "∏"),
+    ("int", "∫"),
+    ("oint", "∮"),
+    ("in", "∈"),
+    ("notin", "∉"),
+    ("subset", "⊂"),
+    ("subseteq", "⊆"),
+    ("supset", "⊃"),
+    ("supseteq", "⊇"),
+    ("cup", "∪"),
+    ("cap", "∩"),
+    ("emptyset", "∅"),
+    ("varnothing", "∅"),
+    ("forall", "∀"),
+    ("exists", "∃"),
+    ("neg", "¬"),
+    ("lnot", "¬"),
+    ("land", "∧"),
+    ("wedge", "∧"),
+    ("lor", "∨"),
+    ("vee", "∨"),
+    ("to", "→"),
+    ("rightarrow", "→"),
+    ("leftarrow", "←"),
+    ("leftrightarrow", "↔"),
+    ("Rightarrow", "⇒"),
+    ("Leftarrow", "⇐"),
+    ("Leftrightarrow", "⇔"),
+    ("implies", "⟹"),
+    ("iff", "⟺"),
+    ("mapsto", "↦"),
+    ("ldots", "…"),
+    ("dots", "…"),
+    ("cdots", "⋯"),
+    ("circ", "∘"),
+    ("angle", "∠"),
+    ("perp", "⊥"),
+    ("parallel", "∥"),
+    ("mid", "∣"),
+    ("hbar", "ℏ"),
+    ("ell", "ℓ"),
+    ("aleph", "ℵ"),
+    ("langle", "⟨"),
+    ("rangle", "⟩"),
+    ("star", "⋆"),
+    ("ast", "∗"),
+    ("quad", "  "),
+    ("qquad", "    "),
+];
+
+const SUPERSCRIPTS: &[(char, char)] = &[
+    ('0', '⁰'),
+    ('1', '¹'),
+    ('2', '²'),
+    ('3', '³'),
+    ('4', '⁴'),
+    ('5', '⁵'),
+    ('6', '⁶'),
+    ('7', '⁷'),
+    ('8', '⁸'),
+    ('9', '⁹'),
+    ('+', '⁺'),
+    ('-', '⁻'),
+    ('=', '⁼'),
+    ('(', '⁽'),
+    (')', '⁾'),
+    ('i', 'ⁱ'),
+    ('n', 'ⁿ'),
+];
+
+const SUBSCRIPTS: &[(char, char)] = &[
+    ('0', '₀'),
+    ('1', '₁'),
+    ('2', '₂'),
+    ('3', '₃'),
+    ('4', '₄'),
+    ('5', '₅'),
+    ('6', '₆'),
+    ('7', '₇'),
+    ('8', '₈'),
+    ('9', '₉'),
+    ('+', '₊'),
+    ('-', '₋'),
+    ('=', '₌'),
+    ('(', '₍'),
+    (')', '₎'),
+    ('a', 'ₐ'),
+    ('e', 'ₑ'),
+    ('i', 'ᵢ'),
+    ('j', 'ⱼ'),
+    ('k', 'ₖ'),
+    ('m', 'ₘ'),
+    ('n', 'ₙ'),
+    ('o', 'ₒ'),
+    ('t', 'ₜ'),
+    ('x', 'ₓ'),
+];
+
+/// Approximate TeX math with Unicode: Greek letters and common symbols are
+/// replaced, simple superscripts and subscripts are raised and lowered, and
+/// fractions and roots are written inline. Anything else is left as TeX.
+pub fn tex_to_unicode(tex: &str) -> String {
+    let mut converter = TexConverter {
+        chars: tex.chars().collect(),
+        position: 0,
+    };
+    converter.convert_until(None)
+}
+
+struct TexConverter {
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl TexConverter {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    /// Convert up to the given closing character, which is consumed, or the
+    /// end of the input.
+    fn convert_until(&mut self, close: Option<char>) -> String {
+        let mut output = String::new();
+        while let Some(c) = self.peek() {
+            self.position += 1;
+            match c {
+                _ if Some(c) == close => return output,
+                '{' => output.push_str(&self.convert_until(Some('}'))),
+                '\\' => output.push_str(&self.command()),
+                '^' => output.push_str(&self.script('^', SUPERSCRIPTS)),
+                '_' => output.push_str(&self.script('_', SUBSCRIPTS)),
+                _ => output.push(c),
+            }
+        }
+        output
+    }
+
+    /// Convert a command, after its backslash.
+    fn command(&mut self) -> String {
+        let start = self.position;
+        while self.peek().is_some_and(|c| c.is_ascii_alphabetic()) {
+            self.position += 1;
+        }
+        let name: String = self.chars[start..self.position].iter().collect();
+        if name.is_empty() {
+            // A control symbol, like `\,` or `\{`.
+            let Some(c) = self.peek() else {
+                return "\\".to_string();
+            };
+            self.position += 1;
+            return match c {
+                ',' | ';' | ':' | ' ' => " ".to_string(),
+                '!' => String::new(),
+                '\\' => " ".to_string(),
+                _ => c.to_string(),
+            };
+        }
+        match name.as_str() {
+            "frac" | "dfrac" | "tfrac" => {
+                let numerator = self.argument();
+                let denominator = self.argument();
+                format!(
+                    "{}/{}",
+                    parenthesize(&numerator),
+                    parenthesize(&denominator)
+                )
+            }
+            "sqrt" => format!("√{}", parenthesize(&self.argument())),
+            "text" | "mathrm" | "mathit" | "mathbf" | "mathsf" | "operatorname" => self.argument(),
+            "mathbb" => {
+                let argument = self.argument();
+                match argument.as_str() {
+                    "N" => "ℕ".to_string(),
+                    "Z" => "ℤ".to_string(),
+                    "Q" => "ℚ".to_string(),
+                    "R" => "ℝ".to_string(),
+                    "C" => "ℂ".to_string(),
+                    _ => argument,
+                }
+            }
+            "left" | "right" | "big" | "Big" | "bigg" | "Bigg" => String::new(),
+            _ => match SYMBOLS.iter().find(|(symbol, _)| *symbol == name) {
+                Some((_, unicode)) => unicode.to_string(),
+                None => format!("\\{name}"),
+            },
+        }
+    }
+
+    /// Read the argument of a command: a group, a command, or a character.
+    fn argument(&mut self) -> String {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.position += 1;
+        }
+        match self.peek() {
+            Some('{') => {
+                self.position += 1;
+                self.convert_until(Some('}'))
+            }
+            Some('\\') => {
+                self.position += 1;
+                self.command()
+            }
+            Some(c) => {
+                self.position += 1;
+                c.to_string()
+            }
+            None => String::new(),
+        }
+    }
+
+    /// Convert a superscript or subscript, using the Unicode forms if every
+    /// character has one.
+    fn script(&mut self, marker: char, table: &[(char, char)]) -> String {
+        let argument = self.argument();
+        let converted: Option<String> = argument
+            .chars()
+            .map(|c| table.iter().find(|(from, _)| *from == c).map(|(_, to)| *to))
+            .collect();
+        match converted {
+            Some(converted) if !converted.is_empty() => converted,
+            _ if argument.chars().count() == 1 => format!("{marker}{argument}"),
+            _ => format!("{marker}({argument})"),
+        }
+    }
+}
+
+/// Put parentheses around text longer than one character.
+fn parenthesize(text: &str) -> String {
+    if text.chars().count() > 1 {
+        format!("({text})")
+    } else {
+        text.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plain(lines: &[Line]) -> Vec<String> {
+        lines
+            .iter()
+            .map(|line| line.iter().map(|span| span.text.as_str()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_html_to_lines() {
+        let html = "<h1>Title</h1>\n<p>Some <em>text</em> &amp;\nmore.</p>\n<ul>\n<li>one</li>\n<li>two</li>\n</ul>\n<pre><code>fn main() {\n}\n</code></pre>\n";
+        let lines = html_to_lines(html);
+        assert_eq!(
+            plain(&lines),
+            vec![
+                "Title",
+                "",
+                "Some text & more.",
+                "",
+                "• one",
+                "• two",
+                "",
+                "fn main() {",
+                "}",
+            ]
+        );
+        assert!(lines[0][0].style.attributes.has(Attribute::Bold));
+        assert!(lines[2][1].style.attributes.has(Attribute::Italic));
+    }
+
+    #[test]
+    fn test_cloze_and_math() {
+        let html = "<p>The <span class='cloze-reveal'>answer</span> is <span class=\"math math-inline\">x^2 + \\alpha</span>.</p>\n<p><img src=\"http://localhost:8000/file/a.png\" alt=\"A cat\" /></p>";
+        let lines = html_to_lines(html);
+        assert_eq!(
+            plain(&lines),
+            vec!["The answer is x² + α.", "", "[image: A cat]"]
+        );
+        assert_eq!(lines[0][1].style.foreground_color, Some(Color::Green));
+    }
+
+    #[test]
+    fn test_ordered_list() {
+        let html = "<ol start=\"3\">\n<li><p>three</p>\n</li>\n<li><p>four</p>\n</li>\n</ol>";
+        assert_eq!(plain(&html_to_lines(html)), vec!["3. three", "4. four"]);
+    }
+
+    #[test]
+    fn test_wrap() {
+        let lines =
+            html_to_lines("<p>The quick <strong>brown</strong> fox jumps</p><p>abcdefghij</p>");
+        assert_eq!(
+            plain(&wrap(&lines, 9)),
+            vec!["The quick", "brown fox", "jumps", "", "abcdefghi", "j"]
+        );
+    }
+
+    #[test]
+    fn test_tex_to_unicode() {
+        assert_eq!(tex_to_unicode("\\alpha + \\beta"), "α + β");
+        assert_eq!(tex_to_unicode("x_{n+1} = x_n^2"), "xₙ₊₁ = xₙ²");
+        assert_eq!(tex_to_unicode("\\frac{a+b}{2}"), "(a+b)/2");
+        assert_eq!(tex_to_unicode("\\sqrt{2}"), "√2");
+        assert_eq!(tex_to_unicode("e^{i\\pi}"), "e^(iπ)");
+        assert_eq!(tex_to_unicode("\\mathbb{R}"), "ℝ");
+        assert_eq!(tex_to_unicode("\\unknown"), "\\unknown");
+    }
+}
@@ -0,0 +1,473 @@
+// Copyright 2025 Fernando Borretti
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Drilling in the terminal, without the web server. The session is the same
+//! as the web UI's, and actions go through the same code.
+
+use std::io::Stdout;
+use std::io::Write;
+use std::io::stdout;
+use std::time::Duration;
+use std::time::Instant;
+
+use crossterm::cursor::Hide;
+use crossterm::cursor::MoveTo;
+use crossterm::cursor::Show;
+use crossterm::event::Event;
+use crossterm::event::KeyCode;
+use crossterm::event::KeyEvent;
+use crossterm::event::KeyEventKind;
+use crossterm::event::KeyModifiers;
+use crossterm::event::poll;
+use crossterm::event::read;
+use crossterm::execute;
+use crossterm::queue;
+use crossterm::style::Attribute;
+use crossterm::style::Color;
+use crossterm::style::ContentStyle;
+use crossterm::style::PrintStyledContent;
+use crossterm::terminal::Clear;
+use crossterm::terminal::ClearType;
+use crossterm::terminal::EnterAlternateScreen;
+use crossterm::terminal::LeaveAlternateScreen;
+use crossterm::terminal::disable_raw_mode;
+use crossterm::terminal::enable_raw_mode;
+use crossterm::terminal::size;
+
+use crate::cmd::drill::editor::card_editor_command;
+use crate::cmd::drill::get::render_config;
+use crate::cmd::drill::post::Action;
+use crate::cmd::drill::post::apply_action;
+use crate::cmd::drill::reload::watch_collection;
+use crate::cmd::drill::server::AnswerControls;
+use crate::cmd::drill::server::ServerConfig;
+use crate::cmd::drill::server::load_session;
+use crate::cmd::drill::server::session_result;
+use crate::cmd::drill::state::MutableState;
+use crate::cmd::drill::state::ServerState;
+use crate::cmd::drill::terminal::Line;
+use crate::cmd::drill::terminal::Span;
+use crate::cmd::drill::terminal::html_to_lines;
+use crate::cmd::drill::terminal::wrap;
+use crate::error::Fallible;
+use crate::types::card::CardType;
+
+const REDRAW_INTERVAL: Duration = Duration::from_millis(500);
+
+pub fn start_tui(config: ServerConfig) -> Fallible<()> {
+    let Some(state) = load_session(&config, None)? else {
+        println!("No cards due today.");
+        return Ok(());
+    };
+
+    // Reload the collection when its files change.
+    let _watcher = match watch_collection(state.clone()) {
+        Ok(watcher) => Some(watcher),
+        Err(e) => {
+            log::warn!("Not watching the collection for changes: {e}");
+            None
+        }
+    };
+    {
+        let _terminal = RawTerminal::enter()?;
+        run(&state)?;
+    }
+    session_result(&state)
+}
+
+/// The terminal in raw mode, on the alternate screen. It is restored when
+/// dropped, including when drilling fails.
+struct RawTerminal;
+
+impl RawTerminal {
+    fn enter() -> Fallible<Self> {
+        enter_raw_mode()?;
+        Ok(Self)
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        let _ = leave_raw_mode();
+    }
+}
+
+fn enter_raw_mode() -> Fallible<()> {
+    enable_raw_mode()?;
+    execute!(stdout(), EnterAlternateScreen, Hide)?;
+    Ok(())
+}
+
+fn leave_raw_mode() -> Fallible<()> {
+    execute!(stdout(), Show, LeaveAlternateScreen)?;
+    disable_raw_mode()?;
+    Ok(())
+}
+
+/// What a key press does.
+#[derive(Debug, PartialEq)]
+enum Command {
+    Action(Action),
+    /// Ask whether to end the session, which is only ended if `y` is pressed
+    /// next.
+    End,
+    /// Open the current card in the editor, and wait for it to exit.
+    Edit,
+    ScrollUp,
+    ScrollDown,
+    /// Leave the terminal UI. An unfinished session is left to be resumed.
+    Quit,
+}
+
+/// The keys are the web UI's shortcuts, plus keys for its buttons, scrolling,
+/// and quitting. Ending the session has to be confirmed, since `e` is easy to
+/// press by mistake.
+fn key_command(key: &KeyEvent, controls: AnswerControls, finished: bool) -> Option<Command> {
+    if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+        return Some(Command::Quit);
+    }
+    if !(key.modifiers - KeyModifiers::SHIFT).is_empty() {
+        return None;
+    }
+    if finished {
+        return match key.code {
+            KeyCode::Enter | KeyCode::Esc | KeyCode::Char('q') => Some(Command::Quit),
+            _ => None,
+        };
+    }
+    let binary = controls == AnswerControls::Binary;
+    match key.code {
+        KeyCode::Char(' ') => Some(Command::Action(Action::Reveal)),
+        KeyCode::Char('u') => Some(Command::Action(Action::Undo)),
+        KeyCode::Char('1') => Some(Command::Action(Action::Forgot)),
+        KeyCode::Char('2') if !binary => Some(Command::Action(Action::Hard)),
+        KeyCode::Char('3') => Some(Command::Action(Action::Good)),
+        KeyCode::Char('4') if !binary => Some(Command::Action(Action::Easy)),
+        KeyCode::Char('e') => Some(Command::End),
+        KeyCode::Char('o') => Some(Command::Edit),
+        KeyCode::Up | KeyCode::Char('k') => Some(Command::ScrollUp),
+        KeyCode::Down | KeyCode::Char('j') => Some(Command::ScrollDown),
+        KeyCode::Esc | KeyCode::Char('q') => Some(Command::Quit),
+        _ => None,
+    }
+}
+
+fn run(state: &ServerState) -> Fallible<()> {
+    let mut out = stdout();
+    let mut scroll: usize = 0;
+    // When the current card was shown, to record how long it took to answer.
+    let mut shown_at = Instant::now();
+    // The revision of the collection on screen, if the screen is up to date.
+    let mut drawn: Option<u64> = None;
+    // An error to show in place of the key hints, until the next key press.
+    let mut notice: Option<String> = None;
+    // Whether the user is being asked to confirm ending the session.
+    let mut confirming_end = false;
+    loop {
+        let (finished, screen) = {
+            let mutable = state.mutable.lock().unwrap();
+            let finished = mutable.finished_at.is_some();
+            let screen = if drawn == Some(mutable.revision) {
+                None
+            } else if finished {
+                Some((mutable.revision, completion_screen(state, &mutable)))
+            } else {
+                Some((mutable.revision, session_screen(state, &mutable)?))
+            };
+            (finished, screen)
+        };
+        if let Some((revision, mut screen)) = screen {
+            if let Some(notice) = notice.take() {
+                screen.footer = vec![span(notice, Some(Color::Red), &[])];
+            }
+            if confirming_end {
+                screen.footer = end_prompt();
+            }
+            scroll = draw(&mut out, &screen, scroll)?;
+            drawn = Some(revision);
+        }
+        // Wake up now and then to show changes to the collection.
+        if !poll(REDRAW_INTERVAL)? {
+            continue;
+        }
+        drawn = None;
+        let key = match read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => key,
+            _ => continue,
+        };
+        if confirming_end {
+            confirming_end = false;
+            if key.code == KeyCode::Char('y') && key.modifiers.is_empty() {
+                apply_action(state, Action::End, None)?;
+            }
+            continue;
+        }
+        match key_command(&key, state.answer_controls, finished) {
+            Some(Command::Action(action)) => {
+                let grade = matches!(
+                    action,
+                    Action::Forgot | Action::Hard | Action::Good | Action::Easy
+                );
+                let duration_ms = grade.then(|| shown_at.elapsed().as_millis() as i64);
+                let reveal = matches!(action, Action::Reveal);
+                if apply_action(state, action, duration_ms)? && !reveal {
+                    shown_at = Instant::now();
+                    scroll = 0;
+                }
+            }
+            Some(Command::End) => confirming_end = true,
+            Some(Command::Edit) => {
+                let command = {
+                    let mutable = state.mutable.lock().unwrap();
+                    card_editor_command(state.editor.as_deref(), &mutable.cards[0])
+                };
+                notice = match command {
+                    Ok(command) => edit(command)?,
+                    Err(e) => Some(e.to_string()),
+                };
+            }
+            Some(Command::ScrollUp) => scroll = scroll.saturating_sub(1),
+            Some(Command::ScrollDown) => scroll += 1,
+            Some(Command::Quit) => return Ok(()),
+            None => {}
+        }
+    }
+}
+
+/// Run the editor in the terminal, outside of raw mode, until it exits.
+/// Returns an error message if it couldn't be started.
+fn edit(mut command: std::process::Command) -> Fallible<Option<String>> {
+    leave_raw_mode()?;
+    let status = command.status();
+    enter_raw_mode()?;
+    Ok(status
+        .err()
+        .map(|e| format!("Failed to start the editor: {e}")))
+}
+
+/// What the terminal shows: a header, a body, which scrolls, and a footer.
+struct Screen {
+    header: Line,
+    body: Vec<Line>,
+    footer: Line,
+}
+
+/// Draw the screen, and return the scroll offset, clamped to the body.
+fn draw(out: &mut Stdout, screen: &Screen, scroll: usize) -> Fallible<usize> {
+    let (width, height) = size()?;
+    let width = width as usize;
+    let height = height as usize;
+    let body = wrap(&screen.body, width.saturating_sub(4));
+    // The header, a blank line, the body, a blank line, and the footer.
+    let rows = height.saturating_sub(4).max(1);
+    let scroll = scroll.min(body.len().saturating_sub(rows));
+    queue!(out, Clear(ClearType::All))?;
+    queue!(out, MoveTo(2, 0))?;
+    print_line(out, &screen.header)?;
+    for (row, line) in body.iter().skip(scroll).take(rows).enumerate() {
+        queue!(out, MoveTo(2, row as u16 + 2))?;
+        print_line(out, line)?;
+    }
+    queue!(out, MoveTo(2, height.saturating_sub(1) as u16))?;
+    print_line(out, &screen.footer)?;
+    out.flush()?;
+    Ok(scroll)
+}
+
+fn print_line(out: &mut Stdout, line: &Line) -> Fallible<()> {
+    for span in line {
+        queue!(
+            out,
+            PrintStyledContent(span.style.apply(span.text.as_str()))
+        )?;
+    }
+    Ok(())
+}
+
+fn span(text: impl Into<String>, color: Option<Color>, attributes: &[Attribute]) -> Span {
+    let mut style = ContentStyle::new();
+    style.foreground_color = color;
+    for attribute in attributes {
+        style.attributes.set(*attribute);
+    }
+    Span {
+        text: text.into(),
+        style,
+    }
+}
+
+fn session_screen(state: &ServerState, mutable: &MutableState) -> Fallible<Screen> {
+    let card = &mutable.cards[0];
+    let config = render_config(state, card)?;
+    let cards_done = state.total_cards - mutable.cards.len();
+    let header = vec![
+        span(card.deck_name().clone(), None, &[Attribute::Bold]),
+        span(
+            format!("  {cards_done}/{}", state.total_cards),
+            Some(Color::DarkGrey),
+            &[],
+        ),
+    ];
+    let mut body: Vec<Line> = Vec::new();
+    match card.card_type() {
+        CardType::Basic => {
+            body.extend(html_to_lines(&card.html_front(&config)?.into_string()));
+            if mutable.reveal {
+                body.push(Vec::new());
+                body.push(vec![span("────────", Some(Color::DarkGrey), &[])]);
+                body.push(Vec::new());
+                body.extend(html_to_lines(&card.html_back(&config)?.into_string()));
+            }
+        }
+        CardType::Cloze | CardType::List => {
+            let html = if mutable.reveal {
+                card.html_back(&config)?
+            } else {
+                card.html_front(&config)?
+            };
+            body.extend(html_to_lines(&html.into_string()));
+        }
+    }
+    if mutable.reveal {
+        if let Some(note) = card.html_note(&config)? {
+            body.push(Vec::new());
+            for mut line in html_to_lines(&note.into_string()) {
+                for span in line.iter_mut() {
+                    span.style.attributes.set(Attribute::Dim);
+                }
+                body.push(line);
+            }
+        }
+    }
+    let keys: Vec<(&str, &str)> = if mutable.reveal {
+        match state.answer_controls {
+            AnswerControls::Binary => vec![("1", "forgot"), ("3", "good")],
+            AnswerControls::Full => {
+                vec![("1", "forgot"), ("2", "hard"), ("3", "good"), ("4", "easy")]
+            }
+        }
+    } else {
+        vec![("space", "reveal")]
+    };
+    let mut keys = keys;
+    if !mutable.reviews.is_empty() {
+        keys.push(("u", "undo"));
+    }
+    keys.extend([("o", "edit"), ("e", "end"), ("q", "quit")]);
+    Ok(Screen {
+        header,
+        body,
+        footer: key_hints(&keys),
+    })
+}
+
+fn key_hints(keys: &[(&str, &str)]) -> Line {
+    let mut line = Vec::new();
+    for (i, (key, description)) in keys.iter().enumerate() {
+        if i > 0 {
+            line.push(span("  ", None, &[]));
+        }
+        line.push(span(*key, None, &[Attribute::Bold]));
+        line.push(span(format!(" {description}"), Some(Color::DarkGrey), &[]));
+    }
+    line
+}
+
+fn end_prompt() -> Line {
+    let mut line = vec![span("End the session?  ", None, &[])];
+    line.extend(key_hints(&[
+        ("y", "end"),
+        ("any other key", "keep drilling"),
+    ]));
+    line
+}
+
+fn completion_screen(state: &ServerState, mutable: &MutableState) -> Screen {
+    let cards_reviewed = state.total_cards - mutable.cards.len();
+    let start = state.session_started_at.into_inner();
+    let end = mutable.finished_at.unwrap().into_inner();
+    let duration_s = (end - start).num_seconds();
+    let pace: f64 = if cards_reviewed == 0 {
+        0.0
+    } else {
+        duration_s as f64 / cards_reviewed as f64
+    };
+    let stats = [
+        ("Total Cards", state.total_cards.to_string()),
+        ("Cards Reviewed", cards_reviewed.to_string()),
+        ("Duration (seconds)", duration_s.to_string()),
+        ("Pace (s/card)", format!("{pace:.2}")),
+    ];
+    let body = stats
+        .into_iter()
+        .map(|(key, value)| {
+            vec![
+                span(format!("{key:<20}"), Some(Color::DarkGrey), &[]),
+                span(value, None, &[]),
+            ]
+        })
+        .collect();
+    Screen {
+        header: vec![span("Session Completed", None, &[Attribute::Bold])],
+        body,
+        footer: key_hints(&[("enter", "exit")]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(c: char) -> KeyEvent {
+        KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn test_key_command() {
+        let full = AnswerControls::Full;
+        let binary = AnswerControls::Binary;
+        assert_eq!(
+            key_command(&key(' '), full, false),
+            Some(Command::Action(Action::Reveal))
+        );
+        assert_eq!(
+            key_command(&key('u'), full, false),
+            Some(Command::Action(Action::Undo))
+        );
+        assert_eq!(
+            key_command(&key('2'), full, false),
+            Some(Command::Action(Action::Hard))
+        );
+        assert_eq!(key_command(&key('o'), full, false), Some(Command::Edit));
+        assert_eq!(key_command(&key('e'), full, false), Some(Command::End));
+        assert_eq!(key_command(&key('2'), binary, false), None);
+        assert_eq!(
+            key_command(&key('3'), binary, false),
+            Some(Command::Action(Action::Good))
+        );
+        let alt = KeyEvent::new(KeyCode::Char('3'), KeyModifiers::ALT);
+        assert_eq!(key_command(&alt, full, false), None);
+        let ctrl_c = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
+        assert_eq!(key_command(&ctrl_c, full, false), Some(Command::Quit));
+        assert_eq!(key_command(&key('3'), full, true), None);
+        assert_eq!(
+            key_command(
+                &KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE),
+                full,
+                true
+            ),
+            Some(Command::Quit)
+        );
+    }
+}
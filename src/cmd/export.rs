@@ -25,6 +25,7 @@ use crate::fsrs::Grade;
 use crate::fsrs::Interval;
 use crate::fsrs::Stability;
 use crate::types::aliases::DeckName;
+use crate::types::card::Card;
 use crate::types::card::CardContent;
 use crate::types::card_hash::CardHash;
 use crate::types::date::Date;
@@ -32,8 +33,12 @@ use crate::types::performance::Performance;
 use crate::types::performance::ReviewedPerformance;
 use crate::types::timestamp::Timestamp;
 
-pub fn export_collection(directory: Option<String>, output: Option<String>) -> Fallible<()> {
-    let coll: Collection = Collection::new(directory)?;
+pub fn export_collection(
+    directory: Option<String>,
+    db: Option<String>,
+    output: Option<String>,
+) -> Fallible<()> {
+    let coll: Collection = Collection::new(directory, db)?;
     let export: Export = get_export(coll)?;
     let json = serde_json::to_string_pretty(&export)?;
     match output {
@@ -52,47 +57,56 @@ struct Export {
 
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
-struct CardExport {
-    hash: CardHash,
-    family_hash: Option<CardHash>,
-    deck_name: DeckName,
-    location: LocationExport,
-    content: CardContentExport,
-    performance: Option<PerformanceExport>,
+pub struct CardExport {
+    pub hash: CardHash,
+    pub family_hash: Option<CardHash>,
+    pub deck_name: DeckName,
+    pub location: LocationExport,
+    pub content: CardContentExport,
+    pub performance: Option<PerformanceExport>,
 }
 
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
-struct LocationExport {
-    file_path: String,
-    line_start: usize,
-    line_end: usize,
+pub struct LocationExport {
+    pub file_path: String,
+    pub line_start: usize,
+    pub line_end: usize,
 }
 
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
-enum CardContentExport {
+pub enum CardContentExport {
     Basic {
         question: String,
         answer: String,
+        note: Option<String>,
     },
     Cloze {
         text: String,
         start: usize,
         end: usize,
+        note: Option<String>,
+    },
+    List {
+        title: String,
+        items: Vec<String>,
+        ordered: bool,
+        index: Option<usize>,
+        note: Option<String>,
     },
 }
 
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
-struct PerformanceExport {
-    last_reviewed_at: Timestamp,
-    stability: Stability,
-    difficulty: Difficulty,
-    interval_raw: Interval,
-    interval_days: i64,
-    due_date: Date,
-    review_count: usize,
+pub struct PerformanceExport {
+    pub last_reviewed_at: Timestamp,
+    pub stability: Stability,
+    pub difficulty: Difficulty,
+    pub interval_raw: Interval,
+    pub interval_days: i64,
+    pub due_date: Date,
+    pub review_count: usize,
 }
 
 #[derive(Serialize)]
@@ -106,16 +120,17 @@ struct SessionExport {
 
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
-struct ReviewExport {
-    review_id: i64,
-    hash: CardHash,
-    reviewed_at: Timestamp,
-    grade: Grade,
-    stability: Stability,
-    difficulty: Difficulty,
-    interval_raw: Interval,
-    interval_days: i64,
-    due_date: Date,
+pub struct ReviewExport {
+    pub review_id: i64,
+    pub hash: CardHash,
+    pub reviewed_at: Timestamp,
+    pub grade: Grade,
+    pub stability: Stability,
+    pub difficulty: Difficulty,
+    pub interval_raw: Interval,
+    pub interval_days: i64,
+    pub due_date: Date,
+    pub duration_ms: Option<i64>,
 }
 
 fn get_export(coll: Collection) -> Fallible<Export> {
@@ -127,35 +142,52 @@ fn get_export(coll: Collection) -> Fallible<Export> {
 fn get_card_export(coll: &Collection) -> Fallible<Vec<CardExport>> {
     let mut cards: Vec<CardExport> = Vec::new();
     for card in coll.cards.iter() {
-        let p = coll.db.get_card_performance_opt(card.hash())?;
-        let ce = CardExport {
-            hash: card.hash(),
-            family_hash: card.family_hash(),
-            deck_name: card.deck_name().to_owned(),
-            location: LocationExport {
-                file_path: card.file_path().clone().display().to_string(),
-                line_start: card.range().0,
-                line_end: card.range().1,
-            },
-            content: match card.content() {
-                CardContent::Basic { question, answer } => CardContentExport::Basic {
-                    question: question.clone(),
-                    answer: answer.clone(),
-                },
-                CardContent::Cloze { text, start, end } => CardContentExport::Cloze {
-                    text: text.clone(),
-                    start: *start,
-                    end: *end,
-                },
-            },
-            performance: export_performance(p),
-        };
-        cards.push(ce);
+        cards.push(export_card(coll, card)?);
     }
     Ok(cards)
 }
 
-fn export_performance(p: Option<Performance>) -> Option<PerformanceExport> {
+pub fn export_card(coll: &Collection, card: &Card) -> Fallible<CardExport> {
+    let p = coll.db.get_card_performance_opt(card.hash())?;
+    Ok(CardExport {
+        hash: card.hash(),
+        family_hash: card.family_hash(),
+        deck_name: card.deck_name().to_owned(),
+        location: LocationExport {
+            file_path: card.file_path().clone().display().to_string(),
+            line_start: card.range().0,
+            line_end: card.range().1,
+        },
+        content: match card.content() {
+            CardContent::Basic { question, answer } => CardContentExport::Basic {
+                question: question.clone(),
+                answer: answer.clone(),
+                note: card.note().map(str::to_string),
+            },
+            CardContent::Cloze { text, start, end } => CardContentExport::Cloze {
+                text: text.clone(),
+                start: *start,
+                end: *end,
+                note: card.note().map(str::to_string),
+            },
+            CardContent::List {
+                title,
+                items,
+                ordered,
+                index,
+            } => CardContentExport::List {
+                title: title.clone(),
+                items: items.clone(),
+                ordered: *ordered,
+                index: *index,
+                note: card.note().map(str::to_string),
+            },
+        },
+        performance: export_performance(p),
+    })
+}
+
+pub fn export_performance(p: Option<Performance>) -> Option<PerformanceExport> {
     match p {
         Some(p) => match p {
             Performance::New => None,
@@ -206,7 +238,7 @@ fn export_session(coll: &Collection, session: SessionRow) -> Fallible<SessionExp
     })
 }
 
-fn export_review(review: ReviewRow) -> ReviewExport {
+pub fn export_review(review: ReviewRow) -> ReviewExport {
     ReviewExport {
         review_id: review.review_id,
         hash: review.data.card_hash,
@@ -217,6 +249,7 @@ fn export_review(review: ReviewRow) -> ReviewExport {
         interval_raw: review.data.interval_raw,
         interval_days: review.data.interval_days,
         due_date: review.data.due_date,
+        duration_ms: review.data.duration_ms,
     }
 }
 
@@ -225,18 +258,20 @@ mod tests {
     use std::path::PathBuf;
 
     use super::*;
+    use crate::cache::parse_deck_cached;
     use crate::db::ReviewRecord;
     use crate::helper::create_tmp_copy_of_test_directory;
     use crate::helper::create_tmp_directory;
-    use crate::parser::parse_deck;
+    use crate::helper::save_session;
 
     #[test]
     fn test_full_export() -> Fallible<()> {
         let dir = create_tmp_copy_of_test_directory()?;
-        let mut coll = Collection::new(Some(dir.clone()))?;
-        let deck = parse_deck(&PathBuf::from(dir.clone()))?;
+        let mut coll = Collection::new(Some(dir.clone()), None)?;
+        let deck = parse_deck_cached(&PathBuf::from(dir.clone()), &mut coll.db)?;
         let now = Timestamp::now();
         let mut reviews = Vec::new();
+        let mut performances = Vec::new();
         for card in deck {
             coll.db.insert_card(card.hash(), now)?;
             let performance = Performance::Reviewed(ReviewedPerformance {
@@ -248,7 +283,7 @@ mod tests {
                 due_date: now.date(),
                 review_count: 1,
             });
-            coll.db.update_card_performance(card.hash(), performance)?;
+            performances.push((card.hash(), performance));
             let review = ReviewRecord {
                 card_hash: card.hash(),
                 reviewed_at: now,
@@ -258,15 +293,16 @@ mod tests {
                 interval_raw: 1.0,
                 interval_days: 1,
                 due_date: now.date(),
+                duration_ms: None,
             };
             reviews.push(review);
         }
-        coll.db.save_session(now, now, reviews)?;
+        save_session(&mut coll.db, now, now, &reviews, &performances)?;
         // Export.
-        export_collection(Some(dir.clone()), None)?;
+        export_collection(Some(dir.clone()), None, None)?;
         let tmp = create_tmp_directory()?;
         let output = tmp.join("export.json").display().to_string();
-        export_collection(Some(dir), Some(output))?;
+        export_collection(Some(dir), None, Some(output))?;
         Ok(())
     }
 }
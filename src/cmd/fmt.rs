@@ -0,0 +1,406 @@
+// Copyright 2025 Fernando Borretti
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fs::read_to_string;
+use std::fs::write;
+use std::path::Path;
+
+use crate::collection::collection_directory;
+use crate::collection::collection_hash_version;
+use crate::error::ErrorReport;
+use crate::error::Fallible;
+use crate::error::fail;
+use crate::format::DeckFormat;
+use crate::parser::card_tag;
+use crate::parser::check_markdown_deck;
+use crate::parser::include_directive;
+use crate::parser::is_separator;
+use crate::parser::lint_allow_directive;
+use crate::types::card_hash::CardHash;
+use crate::types::card_hash::HashVersion;
+use crate::walk::deck_files;
+
+/// Rewrite the Markdown deck files in the collection into the canonical
+/// layout. With `check`, nothing is written, and the command fails if any file
+/// is not formatted. Files that can't be formatted are reported and skipped,
+/// and the command fails once the other files are done.
+pub fn format_collection(
+    directory: Option<String>,
+    db: Option<String>,
+    check: bool,
+) -> Fallible<()> {
+    let directory = collection_directory(directory)?;
+    let hash_version = collection_hash_version(&directory, db)?;
+    let mut unformatted = 0;
+    let mut failed = 0;
+    for path in deck_files(&directory)? {
+        if DeckFormat::from_path(&path) != Some(DeckFormat::Markdown) {
+            continue;
+        }
+        let relative = path.strip_prefix(&directory)?;
+        let text = read_to_string(&path)?;
+        let formatted = match format_deck(&path, &text, hash_version) {
+            Ok(formatted) => formatted,
+            Err(e) => {
+                failed += 1;
+                eprintln!("{}: {e}", relative.display());
+                continue;
+            }
+        };
+        if formatted != text {
+            unformatted += 1;
+            println!("{}", relative.display());
+            if !check {
+                write(&path, formatted)?;
+            }
+        }
+    }
+    match (check, unformatted) {
+        _ if failed == 1 => fail("1 file could not be formatted."),
+        _ if failed > 0 => fail(format!("{failed} files could not be formatted.")),
+        (true, 1) => fail("1 file is not formatted."),
+        (true, n) if n > 0 => fail(format!("{n} files are not formatted.")),
+        _ => Ok(()),
+    }
+}
+
+/// Format the text of a Markdown deck file. The file is parsed before and
+/// after formatting, and formatting fails rather than change any card or the
+/// frontmatter's values.
+pub fn format_deck(path: &Path, text: &str, hash_version: HashVersion) -> Fallible<String> {
+    let before = card_fingerprints(path, text, hash_version)?;
+    let formatted = format_text(text)?;
+    let after = card_fingerprints(path, &formatted, hash_version)?;
+    if before != after || frontmatter_table(text)? != frontmatter_table(&formatted)? {
+        return fail(format!(
+            "Formatting {} would change its cards or frontmatter, so it was left unchanged.",
+            path.display()
+        ));
+    }
+    Ok(formatted)
+}
+
+/// The hashes and notes of the cards in a deck, in order.
+fn card_fingerprints(
+    path: &Path,
+    text: &str,
+    hash_version: HashVersion,
+) -> Fallible<Vec<(CardHash, Option<String>)>> {
+    let (cards, errors) = check_markdown_deck(path, text, hash_version);
+    if let Some(error) = errors.into_iter().next() {
+        return Err(error.into());
+    }
+    Ok(cards
+        .iter()
+        .map(|card| (card.hash(), card.note().map(str::to_string)))
+        .collect())
+}
+
+/// The kind of a line in the body of a deck, as far as layout is concerned.
+#[derive(Clone, Copy, PartialEq)]
+enum LineKind {
+    /// A line that starts a card, or an include directive.
+    Start,
+    /// An `A:` or `N:` line, which continues the card before it.
+    Continue,
+    Separator,
+    /// A `!lint-allow` directive, which belongs to the card after it.
+    Directive,
+    Other,
+}
+
+fn line_kind(line: &str) -> LineKind {
+    if lint_allow_directive(line).is_some() {
+        LineKind::Directive
+    } else if include_directive(line).is_some() {
+        LineKind::Start
+    } else if is_separator(line) {
+        LineKind::Separator
+    } else {
+        match card_tag(line) {
+            Some("A:" | "N:" | "Extra:") => LineKind::Continue,
+            Some(_) => LineKind::Start,
+            None => LineKind::Other,
+        }
+    }
+}
+
+/// Lay out a deck file:
+///
+/// - Frontmatter keys are written with `name` first, then in alphabetical
+///   order, each with the comments above it.
+/// - Tags are followed by a single space, and separators are just `---`.
+/// - Cards, separators, and includes are preceded by a single blank line, and
+///   `A:` and `N:` lines by none.
+/// - The file ends with a single newline, and lines end with `\n`.
+///
+/// Blank lines are only changed where they are at the start or end of a card's
+/// text, which is trimmed when the card is parsed, so no card changes.
+fn format_text(text: &str) -> Fallible<String> {
+    let (frontmatter, body) = split_frontmatter(text)?;
+    let mut output = match frontmatter {
+        Some(frontmatter) => format_frontmatter(&frontmatter)?,
+        None => String::new(),
+    };
+    let mut lines: Vec<String> = Vec::new();
+    let mut blanks: Vec<&str> = Vec::new();
+    let mut previous: Option<LineKind> = None;
+    for line in body.lines() {
+        if line.trim().is_empty() {
+            blanks.push(line);
+            continue;
+        }
+        let kind = line_kind(line);
+        let blank_lines = match (previous, kind) {
+            (None, _) => 0,
+            (Some(_), LineKind::Continue) => 0,
+            (Some(LineKind::Directive), LineKind::Start) => 0,
+            (Some(_), LineKind::Start | LineKind::Separator | LineKind::Directive) => 1,
+            (Some(LineKind::Separator), LineKind::Other) => 1,
+            // Blank lines inside a card's text are part of the card.
+            (Some(_), LineKind::Other) => {
+                lines.extend(blanks.iter().map(|blank| blank.to_string()));
+                0
+            }
+        };
+        lines.extend((0..blank_lines).map(|_| String::new()));
+        blanks.clear();
+        lines.push(match kind {
+            LineKind::Separator => "---".to_string(),
+            LineKind::Start | LineKind::Continue => format_tag_line(line),
+            LineKind::Directive | LineKind::Other => line.to_string(),
+        });
+        previous = Some(kind);
+    }
+    if !output.is_empty() && !lines.is_empty() {
+        output.push('\n');
+    }
+    for line in lines {
+        output.push_str(&line);
+        output.push('\n');
+    }
+    Ok(output)
+}
+
+/// Put a single space between a line's tag and its text.
+fn format_tag_line(line: &str) -> String {
+    match card_tag(line) {
+        Some(tag) => {
+            let text = line[tag.len()..].trim();
+            if text.is_empty() {
+                tag.to_string()
+            } else {
+                format!("{tag} {text}")
+            }
+        }
+        None => line.trim_end().to_string(),
+    }
+}
+
+/// Split the text into its TOML frontmatter, if any, and the rest.
+fn split_frontmatter(text: &str) -> Fallible<(Option<String>, &str)> {
+    let mut offset = 0;
+    let mut frontmatter: Option<Vec<&str>> = None;
+    for line in text.split_inclusive('\n') {
+        offset += line.len();
+        let content = line.trim_end_matches(['\n', '\r']);
+        match &mut frontmatter {
+            None if content.trim() == "---" => frontmatter = Some(Vec::new()),
+            None => return Ok((None, text)),
+            Some(lines) if content.trim() == "---" => {
+                return Ok((Some(lines.join("\n")), &text[offset..]));
+            }
+            Some(lines) => lines.push(content),
+        }
+    }
+    match frontmatter {
+        Some(_) => fail("Frontmatter opening '---' found but no closing '---'"),
+        None => Ok((None, text)),
+    }
+}
+
+/// The values in the text's frontmatter. Text without frontmatter has an
+/// empty table.
+fn frontmatter_table(text: &str) -> Fallible<toml::Table> {
+    match split_frontmatter(text)? {
+        (Some(frontmatter), _) => toml::from_str(&frontmatter)
+            .map_err(|e| ErrorReport::new(format!("Failed to parse TOML frontmatter: {e}"))),
+        (None, _) => Ok(toml::Table::new()),
+    }
+}
+
+/// Write the frontmatter's top-level keys with `name` first, then in
+/// alphabetical order. Each key is written as it was, along with the comments
+/// above it, and tables stay at the end in their own order. Blank lines
+/// between keys are removed, and so is frontmatter with nothing in it.
+fn format_frontmatter(frontmatter: &str) -> Fallible<String> {
+    if frontmatter.trim().is_empty() {
+        return Ok(String::new());
+    }
+    let lines: Vec<&str> = frontmatter.lines().collect();
+    // The keys, each with its lines, comments included.
+    let mut entries: Vec<(String, Vec<&str>)> = Vec::new();
+    // The lines of the key being read, and the index of its first line.
+    let mut entry: Vec<&str> = Vec::new();
+    let mut key_start: Option<usize> = None;
+    // Everything from the first table on.
+    let mut tables: &[&str] = &[];
+    for (index, line) in lines.iter().enumerate() {
+        if let Some(start) = key_start {
+            // A key's value can span several lines, and ends once the lines
+            // read so far are valid TOML.
+            match entry_key(&entry[start..]) {
+                Some(key) => {
+                    entries.push((key, std::mem::take(&mut entry)));
+                    key_start = None;
+                }
+                None => {
+                    entry.push(line);
+                    continue;
+                }
+            }
+        }
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        } else if trimmed.starts_with('[') {
+            tables = &lines[index..];
+            break;
+        } else if !trimmed.starts_with('#') {
+            key_start = Some(entry.len());
+        }
+        entry.push(line);
+    }
+    if let Some(start) = key_start {
+        match entry_key(&entry[start..]) {
+            Some(key) => entries.push((key, std::mem::take(&mut entry))),
+            None => return fail("Failed to parse TOML frontmatter."),
+        }
+    }
+    entries.sort_by_key(|(key, _)| (key != "name", key.clone()));
+
+    let mut output = String::from("---\n");
+    let comments = entry.iter();
+    for line in entries.iter().flat_map(|(_, lines)| lines).chain(comments) {
+        output.push_str(line);
+        output.push('\n');
+    }
+    for line in tables {
+        output.push_str(line);
+        output.push('\n');
+    }
+    output.push_str("---\n");
+    Ok(output)
+}
+
+/// The key the lines define, if they are a single complete TOML key.
+fn entry_key(lines: &[&str]) -> Option<String> {
+    let table: toml::Table = toml::from_str(&lines.join("\n")).ok()?;
+    match table.len() {
+        1 => table.keys().next().cloned(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::helper::create_tmp_copy_of_test_directory;
+
+    fn format(text: &str) -> Fallible<String> {
+        format_deck(&PathBuf::from("deck.md"), text, HashVersion::Raw)
+    }
+
+    #[test]
+    fn test_format_layout() -> Fallible<()> {
+        let text = "\n\nQ:What is 2+2?  \n\nA:   4\nQ: Next?\nA: Yes.\n   ---   \nProse.\n\n\n\nC:The [cat] sat.\n\n\n";
+        let expected =
+            "Q: What is 2+2?\nA: 4\n\nQ: Next?\nA: Yes.\n\n---\n\nProse.\n\nC: The [cat] sat.\n";
+        assert_eq!(format(text)?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_blank_lines_inside_cards_are_kept() -> Fallible<()> {
+        let text = "Q: Steps?\nA: One.\n  \n\nTwo.\n\nQ: Next?\nA: Yes.\n";
+        assert_eq!(format(text)?, text);
+        Ok(())
+    }
+
+    #[test]
+    fn test_directives_stay_with_their_card() -> Fallible<()> {
+        let text = "Q: a\nA: a\n!lint-allow long-answer\n\nQ: b\nA: b\nN:  A note.\n";
+        let expected = "Q: a\nA: a\n\n!lint-allow long-answer\nQ: b\nA: b\nN: A note.\n";
+        assert_eq!(format(text)?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_frontmatter() -> Fallible<()> {
+        let text = "---\nzeta = 1\n\n# The deck's name.\nname =   \"Geography\"\n---\nQ: a\nA: a";
+        let expected =
+            "---\n# The deck's name.\nname =   \"Geography\"\nzeta = 1\n---\n\nQ: a\nA: a\n";
+        assert_eq!(format(text)?, expected);
+        assert_eq!(format("---\n---\nQ: a\nA: a")?, "Q: a\nA: a\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_frontmatter_values_and_tables() -> Fallible<()> {
+        let text = "---\ntags = [\n  \"b\",\n\n  \"a\",\n]\nname = \"X\" # Shown in the UI.\n# Trailing.\n[extra]\nz = 1\n\na = 2\n---\nQ: a\nA: a\n";
+        let expected = "---\nname = \"X\" # Shown in the UI.\ntags = [\n  \"b\",\n\n  \"a\",\n]\n# Trailing.\n[extra]\nz = 1\n\na = 2\n---\n\nQ: a\nA: a\n";
+        assert_eq!(format(text)?, expected);
+        assert_eq!(format(expected)?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_idempotent() -> Fallible<()> {
+        let text = "---\nname = \"X\"\n---\nQ:a\nA:b\n---\nL+: Colors\n- red\n- green\n";
+        let once = format(text)?;
+        assert_eq!(format(&once)?, once);
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_deck() {
+        assert!(format("A: no question").is_err());
+    }
+
+    #[test]
+    fn test_check() -> Fallible<()> {
+        let directory = create_tmp_copy_of_test_directory()?;
+        format_collection(Some(directory.clone()), None, false)?;
+        assert!(format_collection(Some(directory.clone()), None, true).is_ok());
+        std::fs::write(PathBuf::from(&directory).join("messy.md"), "Q:a\n\nA:b")?;
+        assert!(format_collection(Some(directory.clone()), None, true).is_err());
+        format_collection(Some(directory.clone()), None, false)?;
+        assert!(format_collection(Some(directory), None, true).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_files_are_skipped() -> Fallible<()> {
+        let directory = create_tmp_copy_of_test_directory()?;
+        let root = PathBuf::from(&directory);
+        std::fs::write(root.join("broken.md"), "A:no question")?;
+        std::fs::write(root.join("messy.md"), "Q:a\n\nA:b")?;
+        assert!(format_collection(Some(directory), None, false).is_err());
+        assert_eq!(read_to_string(root.join("broken.md"))?, "A:no question");
+        assert_eq!(read_to_string(root.join("messy.md"))?, "Q: a\nA: b\n");
+        Ok(())
+    }
+}
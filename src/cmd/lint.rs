@@ -0,0 +1,51 @@
+// Copyright 2025 Fernando Borretti
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::cmd::check::DiagnosticFormat;
+use crate::cmd::check::print_diagnostics;
+use crate::collection::collection_directory;
+use crate::collection::collection_hash_version;
+use crate::error::Fallible;
+use crate::lint::collect_lints;
+
+pub fn lint_collection(
+    directory: Option<String>,
+    db: Option<String>,
+    format: DiagnosticFormat,
+) -> Fallible<()> {
+    let directory = collection_directory(directory)?;
+    let hash_version = collection_hash_version(&directory, db)?;
+    let diagnostics = collect_lints(&directory, hash_version)?;
+    print_diagnostics(&directory, &diagnostics, format)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helper::create_tmp_copy_of_test_directory;
+
+    #[test]
+    fn test_non_existent_directory() {
+        assert!(
+            lint_collection(Some("./derpherp".to_string()), None, DiagnosticFormat::Text).is_err()
+        );
+    }
+
+    #[test]
+    fn test_directory() -> Fallible<()> {
+        let directory = create_tmp_copy_of_test_directory()?;
+        assert!(lint_collection(Some(directory), None, DiagnosticFormat::Json).is_ok());
+        Ok(())
+    }
+}
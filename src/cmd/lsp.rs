@@ -0,0 +1,813 @@
+// Copyright 2025 Fernando Borretti
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A language server for deck files, spoken over stdio. It reports parser and
+//! media problems as the user types, shows a card's progress on hover, offers
+//! to suspend cards, and completes `@/` media paths.
+
+use std::collections::HashMap;
+use std::fs::read_dir;
+use std::path::PathBuf;
+
+use lsp_server::Connection;
+use lsp_server::ErrorCode;
+use lsp_server::Message;
+use lsp_server::Notification;
+use lsp_server::Request;
+use lsp_server::Response;
+use lsp_types::CodeAction;
+use lsp_types::CodeActionKind;
+use lsp_types::CodeActionOrCommand;
+use lsp_types::CodeActionParams;
+use lsp_types::CodeActionProviderCapability;
+use lsp_types::Command;
+use lsp_types::CompletionItem;
+use lsp_types::CompletionItemKind;
+use lsp_types::CompletionOptions;
+use lsp_types::CompletionParams;
+use lsp_types::CompletionResponse;
+use lsp_types::CompletionTextEdit;
+use lsp_types::DiagnosticSeverity;
+use lsp_types::DidChangeTextDocumentParams;
+use lsp_types::DidCloseTextDocumentParams;
+use lsp_types::DidOpenTextDocumentParams;
+use lsp_types::ExecuteCommandOptions;
+use lsp_types::ExecuteCommandParams;
+use lsp_types::Hover;
+use lsp_types::HoverContents;
+use lsp_types::HoverParams;
+use lsp_types::HoverProviderCapability;
+use lsp_types::MarkupContent;
+use lsp_types::MarkupKind;
+use lsp_types::Position;
+use lsp_types::PublishDiagnosticsParams;
+use lsp_types::Range;
+use lsp_types::ServerCapabilities;
+use lsp_types::TextDocumentSyncCapability;
+use lsp_types::TextDocumentSyncKind;
+use lsp_types::TextEdit;
+use lsp_types::Uri;
+use lsp_types::notification::DidChangeTextDocument;
+use lsp_types::notification::DidCloseTextDocument;
+use lsp_types::notification::DidOpenTextDocument;
+use lsp_types::notification::Notification as _;
+use lsp_types::notification::PublishDiagnostics;
+use lsp_types::request::CodeActionRequest;
+use lsp_types::request::Completion;
+use lsp_types::request::ExecuteCommand;
+use lsp_types::request::HoverRequest;
+use lsp_types::request::Request as _;
+use percent_encoding::percent_decode_str;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::collection::collection_directory;
+use crate::collection::open_database;
+use crate::db::Database;
+use crate::error::ErrorReport;
+use crate::error::Fallible;
+use crate::error::fail;
+use crate::format::DeckFormat;
+use crate::media::resolve::MediaResolverBuilder;
+use crate::media::validate::find_missing_media;
+use crate::types::card::Card;
+use crate::types::card::CardContent;
+use crate::types::card::cards_at_line;
+use crate::types::card_hash::CardHash;
+use crate::types::card_hash::HashVersion;
+use crate::types::diagnostic::Diagnostic;
+use crate::types::diagnostic::Severity;
+use crate::types::performance::Performance;
+use crate::types::timestamp::Timestamp;
+use crate::walk::is_media_file;
+
+const SUSPEND_COMMAND: &str = "hashcards.suspendCard";
+const UNSUSPEND_COMMAND: &str = "hashcards.unsuspendCard";
+
+/// Run the language server over stdio until the client shuts it down.
+pub fn start_language_server(directory: Option<String>, db: Option<String>) -> Fallible<()> {
+    let directory = collection_directory(directory)?;
+    let (connection, io_threads) = Connection::stdio();
+    serve(directory, db, &connection)?;
+    io_threads.join()?;
+    Ok(())
+}
+
+fn serve(directory: PathBuf, db: Option<String>, connection: &Connection) -> Fallible<()> {
+    connection.initialize(serde_json::to_value(capabilities())?)?;
+    let mut server = LanguageServer::new(directory, db)?;
+    for message in &connection.receiver {
+        let replies = match message {
+            Message::Request(request) => {
+                if connection.handle_shutdown(&request)? {
+                    return Ok(());
+                }
+                vec![Message::Response(server.handle_request(request))]
+            }
+            Message::Notification(notification) => {
+                // A notification has no response to carry an error, so it is
+                // logged instead.
+                match server.handle_notification(notification) {
+                    Ok(replies) => replies.into_iter().map(Message::Notification).collect(),
+                    Err(e) => {
+                        log::error!("{e}");
+                        Vec::new()
+                    }
+                }
+            }
+            Message::Response(_) => Vec::new(),
+        };
+        for reply in replies {
+            connection
+                .sender
+                .send(reply)
+                .map_err(|e| ErrorReport::new(format!("Failed to send message: {e}")))?;
+        }
+    }
+    Ok(())
+}
+
+fn capabilities() -> ServerCapabilities {
+    ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
+        completion_provider: Some(CompletionOptions {
+            trigger_characters: Some(vec!["/".to_string()]),
+            ..CompletionOptions::default()
+        }),
+        execute_command_provider: Some(ExecuteCommandOptions {
+            commands: vec![SUSPEND_COMMAND.to_string(), UNSUSPEND_COMMAND.to_string()],
+            ..ExecuteCommandOptions::default()
+        }),
+        ..ServerCapabilities::default()
+    }
+}
+
+struct LanguageServer {
+    directory: PathBuf,
+    db: Database,
+    hash_version: HashVersion,
+    /// The text of the open documents, which may not be saved yet.
+    documents: HashMap<Uri, String>,
+}
+
+/// An open deck file.
+struct Document<'a> {
+    path: PathBuf,
+    format: DeckFormat,
+    text: &'a str,
+}
+
+impl LanguageServer {
+    fn new(directory: PathBuf, db: Option<String>) -> Fallible<Self> {
+        let db = open_database(&directory, db)?;
+        let hash_version = db.hash_version()?;
+        Ok(Self {
+            directory,
+            db,
+            hash_version,
+            documents: HashMap::new(),
+        })
+    }
+
+    fn handle_request(&mut self, request: Request) -> Response {
+        match request.method.as_str() {
+            HoverRequest::METHOD => respond(request, |params| self.hover(params)),
+            CodeActionRequest::METHOD => respond(request, |params| self.code_actions(params)),
+            Completion::METHOD => respond(request, |params| self.complete(params)),
+            ExecuteCommand::METHOD => respond(request, |params| self.execute_command(params)),
+            method => Response::new_err(
+                request.id,
+                ErrorCode::MethodNotFound as i32,
+                format!("Unknown method '{method}'."),
+            ),
+        }
+    }
+
+    /// Handle a notification, returning the notifications to send back.
+    fn handle_notification(&mut self, notification: Notification) -> Fallible<Vec<Notification>> {
+        let uri = match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: DidOpenTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let uri = params.text_document.uri;
+                self.documents
+                    .insert(uri.clone(), params.text_document.text);
+                uri
+            }
+            DidChangeTextDocument::METHOD => {
+                let params: DidChangeTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let uri = params.text_document.uri;
+                // With full document sync, the last change is the whole text.
+                if let Some(change) = params.content_changes.into_iter().last() {
+                    self.documents.insert(uri.clone(), change.text);
+                }
+                uri
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: DidCloseTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let uri = params.text_document.uri;
+                self.documents.remove(&uri);
+                return Ok(vec![publish_diagnostics(uri, Vec::new())]);
+            }
+            _ => return Ok(Vec::new()),
+        };
+        let diagnostics = self.diagnostics(&uri)?;
+        Ok(vec![publish_diagnostics(uri, diagnostics)])
+    }
+
+    fn document(&self, uri: &Uri) -> Option<Document<'_>> {
+        let text = self.documents.get(uri)?;
+        let path = uri_to_path(uri)?;
+        let format = DeckFormat::from_path(&path)?;
+        Some(Document { path, format, text })
+    }
+
+    /// The cards in a document, and its parser errors.
+    fn parse(&self, document: &Document) -> (Vec<Card>, Vec<Diagnostic>) {
+        let (cards, errors) =
+            document
+                .format
+                .check_file(&document.path, document.text, self.hash_version);
+        (cards, errors.into_iter().map(Diagnostic::from).collect())
+    }
+
+    /// The parser and media diagnostics of an open document.
+    fn diagnostics(&self, uri: &Uri) -> Fallible<Vec<lsp_types::Diagnostic>> {
+        let Some(document) = self.document(uri) else {
+            return Ok(Vec::new());
+        };
+        let (cards, mut diagnostics) = self.parse(&document);
+        // Media paths are resolved relative to the file, so a file that was
+        // never saved has none.
+        if document.path.starts_with(&self.directory) && document.path.exists() {
+            // Included cards are checked in their own files.
+            let own: Vec<Card> = cards
+                .into_iter()
+                .filter(|card| card.file_path() == &document.path)
+                .collect();
+            for missing in find_missing_media(&own, &self.directory)? {
+                diagnostics.push(missing.to_diagnostic_in(document.text));
+            }
+        }
+        diagnostics.retain(|d| d.file == document.path);
+        diagnostics.sort();
+        let lines: Vec<&str> = document.text.lines().collect();
+        Ok(diagnostics
+            .iter()
+            .map(|d| to_lsp_diagnostic(d, &lines))
+            .collect())
+    }
+
+    /// The cards defined at the given line of a document. A line between two
+    /// cards belongs to the later one.
+    fn cards_at(&self, document: &Document, line: usize) -> Vec<Card> {
+        let (cards, _) = self.parse(document);
+        cards_at_line(cards, &document.path, line)
+    }
+
+    fn hover(&self, params: HoverParams) -> Fallible<Option<Hover>> {
+        let position = params.text_document_position_params;
+        let Some(document) = self.document(&position.text_document.uri) else {
+            return Ok(None);
+        };
+        let cards = self.cards_at(&document, position.position.line as usize);
+        if cards.is_empty() {
+            return Ok(None);
+        }
+        let mut sections = Vec::new();
+        for card in &cards {
+            sections.push(self.describe(card)?);
+        }
+        Ok(Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: sections.join("\n\n---\n\n"),
+            }),
+            range: None,
+        }))
+    }
+
+    /// Describe a card and its progress, in Markdown.
+    fn describe(&self, card: &Card) -> Fallible<String> {
+        let mut lines = vec![format!("**{}** `{}`", card_label(card), card.hash())];
+        match self.db.get_card_performance_opt(card.hash())? {
+            None => lines.push("Not in the database yet.".to_string()),
+            Some(Performance::New) => lines.push("New, never reviewed.".to_string()),
+            Some(Performance::Reviewed(performance)) => {
+                lines.push(format!("- Due: {}", performance.due_date));
+                lines.push(format!("- Stability: {:.2}", performance.stability));
+                lines.push(format!("- Difficulty: {:.2}", performance.difficulty));
+                lines.push(format!("- Reviews: {}", performance.review_count));
+            }
+        }
+        if self.db.is_suspended(card.hash())? {
+            lines.push("Suspended.".to_string());
+        }
+        Ok(lines.join("\n"))
+    }
+
+    fn code_actions(&self, params: CodeActionParams) -> Fallible<Option<Vec<CodeActionOrCommand>>> {
+        let Some(document) = self.document(&params.text_document.uri) else {
+            return Ok(None);
+        };
+        let cards = self.cards_at(&document, params.range.start.line as usize);
+        let mut actions = Vec::new();
+        for card in &cards {
+            let (verb, command) = if self.db.is_suspended(card.hash())? {
+                ("Unsuspend", UNSUSPEND_COMMAND)
+            } else {
+                ("Suspend", SUSPEND_COMMAND)
+            };
+            let title = if cards.len() == 1 {
+                format!("{verb} card")
+            } else {
+                format!("{verb} card {}", &card.hash().to_hex()[..8])
+            };
+            actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                title: title.clone(),
+                kind: Some(CodeActionKind::EMPTY),
+                command: Some(Command {
+                    title,
+                    command: command.to_string(),
+                    arguments: Some(vec![serde_json::to_value(card.hash())?]),
+                }),
+                ..CodeAction::default()
+            }));
+        }
+        Ok(Some(actions))
+    }
+
+    fn execute_command(&self, params: ExecuteCommandParams) -> Fallible<Option<serde_json::Value>> {
+        let Some(hash) = params.arguments.first().and_then(|arg| arg.as_str()) else {
+            return fail("Expected a card hash.");
+        };
+        let hash = CardHash::from_hex(hash)?;
+        match params.command.as_str() {
+            SUSPEND_COMMAND => {
+                // Cards are added to the database when they are first drilled,
+                // so a new card may not be there yet.
+                let now = Timestamp::now();
+                if !self.db.card_exists(hash)? {
+                    self.db.insert_card(hash, now)?;
+                }
+                self.db.suspend_card(hash, now)?;
+            }
+            UNSUSPEND_COMMAND => self.db.unsuspend_card(hash)?,
+            command => return fail(format!("Unknown command '{command}'.")),
+        }
+        Ok(None)
+    }
+
+    /// Complete the `@/` media path before the cursor with the files and
+    /// directories in the collection.
+    fn complete(&self, params: CompletionParams) -> Fallible<Option<CompletionResponse>> {
+        let position = params.text_document_position;
+        let Some(document) = self.document(&position.text_document.uri) else {
+            return Ok(None);
+        };
+        let Ok(deck_path) = document.path.strip_prefix(&self.directory) else {
+            return Ok(None);
+        };
+        let Some(line) = document.text.lines().nth(position.position.line as usize) else {
+            return Ok(None);
+        };
+        let cursor = byte_offset(line, position.position.character);
+        let Some(path) = media_path_prefix(&line[..cursor]) else {
+            return Ok(None);
+        };
+        let (dir, partial) = path.rsplit_once('/').unwrap_or(("", path));
+        let resolver = MediaResolverBuilder::new()
+            .with_collection_path(self.directory.clone())?
+            .with_deck_path(deck_path.to_path_buf())?
+            .build()?;
+        let Ok(entries) = read_dir(self.directory.join(dir)) else {
+            return Ok(None);
+        };
+        let start = Position {
+            line: position.position.line,
+            character: position.position.character - utf16_len(partial),
+        };
+        let mut items = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            let is_dir = entry.file_type()?.is_dir();
+            if name.starts_with('.') || !(is_dir || is_media_file(&entry.path())) {
+                continue;
+            }
+            let relative = if dir.is_empty() {
+                name.clone()
+            } else {
+                format!("{dir}/{name}")
+            };
+            if resolver.resolve(&format!("@/{relative}")).is_err() {
+                continue;
+            }
+            let (label, kind) = if is_dir {
+                (format!("{name}/"), CompletionItemKind::FOLDER)
+            } else {
+                (name, CompletionItemKind::FILE)
+            };
+            items.push(CompletionItem {
+                label: label.clone(),
+                kind: Some(kind),
+                text_edit: Some(CompletionTextEdit::Edit(TextEdit {
+                    range: Range {
+                        start,
+                        end: position.position,
+                    },
+                    new_text: label,
+                })),
+                ..CompletionItem::default()
+            });
+        }
+        items.sort_by(|a, b| a.label.cmp(&b.label));
+        Ok(Some(CompletionResponse::Array(items)))
+    }
+}
+
+/// Run a request handler, turning its result or error into a response.
+fn respond<P, R>(request: Request, handler: impl FnOnce(P) -> Fallible<R>) -> Response
+where
+    P: DeserializeOwned,
+    R: Serialize,
+{
+    let result = serde_json::from_value(request.params)
+        .map_err(ErrorReport::from)
+        .and_then(handler);
+    match result {
+        Ok(result) => Response::new_ok(request.id, result),
+        Err(e) => Response::new_err(request.id, ErrorCode::InternalError as i32, e.to_string()),
+    }
+}
+
+fn publish_diagnostics(uri: Uri, diagnostics: Vec<lsp_types::Diagnostic>) -> Notification {
+    Notification::new(
+        PublishDiagnostics::METHOD.to_string(),
+        PublishDiagnosticsParams {
+            uri,
+            diagnostics,
+            version: None,
+        },
+    )
+}
+
+/// The local path of a `file:` URI.
+fn uri_to_path(uri: &Uri) -> Option<PathBuf> {
+    if !uri.scheme()?.as_str().eq_ignore_ascii_case("file") {
+        return None;
+    }
+    let path = percent_decode_str(uri.path().as_str()).decode_utf8().ok()?;
+    // On Windows, the path looks like `/C:/Users/...`.
+    let path = match path.strip_prefix('/') {
+        Some(rest) if cfg!(windows) && rest.get(1..2) == Some(":") => rest,
+        _ => &path,
+    };
+    Some(PathBuf::from(path))
+}
+
+/// If the text before the cursor ends inside a collection-relative media path,
+/// return the part of the path after `@/`.
+fn media_path_prefix(before: &str) -> Option<&str> {
+    let (_, path) = before.rsplit_once("@/")?;
+    let inside = !path.contains(|c: char| c.is_whitespace() || c == ')' || c == ']');
+    inside.then_some(path)
+}
+
+/// A short description of the kind of card, for hovers.
+fn card_label(card: &Card) -> String {
+    match card.content() {
+        CardContent::Basic { .. } => "Basic card".to_string(),
+        CardContent::Cloze { text, start, end } => {
+            let deleted = String::from_utf8_lossy(&text.as_bytes()[*start..*end + 1]);
+            format!("Cloze card for “{deleted}”")
+        }
+        CardContent::List { index: None, .. } => "Whole list card".to_string(),
+        CardContent::List {
+            index: Some(index), ..
+        } => format!("List card for item {}", index + 1),
+    }
+}
+
+fn to_lsp_diagnostic(diagnostic: &Diagnostic, lines: &[&str]) -> lsp_types::Diagnostic {
+    let line_num = diagnostic.line.saturating_sub(1);
+    let line = lines.get(line_num).copied().unwrap_or_default();
+    let start = line
+        .char_indices()
+        .nth(diagnostic.column.saturating_sub(1))
+        .map(|(byte, _)| byte)
+        .unwrap_or(line.len());
+    let line_num = line_num as u32;
+    lsp_types::Diagnostic {
+        range: Range {
+            start: Position::new(line_num, utf16_len(&line[..start])),
+            end: Position::new(line_num, utf16_len(line)),
+        },
+        severity: Some(match diagnostic.severity {
+            Severity::Error => DiagnosticSeverity::ERROR,
+            Severity::Warning => DiagnosticSeverity::WARNING,
+        }),
+        source: Some("hashcards".to_string()),
+        message: diagnostic.message.clone(),
+        ..lsp_types::Diagnostic::default()
+    }
+}
+
+/// The length of a string in UTF-16 code units, which is how LSP counts
+/// columns.
+fn utf16_len(text: &str) -> u32 {
+    text.encode_utf16().count() as u32
+}
+
+/// The byte offset of a UTF-16 column in a line.
+fn byte_offset(line: &str, character: u32) -> usize {
+    let mut units = 0;
+    for (byte, c) in line.char_indices() {
+        if units >= character {
+            return byte;
+        }
+        units += c.len_utf16() as u32;
+    }
+    line.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::create_dir_all;
+    use std::fs::write;
+    use std::path::Path;
+    use std::str::FromStr;
+    use std::thread::JoinHandle;
+    use std::thread::spawn;
+
+    use lsp_types::InitializeParams;
+    use lsp_types::InitializedParams;
+    use lsp_types::PartialResultParams;
+    use lsp_types::TextDocumentIdentifier;
+    use lsp_types::TextDocumentItem;
+    use lsp_types::TextDocumentPositionParams;
+    use lsp_types::VersionedTextDocumentIdentifier;
+    use lsp_types::WorkDoneProgressParams;
+    use lsp_types::notification::Exit;
+    use lsp_types::notification::Initialized;
+    use lsp_types::request::Initialize;
+    use lsp_types::request::Shutdown;
+
+    use super::*;
+    use crate::helper::create_tmp_directory;
+
+    struct Client {
+        connection: Connection,
+        server: Option<JoinHandle<Fallible<()>>>,
+        next_id: i32,
+    }
+
+    impl Client {
+        fn start(directory: PathBuf) -> Self {
+            let (client, server) = Connection::memory();
+            let server = spawn(move || serve(directory, None, &server));
+            let mut client = Self {
+                connection: client,
+                server: Some(server),
+                next_id: 0,
+            };
+            client.request::<Initialize>(InitializeParams::default());
+            client.notify::<Initialized>(InitializedParams {});
+            client
+        }
+
+        fn request<R: lsp_types::request::Request>(&mut self, params: R::Params) -> R::Result {
+            self.next_id += 1;
+            let request = Request::new(self.next_id.into(), R::METHOD.to_string(), params);
+            self.connection.sender.send(request.into()).unwrap();
+            match self.receive() {
+                Message::Response(response) => {
+                    assert!(response.error.is_none(), "{:?}", response.error);
+                    serde_json::from_value(response.result.unwrap_or_default()).unwrap()
+                }
+                message => panic!("Expected a response, got {message:?}"),
+            }
+        }
+
+        fn notify<N: lsp_types::notification::Notification>(&self, params: N::Params) {
+            let notification = Notification::new(N::METHOD.to_string(), params);
+            self.connection.sender.send(notification.into()).unwrap();
+        }
+
+        /// Receive a message, or fail with the server's error if it stopped.
+        fn receive(&mut self) -> Message {
+            match self.connection.receiver.recv() {
+                Ok(message) => message,
+                Err(_) => {
+                    let result = self.server.take().unwrap().join().unwrap();
+                    panic!("The server stopped: {result:?}")
+                }
+            }
+        }
+
+        fn diagnostics(&mut self) -> Vec<lsp_types::Diagnostic> {
+            match self.receive() {
+                Message::Notification(notification) => {
+                    assert_eq!(notification.method, PublishDiagnostics::METHOD);
+                    let params: PublishDiagnosticsParams =
+                        serde_json::from_value(notification.params).unwrap();
+                    params.diagnostics
+                }
+                message => panic!("Expected a notification, got {message:?}"),
+            }
+        }
+
+        fn open(&mut self, uri: &Uri, text: &str) -> Vec<lsp_types::Diagnostic> {
+            self.notify::<DidOpenTextDocument>(DidOpenTextDocumentParams {
+                text_document: TextDocumentItem::new(
+                    uri.clone(),
+                    "markdown".to_string(),
+                    1,
+                    text.to_string(),
+                ),
+            });
+            self.diagnostics()
+        }
+
+        fn shutdown(mut self) -> Fallible<()> {
+            self.request::<Shutdown>(());
+            self.notify::<Exit>(());
+            self.server.take().unwrap().join().unwrap()
+        }
+    }
+
+    fn path_to_uri(path: &Path) -> Uri {
+        let path = path.display().to_string().replace('\\', "/");
+        let path = if path.starts_with('/') {
+            path
+        } else {
+            format!("/{path}")
+        };
+        Uri::from_str(&format!("file://{path}")).unwrap()
+    }
+
+    fn position(uri: &Uri, line: u32, character: u32) -> TextDocumentPositionParams {
+        TextDocumentPositionParams {
+            text_document: TextDocumentIdentifier::new(uri.clone()),
+            position: Position::new(line, character),
+        }
+    }
+
+    #[test]
+    fn test_diagnostics_as_you_type() -> Fallible<()> {
+        let directory = create_tmp_directory()?;
+        write(directory.join("deck.md"), "")?;
+        let uri = path_to_uri(&directory.join("deck.md"));
+        let mut client = Client::start(directory);
+
+        let diagnostics = client.open(&uri, "A: orphan\n\nQ: foo\nA: ![](missing.png)");
+        let summary: Vec<(u32, u32, String)> = diagnostics
+            .iter()
+            .map(|d| {
+                (
+                    d.range.start.line,
+                    d.range.start.character,
+                    d.message.clone(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (0, 0, "Found answer tag without a question.".to_string()),
+                (3, 7, "Missing media file 'missing.png'.".to_string()),
+            ]
+        );
+
+        client.notify::<DidChangeTextDocument>(DidChangeTextDocumentParams {
+            text_document: VersionedTextDocumentIdentifier::new(uri.clone(), 2),
+            content_changes: vec![lsp_types::TextDocumentContentChangeEvent {
+                range: None,
+                range_length: None,
+                text: "Q: foo\nA: bar".to_string(),
+            }],
+        });
+        assert!(client.diagnostics().is_empty());
+
+        client.notify::<DidCloseTextDocument>(DidCloseTextDocumentParams {
+            text_document: TextDocumentIdentifier::new(uri.clone()),
+        });
+        assert!(client.diagnostics().is_empty());
+        client.shutdown()
+    }
+
+    #[test]
+    fn test_hover_and_suspend() -> Fallible<()> {
+        let directory = create_tmp_directory()?;
+        write(directory.join("deck.md"), "")?;
+        let uri = path_to_uri(&directory.join("deck.md"));
+        let mut client = Client::start(directory.clone());
+        client.open(&uri, "Q: foo\nA: bar\n\nC: [baz] and [quux]");
+
+        let hover = client.request::<HoverRequest>(HoverParams {
+            text_document_position_params: position(&uri, 1, 0),
+            work_done_progress_params: WorkDoneProgressParams::default(),
+        });
+        let HoverContents::Markup(content) = hover.unwrap().contents else {
+            panic!("Expected Markdown hover contents.");
+        };
+        assert!(content.value.starts_with("**Basic card**"));
+        assert!(content.value.contains("Not in the database yet."));
+
+        let actions = client
+            .request::<CodeActionRequest>(CodeActionParams {
+                text_document: TextDocumentIdentifier::new(uri.clone()),
+                range: Range::new(Position::new(3, 0), Position::new(3, 0)),
+                context: lsp_types::CodeActionContext::default(),
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                partial_result_params: PartialResultParams::default(),
+            })
+            .unwrap();
+        assert_eq!(actions.len(), 2);
+        let CodeActionOrCommand::CodeAction(action) = &actions[0] else {
+            panic!("Expected a code action.");
+        };
+        assert!(action.title.starts_with("Suspend card "));
+        let command = action.command.clone().unwrap();
+        client.request::<ExecuteCommand>(ExecuteCommandParams {
+            command: command.command,
+            arguments: command.arguments.unwrap(),
+            work_done_progress_params: WorkDoneProgressParams::default(),
+        });
+
+        let hover = client.request::<HoverRequest>(HoverParams {
+            text_document_position_params: position(&uri, 3, 0),
+            work_done_progress_params: WorkDoneProgressParams::default(),
+        });
+        let HoverContents::Markup(content) = hover.unwrap().contents else {
+            panic!("Expected Markdown hover contents.");
+        };
+        assert!(content.value.contains("**Cloze card for “baz”**"));
+        assert!(content.value.contains("New, never reviewed.\nSuspended."));
+        client.shutdown()?;
+
+        let db = open_database(&directory, None)?;
+        assert_eq!(db.due_today(Timestamp::now().date())?.len(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_media_completion() -> Fallible<()> {
+        let directory = create_tmp_directory()?;
+        create_dir_all(directory.join("images/birds"))?;
+        write(directory.join("images/cat.png"), "")?;
+        write(directory.join("images/notes.txt"), "")?;
+        write(directory.join("images/.hidden.png"), "")?;
+        write(directory.join("deck.md"), "")?;
+        let uri = path_to_uri(&directory.join("deck.md"));
+        let mut client = Client::start(directory);
+        client.open(&uri, "Q: What is this?\nA: ![](@/images/c");
+
+        let completions = client.request::<Completion>(CompletionParams {
+            text_document_position: position(&uri, 1, 18),
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: PartialResultParams::default(),
+            context: None,
+        });
+        let Some(CompletionResponse::Array(items)) = completions else {
+            panic!("Expected completion items.");
+        };
+        let labels: Vec<&str> = items.iter().map(|item| item.label.as_str()).collect();
+        assert_eq!(labels, vec!["birds/", "cat.png"]);
+        let Some(CompletionTextEdit::Edit(edit)) = &items[1].text_edit else {
+            panic!("Expected a text edit.");
+        };
+        assert_eq!(edit.range.start, Position::new(1, 17));
+        client.shutdown()
+    }
+
+    #[test]
+    fn test_media_path_prefix() {
+        assert_eq!(media_path_prefix("![](@/images/c"), Some("images/c"));
+        assert_eq!(media_path_prefix("![](@/"), Some(""));
+        assert_eq!(media_path_prefix("![](@/a.png) and"), None);
+        assert_eq!(media_path_prefix("![](a.png"), None);
+    }
+
+    #[test]
+    fn test_columns() {
+        assert_eq!(utf16_len("a😀b"), 4);
+        assert_eq!(byte_offset("a😀b", 3), 5);
+        assert_eq!(byte_offset("ab", 10), 2);
+    }
+}
@@ -12,8 +12,14 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+pub mod browse;
+pub mod card;
 pub mod check;
 pub mod drill;
 pub mod export;
+pub mod fmt;
+pub mod lint;
+pub mod lsp;
 pub mod orphans;
+pub mod rehash;
 pub mod stats;
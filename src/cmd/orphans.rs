@@ -18,8 +18,8 @@ use crate::collection::Collection;
 use crate::error::Fallible;
 use crate::types::card_hash::CardHash;
 
-pub fn list_orphans(directory: Option<String>) -> Fallible<()> {
-    let coll = Collection::new(directory)?;
+pub fn list_orphans(directory: Option<String>, db: Option<String>) -> Fallible<()> {
+    let coll = Collection::new(directory, db)?;
     let orphans: Vec<CardHash> = get_orphans(&coll)?;
     // Print.
     for hash in orphans {
@@ -28,8 +28,8 @@ pub fn list_orphans(directory: Option<String>) -> Fallible<()> {
     Ok(())
 }
 
-pub fn delete_orphans(directory: Option<String>) -> Fallible<()> {
-    let coll = Collection::new(directory)?;
+pub fn delete_orphans(directory: Option<String>, db: Option<String>) -> Fallible<()> {
+    let coll = Collection::new(directory, db)?;
     let orphans: Vec<CardHash> = get_orphans(&coll)?;
     for hash in &orphans {
         coll.db.delete_card(*hash)?;
@@ -64,7 +64,7 @@ mod tests {
     #[test]
     fn test_get_orphans() -> Fallible<()> {
         let dir: String = create_tmp_copy_of_test_directory()?;
-        let coll = Collection::new(Some(dir))?;
+        let coll = Collection::new(Some(dir), None)?;
         let hash = CardHash::hash_bytes(b"a");
         let now = Timestamp::now();
         coll.db.insert_card(hash, now)?;
@@ -76,12 +76,12 @@ mod tests {
     #[test]
     fn test_list_and_delete_orphans() -> Fallible<()> {
         let dir: String = create_tmp_copy_of_test_directory()?;
-        let coll = Collection::new(Some(dir.clone()))?;
+        let coll = Collection::new(Some(dir.clone()), None)?;
         let hash = CardHash::hash_bytes(b"a");
         let now = Timestamp::now();
         coll.db.insert_card(hash, now)?;
-        list_orphans(Some(dir.clone()))?;
-        delete_orphans(Some(dir.clone()))?;
+        list_orphans(Some(dir.clone()), None)?;
+        delete_orphans(Some(dir.clone()), None)?;
         assert!(coll.db.card_hashes()?.is_empty());
         Ok(())
     }
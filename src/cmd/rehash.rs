@@ -0,0 +1,68 @@
+// Copyright 2025 Fernando Borretti
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::collection::Collection;
+use crate::error::Fallible;
+use crate::types::card_hash::CardHash;
+use crate::types::card_hash::HashVersion;
+
+/// Switch a collection to normalized hashes, re-keying the cards and reviews
+/// in the database so that no progress is lost.
+pub fn rehash_collection(directory: Option<String>, db: Option<String>) -> Fallible<()> {
+    let mut coll = Collection::new(directory, db)?;
+    if coll.db.hash_version()? == HashVersion::Normalized {
+        println!("Collection already uses normalized hashes.");
+        return Ok(());
+    }
+    let mapping: Vec<(CardHash, CardHash)> = coll
+        .cards
+        .iter()
+        .map(|card| {
+            (
+                card.hash(),
+                card.content().hash(HashVersion::Normalized, card.format()),
+            )
+        })
+        .collect();
+    let count = coll.db.rehash_cards(&mapping, HashVersion::Normalized)?;
+    println!("Re-keyed {count} cards.");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helper::create_tmp_copy_of_test_directory;
+    use crate::types::timestamp::Timestamp;
+
+    #[test]
+    fn test_rehash_collection() -> Fallible<()> {
+        let dir = create_tmp_copy_of_test_directory()?;
+        let coll = Collection::new(Some(dir.clone()), None)?;
+        let now = Timestamp::now();
+        for card in coll.cards.iter() {
+            coll.db.insert_card(card.hash(), now)?;
+        }
+        rehash_collection(Some(dir.clone()), None)?;
+        let coll = Collection::new(Some(dir.clone()), None)?;
+        assert_eq!(coll.db.hash_version()?, HashVersion::Normalized);
+        let db_hashes = coll.db.card_hashes()?;
+        for card in coll.cards.iter() {
+            assert!(db_hashes.contains(&card.hash()));
+        }
+        // Running it again is a no-op.
+        rehash_collection(Some(dir), None)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,193 @@
+// Copyright 2025 Fernando Borretti
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! How well the retrievability predicted by FSRS matches the reviews. Each
+//! retention review is bucketed by the retrievability predicted at the time,
+//! and the mean prediction of each bucket is compared against its pass rate.
+
+use serde::Serialize;
+
+use crate::cmd::stats::history::ReviewStats;
+use crate::types::performance::TARGET_RECALL;
+
+/// The lower bounds of the buckets of predicted retrievability. Most
+/// predictions are near the target, so the buckets are narrower there.
+pub const BUCKETS: [f64; 8] = [0.0, 0.5, 0.6, 0.7, 0.8, 0.85, 0.9, 0.95];
+
+/// Predictions are clamped to this distance from 0 and 1 in the log-loss,
+/// which is otherwise infinite for a confident miss.
+const EPSILON: f64 = 1e-6;
+
+#[derive(Serialize, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Calibration {
+    /// The retrievability the scheduler aims for.
+    pub target: f64,
+    pub review_count: usize,
+    /// The mean predicted retrievability.
+    pub predicted: Option<f64>,
+    /// The share of reviews passed.
+    pub observed: Option<f64>,
+    /// The root mean squared error between each bucket's mean prediction and
+    /// pass rate, weighted by the bucket's reviews.
+    pub rmse: Option<f64>,
+    /// The mean log-loss of the predictions.
+    pub log_loss: Option<f64>,
+    pub buckets: Vec<CalibrationBucket>,
+}
+
+#[derive(Serialize, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CalibrationBucket {
+    pub lower: f64,
+    pub upper: f64,
+    pub review_count: usize,
+    pub predicted: Option<f64>,
+    pub observed: Option<f64>,
+}
+
+/// The calibration of the given reviews. Reviews without a prediction, which
+/// don't test retention, are left out.
+pub fn calibration<'a>(reviews: impl IntoIterator<Item = &'a ReviewStats>) -> Calibration {
+    // The count, sum of predictions, and passes in each bucket.
+    let mut sums = [(0usize, 0.0, 0usize); BUCKETS.len()];
+    let mut log_loss = 0.0;
+    for review in reviews {
+        let Some(predicted) = review.predicted() else {
+            continue;
+        };
+        let passed = review.passed();
+        let i = BUCKETS
+            .iter()
+            .rposition(|bound| predicted >= *bound)
+            .unwrap_or(0);
+        let (count, sum, passes) = &mut sums[i];
+        *count += 1;
+        *sum += predicted;
+        if passed {
+            *passes += 1;
+        }
+        let p = predicted.clamp(EPSILON, 1.0 - EPSILON);
+        log_loss -= if passed { p.ln() } else { (1.0 - p).ln() };
+    }
+    let buckets: Vec<CalibrationBucket> = sums
+        .iter()
+        .enumerate()
+        .map(|(i, (count, sum, passes))| CalibrationBucket {
+            lower: BUCKETS[i],
+            upper: BUCKETS.get(i + 1).copied().unwrap_or(1.0),
+            review_count: *count,
+            predicted: ratio(*sum, *count),
+            observed: ratio(*passes as f64, *count),
+        })
+        .collect();
+    let count: usize = sums.iter().map(|(count, _, _)| count).sum();
+    let sum: f64 = sums.iter().map(|(_, sum, _)| sum).sum();
+    let passes: usize = sums.iter().map(|(_, _, passes)| passes).sum();
+    let squared_error: f64 = buckets
+        .iter()
+        .filter_map(|b| match (b.predicted, b.observed) {
+            (Some(predicted), Some(observed)) => {
+                Some(b.review_count as f64 * (predicted - observed).powi(2))
+            }
+            _ => None,
+        })
+        .sum();
+    Calibration {
+        target: TARGET_RECALL,
+        review_count: count,
+        predicted: ratio(sum, count),
+        observed: ratio(passes as f64, count),
+        rmse: ratio(squared_error, count).map(f64::sqrt),
+        log_loss: ratio(log_loss, count),
+        buckets,
+    }
+}
+
+fn ratio(value: f64, count: usize) -> Option<f64> {
+    if count == 0 {
+        None
+    } else {
+        Some(value / count as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::ReviewRecord;
+    use crate::fsrs::Grade;
+    use crate::fsrs::retrievability;
+    use crate::types::card_hash::CardHash;
+    use crate::types::date::Date;
+    use crate::types::timestamp::Timestamp;
+
+    fn review(elapsed_days: i64, stability: f64, grade: Grade) -> ReviewStats {
+        ReviewStats {
+            deck_name: None,
+            record: ReviewRecord {
+                card_hash: CardHash::hash_bytes(b"a"),
+                reviewed_at: Timestamp::now(),
+                grade,
+                stability: 1.0,
+                difficulty: 5.0,
+                interval_raw: 1.0,
+                interval_days: 1,
+                due_date: Date::today(),
+                duration_ms: None,
+            },
+            elapsed_days: Some(elapsed_days),
+            previous_stability: Some(stability),
+        }
+    }
+
+    fn approx_eq(a: Option<f64>, b: f64) -> bool {
+        a.is_some_and(|a| (a - b).abs() < 1e-9)
+    }
+
+    #[test]
+    fn test_calibration_empty() {
+        let calibration = calibration(&[]);
+        assert_eq!(calibration.review_count, 0);
+        assert_eq!(calibration.rmse, None);
+        assert_eq!(calibration.log_loss, None);
+        assert_eq!(calibration.buckets.len(), BUCKETS.len());
+    }
+
+    #[test]
+    fn test_calibration() {
+        // Reviewed halfway to the stability, the prediction is 90–95%.
+        let p = retrievability(5.0, 10.0);
+        assert!((0.9..0.95).contains(&p));
+        let mut reviews: Vec<ReviewStats> = (0..3).map(|_| review(5, 10.0, Grade::Good)).collect();
+        reviews.push(review(5, 10.0, Grade::Forgot));
+        // A same-day review has no prediction.
+        reviews.push(review(0, 10.0, Grade::Forgot));
+
+        let calibration = calibration(&reviews);
+        assert_eq!(calibration.review_count, 4);
+        assert!(approx_eq(calibration.predicted, p));
+        assert!(approx_eq(calibration.observed, 0.75));
+        assert!(approx_eq(calibration.rmse, (p - 0.75).abs()));
+        let log_loss = -(3.0 * p.ln() + (1.0 - p).ln()) / 4.0;
+        assert!(approx_eq(calibration.log_loss, log_loss));
+
+        // All the reviews are in the 90–95% bucket.
+        let bucket = &calibration.buckets[6];
+        assert_eq!((bucket.lower, bucket.upper), (0.9, 0.95));
+        assert_eq!(bucket.review_count, 4);
+        let others: usize = calibration.buckets.iter().map(|b| b.review_count).sum();
+        assert_eq!(others, 4);
+    }
+}
@@ -0,0 +1,359 @@
+// Copyright 2025 Fernando Borretti
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use crate::collection::Collection;
+use crate::db::ReviewRecord;
+use crate::error::Fallible;
+use crate::fsrs::Grade;
+use crate::fsrs::Recall;
+use crate::fsrs::Stability;
+use crate::fsrs::retrievability;
+use crate::types::aliases::DeckName;
+use crate::types::card_hash::CardHash;
+use crate::types::date::Date;
+use crate::types::performance::Performance;
+use crate::types::performance::ReviewedPerformance;
+
+/// A card in the collection, with its schedule.
+pub struct CardStats {
+    pub deck_name: DeckName,
+    pub performance: Performance,
+    pub suspended: bool,
+}
+
+impl CardStats {
+    pub fn reviewed(&self) -> Option<&ReviewedPerformance> {
+        match &self.performance {
+            Performance::New => None,
+            Performance::Reviewed(p) => Some(p),
+        }
+    }
+
+    /// Is the card due on or before the given day? New and suspended cards
+    /// are not.
+    pub fn is_due(&self, day: Date) -> bool {
+        !self.suspended && self.reviewed().is_some_and(|p| p.due_date <= day)
+    }
+}
+
+/// A review, with the context needed to aggregate it.
+pub struct ReviewStats {
+    /// The deck of the card, if it is still in the collection.
+    pub deck_name: Option<DeckName>,
+    pub record: ReviewRecord,
+    /// The days since the card's previous review, if it had one.
+    pub elapsed_days: Option<i64>,
+    /// The card's stability after its previous review, if it had one.
+    pub previous_stability: Option<Stability>,
+}
+
+impl ReviewStats {
+    /// Does the review test the card's retention? That is, was the card
+    /// reviewed before, on an earlier day. Repeats within a day, after a card
+    /// is forgotten, don't count.
+    pub fn tests_retention(&self) -> bool {
+        self.elapsed_days.is_some_and(|days| days > 0)
+    }
+
+    pub fn passed(&self) -> bool {
+        self.record.grade != Grade::Forgot
+    }
+
+    pub fn date(&self) -> Date {
+        self.record.reviewed_at.date()
+    }
+
+    /// The retrievability predicted at the time of a retention review.
+    pub fn predicted(&self) -> Option<Recall> {
+        match (self.elapsed_days, self.previous_stability) {
+            (Some(days), Some(stability)) if days > 0 => {
+                Some(retrievability(days as f64, stability))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The share of retention reviews that were passed.
+#[derive(Clone, Copy, Default)]
+pub struct Retention {
+    pub passed: usize,
+    pub total: usize,
+}
+
+impl Retention {
+    pub fn add(&mut self, review: &ReviewStats) {
+        if review.tests_retention() {
+            self.total += 1;
+            if review.passed() {
+                self.passed += 1;
+            }
+        }
+    }
+
+    pub fn rate(&self) -> Option<f64> {
+        if self.total == 0 {
+            None
+        } else {
+            Some(self.passed as f64 / self.total as f64)
+        }
+    }
+}
+
+/// The cards in a collection and the reviews in its database.
+pub struct History {
+    pub today: Date,
+    pub cards: Vec<CardStats>,
+    /// All the reviews, oldest first.
+    pub reviews: Vec<ReviewStats>,
+}
+
+impl History {
+    pub fn load(coll: &Collection, today: Date) -> Fallible<Self> {
+        let performance: HashMap<CardHash, Performance> = coll.db.all_card_performance()?;
+        let suspended: HashSet<CardHash> = coll.db.suspended_card_hashes()?;
+        let cards: Vec<CardStats> = coll
+            .cards
+            .iter()
+            .map(|card| CardStats {
+                deck_name: card.deck_name().clone(),
+                performance: performance
+                    .get(&card.hash())
+                    .copied()
+                    .unwrap_or(Performance::New),
+                suspended: suspended.contains(&card.hash()),
+            })
+            .collect();
+        let decks: HashMap<CardHash, &DeckName> = coll
+            .cards
+            .iter()
+            .map(|card| (card.hash(), card.deck_name()))
+            .collect();
+        let mut last_reviewed: HashMap<CardHash, (Date, Stability)> = HashMap::new();
+        let reviews: Vec<ReviewStats> = coll
+            .db
+            .get_all_reviews()?
+            .into_iter()
+            .map(|row| {
+                let record = row.data;
+                let date = record.reviewed_at.date();
+                let previous = last_reviewed.insert(record.card_hash, (date, record.stability));
+                ReviewStats {
+                    deck_name: decks.get(&record.card_hash).map(|d| (*d).clone()),
+                    record,
+                    elapsed_days: previous
+                        .map(|(last, _)| (date.into_inner() - last.into_inner()).num_days()),
+                    previous_stability: previous.map(|(_, stability)| stability),
+                }
+            })
+            .collect();
+        Ok(Self {
+            today,
+            cards,
+            reviews,
+        })
+    }
+
+    /// The names of the decks in the collection, in order.
+    pub fn decks(&self) -> Vec<DeckName> {
+        let mut decks: Vec<DeckName> = self.cards.iter().map(|c| c.deck_name.clone()).collect();
+        decks.sort();
+        decks.dedup();
+        decks
+    }
+
+    /// The number of reviews on each day with reviews.
+    pub fn daily_counts(&self) -> BTreeMap<Date, usize> {
+        let mut counts = BTreeMap::new();
+        for review in &self.reviews {
+            *counts.entry(review.date()).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// The number of cards due on each of the given number of days, starting
+    /// today. Overdue cards count as due today.
+    pub fn due_forecast(&self, days: usize) -> Vec<usize> {
+        let mut forecast = vec![0; days];
+        for card in &self.cards {
+            if card.suspended {
+                continue;
+            }
+            if let Some(p) = card.reviewed() {
+                let offset = (p.due_date.into_inner() - self.today.into_inner()).num_days();
+                let offset = offset.max(0) as usize;
+                if offset < days {
+                    forecast[offset] += 1;
+                }
+            }
+        }
+        forecast
+    }
+
+    /// Retention by month, as `YYYY-MM`, for the months with retention
+    /// reviews.
+    pub fn retention_by_month(&self) -> BTreeMap<String, Retention> {
+        let mut months: BTreeMap<String, Retention> = BTreeMap::new();
+        for review in self.reviews.iter().filter(|r| r.tests_retention()) {
+            let month = review.date().into_inner().format("%Y-%m").to_string();
+            months.entry(month).or_default().add(review);
+        }
+        months
+    }
+
+    /// Retention over all reviews.
+    pub fn retention(&self) -> Retention {
+        let mut retention = Retention::default();
+        for review in &self.reviews {
+            retention.add(review);
+        }
+        retention
+    }
+}
+
+/// Count the values in each bucket. A bucket is given by its inclusive lower
+/// bound, and ends where the next one starts.
+pub fn histogram(values: impl IntoIterator<Item = f64>, bounds: &[f64]) -> Vec<usize> {
+    let mut counts = vec![0; bounds.len()];
+    for value in values {
+        if let Some(i) = bounds.iter().rposition(|bound| value >= *bound) {
+            counts[i] += 1;
+        }
+    }
+    counts
+}
+
+/// The mean of the values, if there are any.
+pub fn mean(values: impl IntoIterator<Item = f64>) -> Option<f64> {
+    let (sum, count) = values
+        .into_iter()
+        .fold((0.0, 0usize), |(sum, count), value| {
+            (sum + value, count + 1)
+        });
+    if count == 0 {
+        None
+    } else {
+        Some(sum / count as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+    use crate::helper::create_tmp_copy_of_test_directory;
+    use crate::helper::save_session;
+    use crate::types::performance::update_performance;
+    use crate::types::timestamp::Timestamp;
+
+    #[test]
+    fn test_histogram() {
+        let counts = histogram([0.5, 1.0, 1.5, 2.0, 10.0], &[1.0, 2.0, 5.0]);
+        assert_eq!(counts, vec![2, 1, 1]);
+        assert_eq!(mean([1.0, 2.0, 6.0]), Some(3.0));
+        assert_eq!(mean([]), None);
+    }
+
+    #[test]
+    fn test_history() -> Fallible<()> {
+        let directory = create_tmp_copy_of_test_directory()?;
+        let mut coll = Collection::new(Some(directory), None)?;
+        let hashes: Vec<CardHash> = coll.cards.iter().map(|c| c.hash()).collect();
+        let start = Timestamp::now();
+        let day = |n: i64| Timestamp::new(start.into_inner() + Duration::days(n));
+        coll.db.insert_cards(hashes.iter().copied(), start)?;
+
+        // The first card is reviewed three times: forgotten and repeated on
+        // the first day, then passed two days later.
+        let mut records = Vec::new();
+        let mut performance = Performance::New;
+        for (reviewed_at, grade) in [
+            (day(0), Grade::Forgot),
+            (day(0), Grade::Good),
+            (day(2), Grade::Good),
+        ] {
+            let p = update_performance(performance, grade, reviewed_at);
+            records.push(ReviewRecord {
+                card_hash: hashes[0],
+                reviewed_at,
+                grade,
+                stability: p.stability,
+                difficulty: p.difficulty,
+                interval_raw: p.interval_raw,
+                interval_days: p.interval_days,
+                due_date: p.due_date,
+                duration_ms: None,
+            });
+            performance = Performance::Reviewed(p);
+        }
+        save_session(
+            &mut coll.db,
+            day(0),
+            day(2),
+            &records,
+            &[(hashes[0], performance)],
+        )?;
+
+        let history = History::load(&coll, day(2).date())?;
+        assert_eq!(history.cards.len(), 2);
+        assert_eq!(history.decks(), vec!["Deck".to_string()]);
+        let elapsed: Vec<Option<i64>> = history.reviews.iter().map(|r| r.elapsed_days).collect();
+        assert_eq!(elapsed, vec![None, Some(0), Some(2)]);
+        let stabilities: Vec<Option<f64>> = history
+            .reviews
+            .iter()
+            .map(|r| r.previous_stability)
+            .collect();
+        assert_eq!(
+            stabilities,
+            vec![
+                None,
+                Some(history.reviews[0].record.stability),
+                Some(history.reviews[1].record.stability)
+            ]
+        );
+        // Only the retention review has a prediction.
+        let predicted: Vec<Option<f64>> = history.reviews.iter().map(|r| r.predicted()).collect();
+        let stability = history.reviews[1].record.stability;
+        assert_eq!(
+            predicted,
+            vec![None, None, Some(retrievability(2.0, stability))]
+        );
+        let retention = history.retention();
+        assert_eq!((retention.passed, retention.total), (1, 1));
+        assert_eq!(retention.rate(), Some(1.0));
+        let counts = history.daily_counts();
+        assert_eq!(counts[&day(0).date()], 2);
+        assert_eq!(counts[&day(2).date()], 1);
+        assert_eq!(
+            history
+                .retention_by_month()
+                .values()
+                .map(|r| r.total)
+                .sum::<usize>(),
+            1
+        );
+
+        // The reviewed card is due in the future; the new card is not due.
+        let forecast = history.due_forecast(400);
+        assert_eq!(forecast.iter().sum::<usize>(), 1);
+        assert_eq!(forecast[0], 0);
+        Ok(())
+    }
+}
@@ -0,0 +1,572 @@
+// Copyright 2025 Fernando Borretti
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A self-contained HTML statistics report. Charts are inline SVG, and the
+//! styles are the drill UI's, so the report needs no server or scripts.
+
+use std::collections::BTreeMap;
+
+use chrono::Datelike;
+use chrono::Duration;
+use maud::DOCTYPE;
+use maud::Markup;
+use maud::PreEscaped;
+use maud::html;
+
+use crate::cmd::stats::calibration::Calibration;
+use crate::cmd::stats::calibration::calibration;
+use crate::cmd::stats::history::CardStats;
+use crate::cmd::stats::history::History;
+use crate::cmd::stats::history::Retention;
+use crate::cmd::stats::history::histogram;
+use crate::cmd::stats::history::mean;
+use crate::types::date::Date;
+
+/// The number of days in the due forecast.
+const FORECAST_DAYS: usize = 30;
+
+/// The lower bounds of the buckets of the interval and stability histograms,
+/// in days.
+const DAY_BUCKETS: [f64; 11] = [0.0, 1.0, 2.0, 3.0, 5.0, 8.0, 15.0, 31.0, 61.0, 121.0, 241.0];
+
+/// The lower bounds of the buckets of the difficulty histogram.
+const DIFFICULTY_BUCKETS: [f64; 10] = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0];
+
+pub fn render_report(history: &History) -> Markup {
+    html! {
+        (DOCTYPE)
+        html lang="en" {
+            head {
+                meta charset="utf-8";
+                meta name="viewport" content="width=device-width, initial-scale=1";
+                title { "hashcards statistics" }
+                style {
+                    (PreEscaped(include_str!("../drill/style.css")))
+                    (PreEscaped(include_str!("style.css")))
+                }
+            }
+            body {
+                div.finished.report {
+                    h1 { "Statistics" }
+                    div.summary {
+                        "As of " (history.today) "."
+                    }
+                    (summary_section(history))
+                    (reviews_section(history))
+                    (forecast_section(history))
+                    (distribution_section(history))
+                    (retention_section(history))
+                    (calibration_section(history))
+                    (decks_section(history))
+                }
+            }
+        }
+    }
+}
+
+fn summary_section(history: &History) -> Markup {
+    let cards = &history.cards;
+    let new = cards.iter().filter(|c| c.reviewed().is_none()).count();
+    let due = cards.iter().filter(|c| c.is_due(history.today)).count();
+    let suspended = cards.iter().filter(|c| c.suspended).count();
+    let reviewed_today = history
+        .reviews
+        .iter()
+        .filter(|r| r.date() == history.today)
+        .count();
+    let rows: Vec<(&str, String)> = vec![
+        ("Cards", cards.len().to_string()),
+        ("New Cards", new.to_string()),
+        ("Due Today", due.to_string()),
+        ("Suspended", suspended.to_string()),
+        ("Reviews", history.reviews.len().to_string()),
+        ("Reviewed Today", reviewed_today.to_string()),
+        ("Retention", percent(history.retention().rate())),
+        (
+            "Average Stability (days)",
+            decimal(mean(
+                cards
+                    .iter()
+                    .filter_map(|c| c.reviewed())
+                    .map(|p| p.stability),
+            )),
+        ),
+        (
+            "Average Difficulty",
+            decimal(mean(
+                cards
+                    .iter()
+                    .filter_map(|c| c.reviewed())
+                    .map(|p| p.difficulty),
+            )),
+        ),
+    ];
+    html! {
+        h2 { "Summary" }
+        div.stats {
+            table {
+                tbody {
+                    @for (key, val) in rows {
+                        tr {
+                            td .key { (key) }
+                            td .val { (val) }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn reviews_section(history: &History) -> Markup {
+    let counts = history.daily_counts();
+    let daily: Vec<Bar> = match counts.keys().next() {
+        Some(first) => days(*first, history.today)
+            .map(|day| {
+                let count = counts.get(&day).copied().unwrap_or(0);
+                Bar {
+                    label: day.to_string(),
+                    value: count as f64,
+                    title: format!("{day}: {count} reviews"),
+                }
+            })
+            .collect(),
+        None => Vec::new(),
+    };
+    html! {
+        h2 { "Reviews" }
+        h3 { "Last Year" }
+        (heatmap(&counts, history.today))
+        h3 { "Daily Reviews" }
+        @if daily.is_empty() {
+            p.empty { "No reviews yet." }
+        } @else {
+            (bar_chart(&daily, Unit::Count))
+        }
+    }
+}
+
+fn forecast_section(history: &History) -> Markup {
+    let forecast = history.due_forecast(FORECAST_DAYS);
+    let bars: Vec<Bar> = forecast
+        .iter()
+        .enumerate()
+        .map(|(offset, count)| {
+            let day = Date::new(history.today.into_inner() + Duration::days(offset as i64));
+            let label = match offset {
+                0 => "Today".to_string(),
+                _ => format!("+{offset}"),
+            };
+            Bar {
+                label,
+                value: *count as f64,
+                title: format!("{day}: {count} cards due"),
+            }
+        })
+        .collect();
+    html! {
+        h2 { "Due Forecast" }
+        p.caption { "Cards due on each of the next " (FORECAST_DAYS) " days. Overdue cards count as due today." }
+        (bar_chart(&bars, Unit::Count))
+    }
+}
+
+fn distribution_section(history: &History) -> Markup {
+    let reviewed = || history.cards.iter().filter_map(CardStats::reviewed);
+    let intervals = histogram(reviewed().map(|p| p.interval_days as f64), &DAY_BUCKETS);
+    let stability = histogram(reviewed().map(|p| p.stability), &DAY_BUCKETS);
+    let difficulty = histogram(reviewed().map(|p| p.difficulty), &DIFFICULTY_BUCKETS);
+    html! {
+        h2 { "Intervals" }
+        p.caption { "The number of cards by interval, in days." }
+        (bar_chart(&histogram_bars(&intervals, &DAY_BUCKETS, "days"), Unit::Count))
+        h2 { "Stability" }
+        p.caption { "The number of cards by stability: the days until the probability of recall falls to 90%." }
+        (bar_chart(&histogram_bars(&stability, &DAY_BUCKETS, "days"), Unit::Count))
+        h2 { "Difficulty" }
+        p.caption { "The number of cards by difficulty, from 1 (easiest) to 10 (hardest)." }
+        (bar_chart(&histogram_bars(&difficulty, &DIFFICULTY_BUCKETS, ""), Unit::Count))
+    }
+}
+
+fn retention_section(history: &History) -> Markup {
+    let months: BTreeMap<String, Retention> = history.retention_by_month();
+    let bars: Vec<Bar> = months
+        .iter()
+        .map(|(month, retention)| Bar {
+            label: month.clone(),
+            value: retention.rate().unwrap_or(0.0),
+            title: format!(
+                "{month}: {} of {} passed",
+                retention.passed, retention.total
+            ),
+        })
+        .collect();
+    html! {
+        h2 { "Retention" }
+        p.caption {
+            "The share of reviews passed (graded other than Forgot), of cards last reviewed on an earlier day."
+        }
+        @if months.is_empty() {
+            p.empty { "No reviews yet." }
+        } @else {
+            (bar_chart(&bars, Unit::Percent))
+            table.grid {
+                thead {
+                    tr {
+                        th { "Month" }
+                        th.num { "Reviews" }
+                        th.num { "Passed" }
+                        th.num { "Retention" }
+                    }
+                }
+                tbody {
+                    @for (month, retention) in months.iter().rev() {
+                        tr {
+                            td { (month) }
+                            td.num { (retention.total) }
+                            td.num { (retention.passed) }
+                            td.num { (percent(retention.rate())) }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn calibration_section(history: &History) -> Markup {
+    let overall = calibration(&history.reviews);
+    let decks: Vec<(String, Calibration)> = history
+        .decks()
+        .into_iter()
+        .map(|deck| {
+            let reviews = history
+                .reviews
+                .iter()
+                .filter(|r| r.deck_name.as_ref() == Some(&deck));
+            (deck.clone(), calibration(reviews))
+        })
+        .collect();
+    html! {
+        h2 { "Calibration" }
+        p.caption {
+            "Retention reviews bucketed by the retrievability predicted at the time, \
+             against the share passed. A well-calibrated model has the two close \
+             together; the scheduler aims for a retrievability of "
+            (percent(Some(overall.target))) "."
+        }
+        @if overall.review_count == 0 {
+            p.empty { "No retention reviews yet." }
+        } @else {
+            table.grid {
+                thead {
+                    tr {
+                        th { "Predicted" }
+                        th.num { "Reviews" }
+                        th.num { "Mean Predicted" }
+                        th.num { "Observed" }
+                    }
+                }
+                tbody {
+                    @for bucket in overall.buckets.iter().filter(|b| b.review_count > 0) {
+                        tr {
+                            td { (format!("{:.0}–{:.0}%", bucket.lower * 100.0, bucket.upper * 100.0)) }
+                            td.num { (bucket.review_count) }
+                            td.num { (percent(bucket.predicted)) }
+                            td.num { (percent(bucket.observed)) }
+                        }
+                    }
+                    tr {
+                        td { "All" }
+                        td.num { (overall.review_count) }
+                        td.num { (percent(overall.predicted)) }
+                        td.num { (percent(overall.observed)) }
+                    }
+                }
+            }
+            h3 { "By Deck" }
+            table.grid {
+                thead {
+                    tr {
+                        th { "Deck" }
+                        th.num { "Reviews" }
+                        th.num { "Predicted" }
+                        th.num { "Observed" }
+                        th.num { "RMSE" }
+                        th.num { "Log-Loss" }
+                    }
+                }
+                tbody {
+                    @for (deck, calibration) in decks.iter().chain([("All".to_string(), overall)].iter()) {
+                        tr {
+                            td { (deck) }
+                            td.num { (calibration.review_count) }
+                            td.num { (percent(calibration.predicted)) }
+                            td.num { (percent(calibration.observed)) }
+                            td.num { (metric(calibration.rmse)) }
+                            td.num { (metric(calibration.log_loss)) }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn decks_section(history: &History) -> Markup {
+    html! {
+        h2 { "Decks" }
+        table.grid {
+            thead {
+                tr {
+                    th { "Deck" }
+                    th.num { "Cards" }
+                    th.num { "New" }
+                    th.num { "Due" }
+                    th.num { "Reviews" }
+                    th.num { "Retention" }
+                    th.num { "Stability" }
+                    th.num { "Difficulty" }
+                }
+            }
+            tbody {
+                @for deck in history.decks() {
+                    @let cards: Vec<&CardStats> = history.cards.iter().filter(|c| c.deck_name == deck).collect();
+                    @let reviews = history.reviews.iter().filter(|r| r.deck_name.as_ref() == Some(&deck));
+                    @let retention = reviews.clone().fold(Retention::default(), |mut acc, r| { acc.add(r); acc });
+                    tr {
+                        td { (deck) }
+                        td.num { (cards.len()) }
+                        td.num { (cards.iter().filter(|c| c.reviewed().is_none()).count()) }
+                        td.num { (cards.iter().filter(|c| c.is_due(history.today)).count()) }
+                        td.num { (reviews.count()) }
+                        td.num { (percent(retention.rate())) }
+                        td.num { (decimal(mean(cards.iter().filter_map(|c| c.reviewed()).map(|p| p.stability)))) }
+                        td.num { (decimal(mean(cards.iter().filter_map(|c| c.reviewed()).map(|p| p.difficulty)))) }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A bar in a bar chart.
+struct Bar {
+    /// The label on the horizontal axis.
+    label: String,
+    value: f64,
+    /// The tooltip.
+    title: String,
+}
+
+#[derive(Clone, Copy)]
+enum Unit {
+    Count,
+    /// A fraction, shown as a percentage.
+    Percent,
+}
+
+const CHART_WIDTH: f64 = 800.0;
+const CHART_HEIGHT: f64 = 200.0;
+const CHART_LEFT: f64 = 48.0;
+const CHART_TOP: f64 = 8.0;
+const CHART_BOTTOM: f64 = 24.0;
+
+/// The most labels on the horizontal axis of a bar chart.
+const MAX_LABELS: usize = 10;
+
+fn bar_chart(bars: &[Bar], unit: Unit) -> Markup {
+    let max = match unit {
+        Unit::Count => bars.iter().map(|b| b.value).fold(0.0, f64::max).max(1.0),
+        Unit::Percent => 1.0,
+    };
+    let max_label = match unit {
+        Unit::Count => format!("{max}"),
+        Unit::Percent => "100%".to_string(),
+    };
+    let plot_width = CHART_WIDTH - CHART_LEFT;
+    let plot_height = CHART_HEIGHT - CHART_TOP - CHART_BOTTOM;
+    let slot = plot_width / bars.len().max(1) as f64;
+    let gap = if slot > 4.0 { slot * 0.2 } else { 0.0 };
+    let label_every = bars.len().div_ceil(MAX_LABELS).max(1);
+    let baseline = CHART_TOP + plot_height;
+    html! {
+        svg.chart viewBox=(format!("0 0 {CHART_WIDTH} {CHART_HEIGHT}")) role="img" {
+            text.axis x=(CHART_LEFT - 6.0) y=(CHART_TOP + 10.0) text-anchor="end" { (max_label) }
+            text.axis x=(CHART_LEFT - 6.0) y=(baseline) text-anchor="end" { "0" }
+            line.axis x1=(CHART_LEFT) y1=(baseline) x2=(CHART_WIDTH) y2=(baseline) {}
+            @for (i, bar) in bars.iter().enumerate() {
+                @let height = plot_height * bar.value / max;
+                @let x = CHART_LEFT + slot * i as f64 + gap / 2.0;
+                rect.bar x=(px(x)) y=(px(baseline - height)) width=(px(slot - gap)) height=(px(height)) {
+                    title { (bar.title) }
+                }
+                @if i % label_every == 0 {
+                    text.axis x=(px(x + (slot - gap) / 2.0)) y=(CHART_HEIGHT - 6.0) text-anchor="middle" { (bar.label) }
+                }
+            }
+        }
+    }
+}
+
+/// The bars of a histogram with buckets starting at the given bounds.
+fn histogram_bars(counts: &[usize], bounds: &[f64], unit: &str) -> Vec<Bar> {
+    counts
+        .iter()
+        .enumerate()
+        .map(|(i, count)| {
+            let label = bucket_label(bounds, i);
+            let title = format!("{label} {unit}: {count} cards").replace("  ", " ");
+            Bar {
+                label,
+                value: *count as f64,
+                title,
+            }
+        })
+        .collect()
+}
+
+fn bucket_label(bounds: &[f64], i: usize) -> String {
+    let lower = bounds[i];
+    match bounds.get(i + 1) {
+        None => format!("{lower}+"),
+        Some(upper) if lower == 0.0 => format!("<{upper}"),
+        Some(upper) if upper - lower <= 1.0 => format!("{lower}"),
+        Some(upper) => format!("{lower}–{}", upper - 1.0),
+    }
+}
+
+const CELL: f64 = 12.0;
+const CELL_GAP: f64 = 2.0;
+const HEATMAP_LEFT: f64 = 32.0;
+const HEATMAP_TOP: f64 = 16.0;
+
+/// A calendar of the reviews on each day of the last year, by week.
+fn heatmap(counts: &BTreeMap<Date, usize>, today: Date) -> Markup {
+    let today = today.into_inner();
+    let start = today - Duration::weeks(52);
+    let start = start - Duration::days(start.weekday().num_days_from_monday() as i64);
+    let start = Date::new(start);
+    let max = days(start, Date::new(today))
+        .filter_map(|day| counts.get(&day))
+        .copied()
+        .max()
+        .unwrap_or(0);
+    let step = CELL + CELL_GAP;
+    let weeks = ((today - start.into_inner()).num_days() / 7 + 1) as f64;
+    let width = HEATMAP_LEFT + weeks * step;
+    let height = HEATMAP_TOP + 7.0 * step;
+    html! {
+        svg.heatmap viewBox=(format!("0 0 {width} {height}")) role="img" {
+            @for (row, name) in [(0.0, "Mon"), (2.0, "Wed"), (4.0, "Fri")] {
+                text.axis x=(HEATMAP_LEFT - 6.0) y=(HEATMAP_TOP + row * step + CELL - 2.0) text-anchor="end" { (name) }
+            }
+            @for day in days(start, Date::new(today)) {
+                @let date = day.into_inner();
+                @let offset = (date - start.into_inner()).num_days();
+                @let x = HEATMAP_LEFT + (offset / 7) as f64 * step;
+                @let y = HEATMAP_TOP + date.weekday().num_days_from_monday() as f64 * step;
+                @let count = counts.get(&day).copied().unwrap_or(0);
+                @if date.day() == 1 {
+                    text.axis x=(x) y=(HEATMAP_TOP - 4.0) { (date.format("%b")) }
+                }
+                rect class=(format!("level-{}", level(count, max))) x=(x) y=(y) width=(CELL) height=(CELL) {
+                    title { (day) ": " (count) " reviews" }
+                }
+            }
+        }
+    }
+}
+
+/// The shade of a heatmap cell, from 0 (no reviews) to 4.
+fn level(count: usize, max: usize) -> usize {
+    if count == 0 || max == 0 {
+        0
+    } else {
+        (count * 4).div_ceil(max).clamp(1, 4)
+    }
+}
+
+/// The days from `start` to `end`, inclusive.
+fn days(start: Date, end: Date) -> impl Iterator<Item = Date> {
+    start
+        .into_inner()
+        .iter_days()
+        .take_while(move |day| *day <= end.into_inner())
+        .map(Date::new)
+}
+
+fn percent(rate: Option<f64>) -> String {
+    match rate {
+        Some(rate) => format!("{:.1}%", rate * 100.0),
+        None => "–".to_string(),
+    }
+}
+
+/// An error metric, which is small, so it gets more digits.
+fn metric(value: Option<f64>) -> String {
+    match value {
+        Some(value) => format!("{value:.3}"),
+        None => "–".to_string(),
+    }
+}
+
+/// An SVG coordinate, rounded to keep the markup small.
+fn px(value: f64) -> String {
+    format!("{value:.1}")
+}
+
+fn decimal(value: Option<f64>) -> String {
+    match value {
+        Some(value) => format!("{value:.2}"),
+        None => "–".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_label() {
+        let labels: Vec<String> = (0..DAY_BUCKETS.len())
+            .map(|i| bucket_label(&DAY_BUCKETS, i))
+            .collect();
+        assert_eq!(
+            labels,
+            vec![
+                "<1",
+                "1",
+                "2",
+                "3–4",
+                "5–7",
+                "8–14",
+                "15–30",
+                "31–60",
+                "61–120",
+                "121–240",
+                "241+"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_level() {
+        assert_eq!(level(0, 10), 0);
+        assert_eq!(level(1, 10), 1);
+        assert_eq!(level(5, 10), 2);
+        assert_eq!(level(10, 10), 4);
+    }
+}
@@ -0,0 +1,360 @@
+// Copyright 2025 Fernando Borretti
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The JSON statistics. The schema is documented in the README; fields may be
+//! added without notice, but removing a field or changing its meaning bumps
+//! [`SCHEMA_VERSION`].
+
+use serde::Serialize;
+
+use crate::cmd::stats::calibration::Calibration;
+use crate::cmd::stats::calibration::calibration;
+use crate::cmd::stats::history::CardStats;
+use crate::cmd::stats::history::History;
+use crate::cmd::stats::history::Retention;
+use crate::cmd::stats::history::ReviewStats;
+use crate::cmd::stats::history::mean;
+use crate::collection::Collection;
+use crate::error::Fallible;
+use crate::fsrs::Grade;
+use crate::types::date::Date;
+
+/// The version of the JSON schema.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Cards with an interval of at least this many days are mature.
+const MATURE_INTERVAL_DAYS: i64 = 21;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Stats {
+    pub schema_version: u32,
+    pub date: Date,
+    pub cards_in_deck_count: usize,
+    pub cards_in_db_count: usize,
+    pub tex_macro_count: usize,
+    pub cards_reviewed_today_count: usize,
+    pub cards: CardCounts,
+    pub reviews: Vec<WindowStats>,
+    pub streak: Streak,
+    pub session_count: usize,
+    pub study_time_seconds: i64,
+    pub calibration: Calibration,
+    pub decks: Vec<DeckStats>,
+}
+
+#[derive(Serialize, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CardCounts {
+    pub total: usize,
+    pub new: usize,
+    pub due: usize,
+    pub overdue: usize,
+    pub suspended: usize,
+    pub young: usize,
+    pub mature: usize,
+    pub average_stability: Option<f64>,
+    pub average_difficulty: Option<f64>,
+}
+
+/// The reviews in the last `days` days, including today, or all of them.
+#[derive(Serialize, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct WindowStats {
+    pub days: Option<u32>,
+    pub review_count: usize,
+    pub grades: GradeCounts,
+    pub retention: RetentionStats,
+}
+
+#[derive(Serialize, Default, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GradeCounts {
+    pub forgot: usize,
+    pub hard: usize,
+    pub good: usize,
+    pub easy: usize,
+}
+
+#[derive(Serialize, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RetentionStats {
+    pub passed: usize,
+    pub total: usize,
+    pub rate: Option<f64>,
+}
+
+/// Runs of consecutive days with reviews.
+#[derive(Serialize, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Streak {
+    /// The run ending today, or yesterday if there are no reviews today yet.
+    pub current: usize,
+    pub longest: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeckStats {
+    pub name: String,
+    pub cards: CardCounts,
+    pub reviews: Vec<WindowStats>,
+    pub calibration: Calibration,
+}
+
+pub fn get_stats(coll: &Collection, history: &History, windows: &[u32]) -> Fallible<Stats> {
+    let sessions = coll.db.get_all_sessions()?;
+    let study_time_seconds = sessions
+        .iter()
+        .map(|s| (s.ended_at.into_inner() - s.started_at.into_inner()).num_seconds())
+        .sum();
+    let all_cards: Vec<&CardStats> = history.cards.iter().collect();
+    let all_reviews: Vec<&ReviewStats> = history.reviews.iter().collect();
+    let decks = history
+        .decks()
+        .into_iter()
+        .map(|name| {
+            let cards: Vec<&CardStats> = history
+                .cards
+                .iter()
+                .filter(|c| c.deck_name == name)
+                .collect();
+            let reviews: Vec<&ReviewStats> = history
+                .reviews
+                .iter()
+                .filter(|r| r.deck_name.as_ref() == Some(&name))
+                .collect();
+            DeckStats {
+                cards: card_counts(&cards, history.today),
+                reviews: window_stats(&reviews, history.today, windows),
+                calibration: calibration(reviews),
+                name,
+            }
+        })
+        .collect();
+    Ok(Stats {
+        schema_version: SCHEMA_VERSION,
+        date: history.today,
+        cards_in_deck_count: coll.cards.len(),
+        cards_in_db_count: coll.db.card_hashes()?.len(),
+        tex_macro_count: coll.macros.len(),
+        cards_reviewed_today_count: coll.db.count_reviews_in_date(history.today)?,
+        cards: card_counts(&all_cards, history.today),
+        reviews: window_stats(&all_reviews, history.today, windows),
+        streak: streak(history),
+        session_count: sessions.len(),
+        study_time_seconds,
+        calibration: calibration(&history.reviews),
+        decks,
+    })
+}
+
+fn card_counts(cards: &[&CardStats], today: Date) -> CardCounts {
+    let reviewed = || cards.iter().filter_map(|c| c.reviewed());
+    let mature = reviewed()
+        .filter(|p| p.interval_days >= MATURE_INTERVAL_DAYS)
+        .count();
+    CardCounts {
+        total: cards.len(),
+        new: cards.iter().filter(|c| c.reviewed().is_none()).count(),
+        due: cards.iter().filter(|c| c.is_due(today)).count(),
+        overdue: cards.iter().filter(|c| c.is_due(yesterday(today))).count(),
+        suspended: cards.iter().filter(|c| c.suspended).count(),
+        young: reviewed().count() - mature,
+        mature,
+        average_stability: mean(reviewed().map(|p| p.stability)),
+        average_difficulty: mean(reviewed().map(|p| p.difficulty)),
+    }
+}
+
+/// The stats of each window, followed by all time.
+fn window_stats(reviews: &[&ReviewStats], today: Date, windows: &[u32]) -> Vec<WindowStats> {
+    windows
+        .iter()
+        .map(|days| Some(*days))
+        .chain([None])
+        .map(|days| {
+            let reviews = reviews.iter().filter(|r| match days {
+                Some(days) => {
+                    let age = (today.into_inner() - r.date().into_inner()).num_days();
+                    (0..days as i64).contains(&age)
+                }
+                None => true,
+            });
+            let mut count = 0;
+            let mut grades = GradeCounts::default();
+            let mut retention = Retention::default();
+            for review in reviews {
+                count += 1;
+                match review.record.grade {
+                    Grade::Forgot => grades.forgot += 1,
+                    Grade::Hard => grades.hard += 1,
+                    Grade::Good => grades.good += 1,
+                    Grade::Easy => grades.easy += 1,
+                }
+                retention.add(review);
+            }
+            WindowStats {
+                days,
+                review_count: count,
+                grades,
+                retention: RetentionStats {
+                    passed: retention.passed,
+                    total: retention.total,
+                    rate: retention.rate(),
+                },
+            }
+        })
+        .collect()
+}
+
+fn streak(history: &History) -> Streak {
+    let days = history.daily_counts();
+    let mut longest = 0;
+    let mut run = 0;
+    let mut previous: Option<Date> = None;
+    for day in days.keys() {
+        run = match previous {
+            Some(previous) if yesterday(*day) == previous => run + 1,
+            _ => 1,
+        };
+        longest = usize::max(longest, run);
+        previous = Some(*day);
+    }
+    let mut current = 0;
+    let mut day = if days.contains_key(&history.today) {
+        history.today
+    } else {
+        yesterday(history.today)
+    };
+    while days.contains_key(&day) {
+        current += 1;
+        day = yesterday(day);
+    }
+    Streak { current, longest }
+}
+
+fn yesterday(day: Date) -> Date {
+    Date::new(day.into_inner().pred_opt().unwrap_or(day.into_inner()))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+    use crate::db::ReviewRecord;
+    use crate::helper::create_tmp_copy_of_test_directory;
+    use crate::helper::save_session;
+    use crate::types::card_hash::CardHash;
+    use crate::types::performance::Performance;
+    use crate::types::performance::update_performance;
+    use crate::types::timestamp::Timestamp;
+
+    #[test]
+    fn test_get_stats() -> Fallible<()> {
+        let directory = create_tmp_copy_of_test_directory()?;
+        let mut coll = Collection::new(Some(directory), None)?;
+        let hashes: Vec<CardHash> = coll.cards.iter().map(|c| c.hash()).collect();
+        let start = Timestamp::now();
+        let day = |n: i64| Timestamp::new(start.into_inner() + Duration::days(n));
+        coll.db.insert_cards(hashes.iter().copied(), start)?;
+
+        // The first card is reviewed in a one-minute session on each of days
+        // 0, 1, 2 and 4.
+        let mut performance = Performance::New;
+        for (n, grade) in [
+            (0, Grade::Good),
+            (1, Grade::Forgot),
+            (2, Grade::Good),
+            (4, Grade::Easy),
+        ] {
+            let p = update_performance(performance, grade, day(n));
+            let record = ReviewRecord {
+                card_hash: hashes[0],
+                reviewed_at: day(n),
+                grade,
+                stability: p.stability,
+                difficulty: p.difficulty,
+                interval_raw: p.interval_raw,
+                interval_days: p.interval_days,
+                due_date: p.due_date,
+                duration_ms: None,
+            };
+            let ended_at = Timestamp::new(day(n).into_inner() + Duration::seconds(60));
+            performance = Performance::Reviewed(p);
+            save_session(
+                &mut coll.db,
+                day(n),
+                ended_at,
+                &[record],
+                &[(hashes[0], performance)],
+            )?;
+        }
+
+        let history = History::load(&coll, day(4).date())?;
+        let stats = get_stats(&coll, &history, &[1, 3])?;
+        assert_eq!(stats.schema_version, SCHEMA_VERSION);
+        assert_eq!(stats.cards_in_db_count, 2);
+        assert_eq!(stats.session_count, 4);
+        assert_eq!(stats.study_time_seconds, 240);
+        assert_eq!(
+            stats.streak,
+            Streak {
+                current: 1,
+                longest: 3
+            }
+        );
+        let cards = &stats.cards;
+        assert_eq!(
+            (cards.total, cards.new, cards.due, cards.overdue),
+            (2, 1, 0, 0)
+        );
+        assert_eq!(cards.young + cards.mature, 1);
+        assert!(cards.average_stability.is_some());
+
+        // The windows are the last day, the last three days, and all time.
+        let windows: Vec<(Option<u32>, usize)> = stats
+            .reviews
+            .iter()
+            .map(|w| (w.days, w.review_count))
+            .collect();
+        assert_eq!(windows, vec![(Some(1), 1), (Some(3), 2), (None, 4)]);
+        let all = &stats.reviews[2];
+        assert_eq!(
+            all.grades,
+            GradeCounts {
+                forgot: 1,
+                hard: 0,
+                good: 2,
+                easy: 1
+            }
+        );
+        // The first review doesn't test retention.
+        assert_eq!((all.retention.passed, all.retention.total), (2, 3));
+
+        assert_eq!(stats.decks.len(), 1);
+        assert_eq!(stats.decks[0].name, "Deck");
+        assert_eq!(stats.decks[0].reviews[2].review_count, 4);
+        assert_eq!(stats.calibration.review_count, 3);
+        assert_eq!(stats.decks[0].calibration, stats.calibration);
+
+        // Later on, the card is overdue, and the streak is over.
+        let history = History::load(&coll, day(400).date())?;
+        let stats = get_stats(&coll, &history, &[])?;
+        assert_eq!((stats.cards.due, stats.cards.overdue), (1, 1));
+        assert_eq!(stats.streak.current, 0);
+        Ok(())
+    }
+}
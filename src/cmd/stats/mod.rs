@@ -0,0 +1,148 @@
+// Copyright 2025 Fernando Borretti
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod calibration;
+mod history;
+mod html;
+mod json;
+
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fs::write;
+
+use clap::ValueEnum;
+
+use crate::cmd::stats::history::History;
+use crate::cmd::stats::html::render_report;
+use crate::cmd::stats::json::get_stats;
+use crate::collection::Collection;
+use crate::error::Fallible;
+use crate::types::date::Date;
+
+#[derive(ValueEnum, Clone)]
+pub enum StatsFormat {
+    /// HTML output.
+    Html,
+    /// JSON output.
+    Json,
+}
+
+impl Display for StatsFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StatsFormat::Html => write!(f, "html"),
+            StatsFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+pub fn print_stats(
+    directory: Option<String>,
+    db: Option<String>,
+    format: StatsFormat,
+    output: Option<String>,
+    windows: Vec<u32>,
+) -> Fallible<()> {
+    let coll = Collection::new(directory, db)?;
+    let history = History::load(&coll, Date::today())?;
+    let text = match format {
+        StatsFormat::Html => render_report(&history).into_string(),
+        StatsFormat::Json => {
+            let stats = get_stats(&coll, &history, &windows)?;
+            serde_json::to_string_pretty(&stats)?
+        }
+    };
+    match output {
+        Some(path) => write(path, text)?,
+        None => println!("{}", text),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helper::create_tmp_copy_of_test_directory;
+    use crate::helper::create_tmp_directory;
+
+    #[test]
+    fn test_display_stats_format() {
+        assert_eq!(StatsFormat::Html.to_string(), "html");
+        assert_eq!(StatsFormat::Json.to_string(), "json");
+    }
+
+    #[test]
+    fn test_print_stats_json() -> Fallible<()> {
+        let directory = create_tmp_copy_of_test_directory()?;
+        let tmp = create_tmp_directory()?;
+        let output = tmp.join("stats.json");
+        print_stats(
+            Some(directory),
+            None,
+            StatsFormat::Json,
+            Some(output.display().to_string()),
+            vec![7, 30],
+        )?;
+        let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(output)?)?;
+        assert_eq!(json["schemaVersion"], 1);
+        assert_eq!(json["cardsInDeckCount"], 2);
+        assert_eq!(json["cardsInDbCount"], 0);
+        assert_eq!(json["texMacroCount"], 1);
+        assert_eq!(json["cardsReviewedTodayCount"], 0);
+        assert_eq!(json["cards"]["new"], 2);
+        let days: Vec<Option<u64>> = json["reviews"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|w| w["days"].as_u64())
+            .collect();
+        assert_eq!(days, vec![Some(7), Some(30), None]);
+        assert_eq!(json["decks"][0]["name"], "Deck");
+        assert_eq!(json["calibration"]["reviewCount"], 0);
+        assert_eq!(json["calibration"]["rmse"], serde_json::Value::Null);
+        Ok(())
+    }
+
+    #[test]
+    fn test_print_stats_html() -> Fallible<()> {
+        let directory = create_tmp_copy_of_test_directory()?;
+        let tmp = create_tmp_directory()?;
+        let output = tmp.join("stats.html");
+        print_stats(
+            Some(directory),
+            None,
+            StatsFormat::Html,
+            Some(output.display().to_string()),
+            vec![],
+        )?;
+        let html = std::fs::read_to_string(output)?;
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        for section in [
+            "Summary",
+            "Reviews",
+            "Due Forecast",
+            "Intervals",
+            "Stability",
+            "Difficulty",
+            "Retention",
+            "Calibration",
+            "Decks",
+        ] {
+            assert!(html.contains(&format!("<h2>{section}</h2>")));
+        }
+        assert!(html.contains("<td>Deck</td>"));
+        assert!(html.contains("--progress-fill"));
+        Ok(())
+    }
+}
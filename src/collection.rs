@@ -13,17 +13,22 @@
 // limitations under the License.
 
 use std::env::current_dir;
+use std::env::var_os;
+use std::fs::create_dir_all;
 use std::fs::read_to_string;
+use std::path::Path;
 use std::path::PathBuf;
 use std::time::Instant;
 
+use crate::cache::parse_deck_checked;
+use crate::config::CollectionConfig;
 use crate::db::Database;
 use crate::error::ErrorReport;
 use crate::error::Fallible;
 use crate::error::fail;
 use crate::media::validate::validate_media_files;
-use crate::parser::parse_deck;
 use crate::types::card::Card;
+use crate::types::card_hash::HashVersion;
 
 pub struct Collection {
     pub directory: PathBuf,
@@ -32,57 +37,121 @@ pub struct Collection {
     pub macros: Vec<(String, String)>,
 }
 
-impl Collection {
-    pub fn new(directory: Option<String>) -> Fallible<Self> {
-        let directory: PathBuf = match directory {
-            Some(dir) => PathBuf::from(dir),
-            None => current_dir()?,
-        };
-        let directory: PathBuf = if directory.exists() {
-            directory.canonicalize()?
-        } else {
-            return fail("directory does not exist.");
-        };
-
-        let db_path: PathBuf = directory.join("hashcards.db");
-        let db_path: &str = db_path
-            .to_str()
-            .ok_or_else(|| ErrorReport::new("invalid path"))?;
-        let db: Database = Database::new(db_path)?;
-
-        let macros: Vec<(String, String)> = {
-            let mut macros = Vec::new();
-            let macros_path = directory.join("macros.tex");
-            if macros_path.exists() {
-                let content = read_to_string(macros_path)?;
-                for line in content.lines() {
-                    // Skip lines starting with '%'.
-                    if !line.trim_start().starts_with('%') {
-                        let split = line.split_once(' ');
-                        match split {
-                            Some((name, definition)) => {
-                                macros.push((name.to_string(), definition.to_string()));
-                            }
-                            None => {}
-                        }
-                    }
-                }
+/// Resolve the path to a collection: the given directory, or the current
+/// working directory.
+pub fn collection_directory(directory: Option<String>) -> Fallible<PathBuf> {
+    let directory: PathBuf = match directory {
+        Some(dir) => PathBuf::from(dir),
+        None => current_dir()?,
+    };
+    if directory.exists() {
+        Ok(directory.canonicalize()?)
+    } else {
+        fail("directory does not exist.")
+    }
+}
+
+/// The name of the database file.
+const DATABASE_FILE: &str = "hashcards.db";
+
+pub const MACROS_FILE: &str = "macros.tex";
+
+/// Find the database of the collection in the given directory. In order of
+/// precedence, this is:
+///
+/// 1. The given path, from the `--db` flag.
+/// 2. The `database` key in `hashcards.toml`, relative to the collection.
+/// 3. `hashcards.db` in the collection, if it exists.
+/// 4. A database in the state directory, keyed by the collection's path.
+pub fn database_path(directory: &Path, db: Option<String>) -> Fallible<PathBuf> {
+    if let Some(db) = db {
+        return Ok(PathBuf::from(db));
+    }
+    if let Some(db) = CollectionConfig::load(directory)?.database {
+        return Ok(directory.join(db));
+    }
+    let local = directory.join(DATABASE_FILE);
+    if local.exists() {
+        return Ok(local);
+    }
+    let state = state_directory()?.join(collection_key(directory)?);
+    Ok(state.join(DATABASE_FILE))
+}
+
+/// The directory where hashcards keeps per-user state: `$XDG_STATE_HOME`, or
+/// `~/.local/state`, followed by `hashcards`.
+fn state_directory() -> Fallible<PathBuf> {
+    // Keep the tests' databases out of the user's state directory.
+    #[cfg(test)]
+    crate::helper::isolate_state_directory();
+    let base: PathBuf = match var_os("XDG_STATE_HOME").map(PathBuf::from) {
+        Some(path) if path.is_absolute() => path,
+        _ => match var_os("HOME") {
+            Some(home) => PathBuf::from(home).join(".local").join("state"),
+            None => {
+                return fail(
+                    "can't find the state directory: set $XDG_STATE_HOME or $HOME, or use --db.",
+                );
             }
-            macros
-        };
-
-        let cards: Vec<Card> = {
-            log::debug!("Loading deck...");
-            let start = Instant::now();
-            let cards = parse_deck(&directory)?;
-            let end = Instant::now();
-            let duration = end.duration_since(start).as_millis();
-            log::debug!("Deck loaded in {duration}ms.");
-            cards
-        };
-
-        // Validate media files
-        validate_media_files(&cards, &directory)?;
+        },
+    };
+    Ok(base.join("hashcards"))
+}
+
+/// A name for the collection in the state directory: the collection
+/// directory's name, to make it recognizable, followed by a hash of its
+/// canonical path, to make it unique.
+fn collection_key(directory: &Path) -> Fallible<String> {
+    let canonical = directory.canonicalize()?;
+    let name = canonical
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "root".to_string());
+    let hash = blake3::hash(canonical.as_os_str().as_encoded_bytes()).to_hex();
+    Ok(format!("{name}-{}", &hash[..16]))
+}
+
+/// Open the database of the collection in the given directory (see
+/// `database_path`), creating it if it doesn't exist.
+pub fn open_database(directory: &Path, db: Option<String>) -> Fallible<Database> {
+    let db_path: PathBuf = database_path(directory, db)?;
+    log::debug!("Using the database at {}.", db_path.display());
+    if let Some(parent) = db_path.parent() {
+        create_dir_all(parent)?;
+    }
+    let db_path: &str = db_path
+        .to_str()
+        .ok_or_else(|| ErrorReport::new("invalid path"))?;
+    Database::new(db_path)
+}
+
+/// Open the database of the collection in the given directory, if it exists.
+/// Commands that only read the collection use this, so that they don't create
+/// a database.
+pub fn open_existing_database(directory: &Path, db: Option<String>) -> Fallible<Option<Database>> {
+    if database_path(directory, db.clone())?.exists() {
+        open_database(directory, db).map(Some)
+    } else {
+        Ok(None)
+    }
+}
+
+/// The hash version of the collection in the given directory. A collection
+/// without a database uses the default hash version.
+pub fn collection_hash_version(directory: &Path, db: Option<String>) -> Fallible<HashVersion> {
+    match open_existing_database(directory, db)? {
+        Some(db) => db.hash_version(),
+        None => Ok(HashVersion::Raw),
+    }
+}
+
+impl Collection {
+    pub fn new(directory: Option<String>, db: Option<String>) -> Fallible<Self> {
+        let directory: PathBuf = collection_directory(directory)?;
+        let mut db: Database = open_database(&directory, db)?;
+
+        let macros: Vec<(String, String)> = load_macros(&directory)?;
+        let cards: Vec<Card> = load_cards(&directory, &mut db)?;
 
         Ok(Self {
             directory,
@@ -92,3 +161,106 @@ impl Collection {
         })
     }
 }
+
+/// Read the KaTeX macros in the collection's `macros.tex`, if it exists.
+pub fn load_macros(directory: &Path) -> Fallible<Vec<(String, String)>> {
+    let mut macros = Vec::new();
+    let macros_path = directory.join(MACROS_FILE);
+    if macros_path.exists() {
+        let content = read_to_string(macros_path)?;
+        for line in content.lines() {
+            // Skip lines starting with '%'.
+            if !line.trim_start().starts_with('%') {
+                let split = line.split_once(' ');
+                match split {
+                    Some((name, definition)) => {
+                        macros.push((name.to_string(), definition.to_string()));
+                    }
+                    None => {}
+                }
+            }
+        }
+    }
+    Ok(macros)
+}
+
+/// Parse the cards in the collection, and check that the media they use
+/// exists.
+pub fn load_cards(directory: &Path, db: &mut Database) -> Fallible<Vec<Card>> {
+    log::debug!("Loading deck...");
+    let start = Instant::now();
+    // Only the media of the files that had to be parsed is validated; `check`
+    // validates every card.
+    let cards = parse_deck_checked(directory, db, |parsed| {
+        validate_media_files(parsed, directory)
+    })?;
+    let end = Instant::now();
+    let duration = end.duration_since(start).as_millis();
+    log::debug!("Deck loaded in {duration}ms.");
+    Ok(cards)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::write;
+
+    use super::*;
+    use crate::config::CONFIG_FILE;
+    use crate::helper::create_tmp_directory;
+
+    #[test]
+    fn test_database_path() -> Fallible<()> {
+        let directory = create_tmp_directory()?;
+
+        // By default, the database is in the state directory.
+        let default = database_path(&directory, None)?;
+        assert!(default.starts_with(state_directory()?));
+        assert_eq!(default, database_path(&directory, None)?);
+        assert!(!default.exists());
+
+        // An existing database in the collection is used.
+        write(directory.join(DATABASE_FILE), "")?;
+        assert_eq!(
+            database_path(&directory, None)?,
+            directory.join(DATABASE_FILE)
+        );
+
+        // The config file overrides it.
+        write(directory.join(CONFIG_FILE), "database = \"progress.db\"\n")?;
+        assert_eq!(
+            database_path(&directory, None)?,
+            directory.join("progress.db")
+        );
+
+        // The flag overrides everything.
+        assert_eq!(
+            database_path(&directory, Some("/tmp/other.db".to_string()))?,
+            PathBuf::from("/tmp/other.db")
+        );
+        Ok(())
+    }
+
+    /// Reading the hash version doesn't create a database, and opening one
+    /// creates its directory.
+    #[test]
+    fn test_open_existing_database() -> Fallible<()> {
+        let directory = create_tmp_directory()?;
+        let path = database_path(&directory, None)?;
+        assert_eq!(collection_hash_version(&directory, None)?, HashVersion::Raw);
+        assert!(open_existing_database(&directory, None)?.is_none());
+        assert!(!path.exists());
+        open_database(&directory, None)?;
+        assert!(path.exists());
+        assert!(open_existing_database(&directory, None)?.is_some());
+        Ok(())
+    }
+
+    /// Different collections get different databases.
+    #[test]
+    fn test_collection_key() -> Fallible<()> {
+        let a = create_tmp_directory()?;
+        let b = create_tmp_directory()?;
+        assert_ne!(collection_key(&a)?, collection_key(&b)?);
+        Ok(())
+    }
+}
@@ -0,0 +1,138 @@
+// Copyright 2025 Fernando Borretti
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fs::read_to_string;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::error::ErrorReport;
+use crate::error::Fallible;
+
+/// The name of the collection's configuration file.
+pub const CONFIG_FILE: &str = "hashcards.toml";
+
+/// Settings read from the `hashcards.toml` file at the root of a collection.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CollectionConfig {
+    /// Globs of deck files to parse, relative to the collection root. If empty,
+    /// every deck file is parsed.
+    pub include: Vec<String>,
+    /// Globs of deck files to skip, relative to the collection root.
+    pub exclude: Vec<String>,
+    /// Whether to follow symbolic links to directories.
+    pub follow_symlinks: bool,
+    /// Path to the database, relative to the collection root.
+    pub database: Option<String>,
+    /// The command to open a deck file at a line, with `{file}` and `{line}`
+    /// placeholders, e.g. `code -g {file}:{line}`.
+    pub editor: Option<String>,
+    /// Settings for the `lint` command.
+    pub lint: LintConfig,
+}
+
+/// Settings for the `lint` command, from the `[lint]` table.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct LintConfig {
+    /// The longest answer, in characters, before `long-answer` warns.
+    pub max_answer_length: usize,
+    /// The largest fraction of a cloze card's text that can be deleted before
+    /// `large-cloze` warns.
+    pub max_cloze_fraction: f64,
+    /// Rules that are turned off.
+    pub allow: Vec<String>,
+    /// Rules whose warnings are reported as errors.
+    pub deny: Vec<String>,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        Self {
+            max_answer_length: 300,
+            max_cloze_fraction: 0.5,
+            allow: Vec::new(),
+            deny: Vec::new(),
+        }
+    }
+}
+
+impl CollectionConfig {
+    /// Load the configuration of the collection in the given directory. A
+    /// missing configuration file means the default configuration.
+    pub fn load(directory: &Path) -> Fallible<Self> {
+        let path = directory.join(CONFIG_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let text = read_to_string(&path)?;
+        toml::from_str(&text)
+            .map_err(|e| ErrorReport::new(format!("Failed to parse {CONFIG_FILE}: {e}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_missing_config() -> Fallible<()> {
+        let directory = tempdir()?;
+        let config = CollectionConfig::load(directory.path())?;
+        assert!(config.include.is_empty());
+        assert!(config.exclude.is_empty());
+        assert!(!config.follow_symlinks);
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_config() -> Fallible<()> {
+        let directory = tempdir()?;
+        std::fs::write(
+            directory.path().join(CONFIG_FILE),
+            "include = [\"decks/**\"]\nexclude = [\"decks/drafts/**\"]\n",
+        )?;
+        let config = CollectionConfig::load(directory.path())?;
+        assert_eq!(config.include, vec!["decks/**"]);
+        assert_eq!(config.exclude, vec!["decks/drafts/**"]);
+        assert_eq!(config.lint.max_answer_length, 300);
+        Ok(())
+    }
+
+    #[test]
+    fn test_lint_config() -> Fallible<()> {
+        let directory = tempdir()?;
+        std::fs::write(
+            directory.path().join(CONFIG_FILE),
+            "[lint]\nmax-answer-length = 100\nallow = [\"same-answer\"]\n",
+        )?;
+        let config = CollectionConfig::load(directory.path())?;
+        assert_eq!(config.lint.max_answer_length, 100);
+        assert_eq!(config.lint.max_cloze_fraction, 0.5);
+        assert_eq!(config.lint.allow, vec!["same-answer"]);
+        assert!(config.lint.deny.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_key() -> Fallible<()> {
+        let directory = tempdir()?;
+        std::fs::write(directory.path().join(CONFIG_FILE), "foo = 1\n")?;
+        assert!(CollectionConfig::load(directory.path()).is_err());
+        Ok(())
+    }
+}
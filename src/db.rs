@@ -12,9 +12,13 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::HashMap;
 use std::collections::HashSet;
+use std::fs::remove_file;
+use std::path::Path;
 
 use rusqlite::Connection;
+use rusqlite::Row;
 use rusqlite::Transaction;
 use rusqlite::config::DbConfig;
 use rusqlite::params;
@@ -25,6 +29,7 @@ use crate::fsrs::Difficulty;
 use crate::fsrs::Grade;
 use crate::fsrs::Stability;
 use crate::types::card_hash::CardHash;
+use crate::types::card_hash::HashVersion;
 use crate::types::date::Date;
 use crate::types::performance::Performance;
 use crate::types::performance::ReviewedPerformance;
@@ -34,6 +39,45 @@ pub struct Database {
     conn: Connection,
 }
 
+/// The migrations that build the database schema, in order. The
+/// `user_version` of a database is the number of migrations applied to it.
+/// Released migrations must never be edited: change the schema by adding a new
+/// one.
+const MIGRATIONS: &[&str] = &[
+    include_str!("migrations/0001_initial.sql"),
+    include_str!("migrations/0002_metadata.sql"),
+    include_str!("migrations/0003_suspended_cards.sql"),
+    include_str!("migrations/0004_parse_cache.sql"),
+    include_str!("migrations/0005_due_date_index.sql"),
+    include_str!("migrations/0006_pending_sessions.sql"),
+    include_str!("migrations/0007_review_duration.sql"),
+];
+
+const HASH_VERSION_KEY: &str = "hash_version";
+
+/// The columns of the `cards` table that make up a card's performance, in the
+/// order `performance_from_row` expects them.
+const PERFORMANCE_COLUMNS: &str =
+    "last_reviewed_at, stability, difficulty, interval_raw, interval_days, due_date, review_count";
+
+/// The columns of the `reviews` table that make up a `ReviewRow`, in the
+/// order `review_from_row` expects them.
+const REVIEW_COLUMNS: &str = "review_id, card_hash, reviewed_at, grade, stability, difficulty, interval_raw, interval_days, due_date, duration_ms";
+
+/// An entry in the parse cache.
+pub struct ParseCacheEntry {
+    /// The absolute path of the deck file.
+    pub file_path: String,
+    /// The hash of the file's contents when it was parsed.
+    pub content_hash: String,
+    /// The hash version the cards were parsed with.
+    pub hash_version: HashVersion,
+    /// The version of hashcards whose parser produced the cards.
+    pub parser_version: String,
+    /// The parsed cards, serialized as JSON.
+    pub cards: String,
+}
+
 pub struct ReviewRecord {
     pub card_hash: CardHash,
     pub reviewed_at: Timestamp,
@@ -43,6 +87,20 @@ pub struct ReviewRecord {
     pub interval_raw: f64,
     pub interval_days: i64,
     pub due_date: Date,
+    /// How long it took to answer the card, in milliseconds, if known.
+    pub duration_ms: Option<i64>,
+}
+
+/// A drilling session that has not finished.
+pub struct PendingSession {
+    pub pending_session_id: i64,
+    pub started_at: Timestamp,
+    /// The hashes of the cards left to drill, in order.
+    pub queue: Vec<CardHash>,
+    /// The number of cards the session started with.
+    pub total_cards: usize,
+    /// The reviews so far, in order.
+    pub reviews: Vec<ReviewRecord>,
 }
 
 pub struct SessionRow {
@@ -60,13 +118,7 @@ impl Database {
     pub fn new(database_path: &str) -> Fallible<Self> {
         let mut conn = Connection::open(database_path)?;
         conn.set_db_config(DbConfig::SQLITE_DBCONFIG_ENABLE_FKEY, true)?;
-        {
-            let tx = conn.transaction()?;
-            if !probe_schema_exists(&tx)? {
-                tx.execute_batch(include_str!("schema.sql"))?;
-                tx.commit()?;
-            }
-        }
+        migrate(&mut conn, database_path)?;
         Ok(Self { conn })
     }
 
@@ -74,14 +126,35 @@ impl Database {
     ///
     /// If a card with the given hash exists, returns an error.
     pub fn insert_card(&self, card_hash: CardHash, added_at: Timestamp) -> Fallible<()> {
-        if self.card_exists(card_hash)? {
+        let sql = "insert into cards (card_hash, added_at, review_count) values (?, ?, 0) on conflict (card_hash) do nothing;";
+        if self.conn.execute(sql, params![card_hash, added_at])? == 0 {
             return fail("Card already exists");
         }
-        let sql = "insert into cards (card_hash, added_at, review_count) values (?, ?, 0);";
-        self.conn.execute(sql, params![card_hash, added_at])?;
         Ok(())
     }
 
+    /// Insert the cards that are not already in the database, in a single
+    /// transaction. Cards that exist are left unchanged.
+    ///
+    /// Returns the number of cards inserted.
+    pub fn insert_cards(
+        &mut self,
+        card_hashes: impl IntoIterator<Item = CardHash>,
+        added_at: Timestamp,
+    ) -> Fallible<usize> {
+        let tx = self.conn.transaction()?;
+        let mut count = 0;
+        {
+            let sql = "insert into cards (card_hash, added_at, review_count) values (?, ?, 0) on conflict (card_hash) do nothing;";
+            let mut stmt = tx.prepare(sql)?;
+            for card_hash in card_hashes {
+                count += stmt.execute(params![card_hash, added_at])?;
+            }
+        }
+        tx.commit()?;
+        Ok(count)
+    }
+
     /// Return the set of all card hashes in the database.
     pub fn card_hashes(&self) -> Fallible<HashSet<CardHash>> {
         let sql = "select card_hash from cards;";
@@ -97,70 +170,32 @@ impl Database {
         Ok(card_hashes)
     }
 
-    /// Find the hashes of the cards due today.
-    pub fn due_today(&self, today: Date) -> Fallible<HashSet<CardHash>> {
-        let mut due = HashSet::new();
-        let sql = "select card_hash, due_date from cards;";
-        let mut stmt = self.conn.prepare(sql)?;
-        let mut rows = stmt.query(params![])?;
-        while let Some(row) = rows.next()? {
-            let hash: CardHash = row.get(0)?;
-            let due_date: Option<Date> = row.get(1)?;
-            match due_date {
-                None => {
-                    // Never reviewed, so it's due.
-                    due.insert(hash);
-                }
-                Some(due_date) => {
-                    if due_date <= today {
-                        due.insert(hash);
-                    }
-                }
-            }
+    /// Find the cards due today, with their performance. New cards are always
+    /// due, and suspended cards are never due.
+    pub fn due_today(&self, today: Date) -> Fallible<HashMap<CardHash, Performance>> {
+        let sql = format!(
+            "select card_hash, {PERFORMANCE_COLUMNS} from cards
+             where (due_date is null or due_date <= ?)
+               and card_hash not in (select card_hash from suspended_cards);"
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(params![today], |row| {
+            let card_hash: CardHash = row.get(0)?;
+            Ok((card_hash, performance_from_row(row, 1)?))
+        })?;
+        let mut due = HashMap::new();
+        for row in rows {
+            let (card_hash, performance) = row?;
+            due.insert(card_hash, performance);
         }
         Ok(due)
     }
 
     /// Get a card's performance information.
     pub fn get_card_performance_opt(&self, card_hash: CardHash) -> Fallible<Option<Performance>> {
-        let sql = "select last_reviewed_at, stability, difficulty, interval_raw, interval_days, due_date, review_count from cards where card_hash = ?;";
-        let mut stmt = self.conn.prepare(sql)?;
-        let rows = stmt.query_map(params![card_hash], |row| {
-            let last_reviewed_at: Option<Timestamp> = row.get(0)?;
-            let stability: Option<Stability> = row.get(1)?;
-            let difficulty: Option<Difficulty> = row.get(2)?;
-            let interval_raw: Option<f64> = row.get(3)?;
-            let interval_days: Option<i64> = row.get(4)?;
-            let due_date: Option<Date> = row.get(5)?;
-            let review_count: i32 = row.get(6)?;
-            if let (
-                Some(last_reviewed_at),
-                Some(stability),
-                Some(difficulty),
-                Some(interval_raw),
-                Some(interval_days),
-                Some(due_date),
-            ) = (
-                last_reviewed_at,
-                stability,
-                difficulty,
-                interval_raw,
-                interval_days,
-                due_date,
-            ) {
-                Ok(Performance::Reviewed(ReviewedPerformance {
-                    last_reviewed_at,
-                    stability,
-                    difficulty,
-                    interval_raw,
-                    interval_days,
-                    due_date,
-                    review_count: review_count as usize,
-                }))
-            } else {
-                Ok(Performance::New)
-            }
-        })?;
+        let sql = format!("select {PERFORMANCE_COLUMNS} from cards where card_hash = ?;");
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(params![card_hash], |row| performance_from_row(row, 0))?;
         if let Some(row) = rows.into_iter().next() {
             Ok(Some(row?))
         } else {
@@ -179,83 +214,173 @@ impl Database {
         }
     }
 
-    /// Update a card's performance information.
-    ///
-    /// If no card with the given hash exists, returns an error.
-    pub fn update_card_performance(
+    /// Start a pending session, whose reviews are journaled as they happen,
+    /// with the given queue of cards. Returns its ID.
+    pub fn start_pending_session(
         &self,
-        card_hash: CardHash,
-        performance: Performance,
+        started_at: Timestamp,
+        queue: &[CardHash],
+        total_cards: usize,
+    ) -> Fallible<i64> {
+        let sql = "insert into pending_sessions (started_at, queue, total_cards) values (?, ?, ?) returning pending_session_id;";
+        let id: i64 = self.conn.query_row(
+            sql,
+            params![started_at, encode_queue(queue)?, total_cards as i64],
+            |row| row.get(0),
+        )?;
+        Ok(id)
+    }
+
+    /// Journal a review in a pending session, along with the queue of cards
+    /// left after it. This runs in a single transaction.
+    pub fn add_pending_review(
+        &mut self,
+        pending_session_id: i64,
+        review: &ReviewRecord,
+        queue: &[CardHash],
     ) -> Fallible<()> {
-        if !self.card_exists(card_hash)? {
-            return fail("Card not found");
-        }
-        let (
-            last_reviewed_at,
-            stability,
-            difficulty,
-            interval_raw,
-            interval_days,
-            due_date,
-            review_count,
-        ) = match performance {
-            Performance::New => (None, None, None, None, None, None, 0),
-            Performance::Reviewed(rp) => (
-                Some(rp.last_reviewed_at),
-                Some(rp.stability),
-                Some(rp.difficulty),
-                Some(rp.interval_raw),
-                Some(rp.interval_days as i32),
-                Some(rp.due_date),
-                rp.review_count as i32,
-            ),
-        };
-        let sql = "update cards set last_reviewed_at = ?, stability = ?, difficulty = ?, interval_raw = ?, interval_days = ?, due_date = ?, review_count = ? where card_hash = ?;";
-        let params = params![
-            last_reviewed_at,
-            stability,
-            difficulty,
-            interval_raw,
-            interval_days,
-            due_date,
-            review_count,
-            card_hash
-        ];
-        self.conn.execute(sql, params)?;
+        let tx = self.conn.transaction()?;
+        let sql = "insert into pending_reviews (pending_session_id, card_hash, reviewed_at, grade, stability, difficulty, interval_raw, interval_days, due_date, duration_ms) values (?, ?, ?, ?, ?, ?, ?, ?, ?, ?);";
+        tx.execute(
+            sql,
+            params![
+                pending_session_id,
+                review.card_hash,
+                review.reviewed_at,
+                review.grade,
+                review.stability,
+                review.difficulty,
+                review.interval_raw,
+                review.interval_days,
+                review.due_date,
+                review.duration_ms
+            ],
+        )?;
+        tx_set_pending_queue(&tx, pending_session_id, queue)?;
+        tx.commit()?;
         Ok(())
     }
 
-    /// Save a session.
-    pub fn save_session(
+    /// Replace the queue of cards left to drill in a pending session.
+    pub fn set_pending_queue(
         &mut self,
-        started_at: Timestamp,
+        pending_session_id: i64,
+        queue: &[CardHash],
+    ) -> Fallible<()> {
+        let tx = self.conn.transaction()?;
+        tx_set_pending_queue(&tx, pending_session_id, queue)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Remove the most recent review from a pending session, to undo it,
+    /// along with the queue of cards left after undoing it. This runs in a
+    /// single transaction.
+    pub fn remove_last_pending_review(
+        &mut self,
+        pending_session_id: i64,
+        queue: &[CardHash],
+    ) -> Fallible<()> {
+        let tx = self.conn.transaction()?;
+        let sql = "delete from pending_reviews where pending_review_id = (select max(pending_review_id) from pending_reviews where pending_session_id = ?);";
+        tx.execute(sql, params![pending_session_id])?;
+        tx_set_pending_queue(&tx, pending_session_id, queue)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// All the pending sessions, oldest first.
+    pub fn pending_sessions(&self) -> Fallible<Vec<PendingSession>> {
+        let sql = "select pending_session_id, started_at, queue, total_cards from pending_sessions order by pending_session_id;";
+        let mut stmt = self.conn.prepare(sql)?;
+        let rows: Vec<(i64, Timestamp, String, i64)> = stmt
+            .query_map([], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })?
+            .collect::<Result<_, _>>()?;
+        let mut sessions = Vec::new();
+        for (pending_session_id, started_at, queue, total_cards) in rows {
+            sessions.push(PendingSession {
+                pending_session_id,
+                started_at,
+                queue: decode_queue(&queue)?,
+                total_cards: total_cards as usize,
+                reviews: pending_reviews(&self.conn, pending_session_id)?,
+            });
+        }
+        Ok(sessions)
+    }
+
+    /// The performance of each card reviewed in a pending session, after its
+    /// last review there.
+    pub fn pending_performance(
+        &self,
+        pending_session_id: i64,
+    ) -> Fallible<HashMap<CardHash, ReviewedPerformance>> {
+        let reviews = pending_reviews(&self.conn, pending_session_id)?;
+        performance_after(&self.conn, &reviews)
+    }
+
+    /// Finish a pending session: record it, with its journaled reviews, as a
+    /// session, and write the given card performance. This runs in a single
+    /// transaction.
+    pub fn finish_pending_session(
+        &mut self,
+        pending_session_id: i64,
         ended_at: Timestamp,
-        reviews: Vec<ReviewRecord>,
+        performance: &[(CardHash, Performance)],
     ) -> Fallible<()> {
         let tx = self.conn.transaction()?;
-        let sql = "insert into sessions (started_at, ended_at) values (?, ?) returning session_id;";
-        let session_id: i64 = tx.query_row(sql, params![started_at, ended_at], |row| row.get(0))?;
-        for review in reviews {
-            let sql = "insert into reviews (session_id, card_hash, reviewed_at, grade, stability, difficulty, interval_raw, interval_days, due_date) values (?, ?, ?, ?, ?, ?, ?, ?, ?);";
-            tx.execute(
-                sql,
-                params![
-                    session_id,
-                    review.card_hash,
-                    review.reviewed_at,
-                    review.grade,
-                    review.stability,
-                    review.difficulty,
-                    review.interval_raw,
-                    review.interval_days as i32,
-                    review.due_date
-                ],
-            )?;
+        tx_finish_pending_session(&tx, pending_session_id, ended_at)?;
+        for (card_hash, performance) in performance {
+            if !tx_card_exists(&tx, *card_hash)? {
+                return fail("Card not found");
+            }
+            set_card_performance(&tx, *card_hash, *performance)?;
         }
         tx.commit()?;
         Ok(())
     }
 
+    /// Record the pending sessions left behind by interrupted drilling
+    /// sessions, except the one in `keep`, as if they had been finished after
+    /// their last review. Each reviewed card gets the performance from its
+    /// last review. Pending sessions without reviews are dropped. This runs in
+    /// a single transaction.
+    ///
+    /// Returns the number of reviews recovered.
+    pub fn recover_pending_sessions(&mut self, keep: Option<i64>) -> Fallible<usize> {
+        let mut count = 0;
+        let tx = self.conn.transaction()?;
+        let sessions: Vec<i64> = {
+            let sql =
+                "select pending_session_id from pending_sessions order by pending_session_id;";
+            let mut stmt = tx.prepare(sql)?;
+            stmt.query_map([], |row| row.get(0))?
+                .collect::<Result<_, _>>()?
+        };
+        for pending_session_id in sessions {
+            if Some(pending_session_id) == keep {
+                continue;
+            }
+            let reviews: Vec<ReviewRecord> = pending_reviews(&tx, pending_session_id)?;
+            let Some(last) = reviews.last() else {
+                let sql = "delete from pending_sessions where pending_session_id = ?;";
+                tx.execute(sql, params![pending_session_id])?;
+                continue;
+            };
+            let ended_at = last.reviewed_at;
+            let performance = performance_after(&tx, &reviews)?;
+            tx_finish_pending_session(&tx, pending_session_id, ended_at)?;
+            for (card_hash, performance) in performance {
+                set_card_performance(&tx, card_hash, Performance::Reviewed(performance))?;
+            }
+            count += reviews.len();
+        }
+        tx.commit()?;
+        Ok(count)
+    }
+
     /// Delete a card and its reviews.
     ///
     /// If no card with the given hash exists, returns an error.
@@ -270,8 +395,150 @@ impl Database {
         Ok(())
     }
 
+    /// The hash version used by this collection. Databases that predate hash
+    /// versions use [`HashVersion::Raw`].
+    pub fn hash_version(&self) -> Fallible<HashVersion> {
+        let sql = "select value from metadata where key = ?;";
+        let mut stmt = self.conn.prepare(sql)?;
+        let mut rows = stmt.query(params![HASH_VERSION_KEY])?;
+        match rows.next()? {
+            Some(row) => {
+                let value: String = row.get(0)?;
+                HashVersion::try_from(value)
+            }
+            None => Ok(HashVersion::Raw),
+        }
+    }
+
+    /// Re-key cards from their old hash to their new hash, and record the new
+    /// hash version. This runs in a single transaction.
+    ///
+    /// Reviews follow their card through the `on update cascade` foreign key.
+    /// If several cards map to the same new hash, the first one keeps its
+    /// performance, and the reviews of the others are merged into it.
+    ///
+    /// Returns the number of cards whose hash changed.
+    pub fn rehash_cards(
+        &mut self,
+        mapping: &[(CardHash, CardHash)],
+        version: HashVersion,
+    ) -> Fallible<usize> {
+        let tx = self.conn.transaction()?;
+        let mut count = 0;
+        for (old_hash, new_hash) in mapping {
+            if old_hash == new_hash || !tx_card_exists(&tx, *old_hash)? {
+                continue;
+            }
+            if tx_card_exists(&tx, *new_hash)? {
+                let sql = "update reviews set card_hash = ? where card_hash = ?;";
+                tx.execute(sql, params![new_hash, old_hash])?;
+                let sql = "delete from cards where card_hash = ?;";
+                tx.execute(sql, params![old_hash])?;
+            } else {
+                let sql = "update cards set card_hash = ? where card_hash = ?;";
+                tx.execute(sql, params![new_hash, old_hash])?;
+            }
+            count += 1;
+        }
+        let sql = "insert into metadata (key, value) values (?, ?) on conflict (key) do update set value = excluded.value;";
+        tx.execute(sql, params![HASH_VERSION_KEY, version.as_str()])?;
+        tx.commit()?;
+        Ok(count)
+    }
+
+    /// Suspend a card, so it is not drilled until it is unsuspended.
+    ///
+    /// If no card with the given hash exists, returns an error.
+    pub fn suspend_card(&self, card_hash: CardHash, suspended_at: Timestamp) -> Fallible<()> {
+        if !self.card_exists(card_hash)? {
+            return fail("Card not found");
+        }
+        let sql = "insert into suspended_cards (card_hash, suspended_at) values (?, ?) on conflict (card_hash) do nothing;";
+        self.conn.execute(sql, params![card_hash, suspended_at])?;
+        Ok(())
+    }
+
+    /// Unsuspend a card. Unsuspending a card that is not suspended does
+    /// nothing.
+    pub fn unsuspend_card(&self, card_hash: CardHash) -> Fallible<()> {
+        let sql = "delete from suspended_cards where card_hash = ?;";
+        self.conn.execute(sql, params![card_hash])?;
+        Ok(())
+    }
+
+    /// Is the card with the given hash suspended?
+    pub fn is_suspended(&self, card_hash: CardHash) -> Fallible<bool> {
+        let sql = "select count(*) from suspended_cards where card_hash = ?;";
+        let count: i64 = self.conn.query_row(sql, [card_hash], |row| row.get(0))?;
+        Ok(count > 0)
+    }
+
+    /// All the entries in the parse cache, keyed by file path.
+    pub fn parse_cache(&self) -> Fallible<HashMap<String, ParseCacheEntry>> {
+        let sql =
+            "select file_path, content_hash, hash_version, parser_version, cards from parse_cache;";
+        let mut stmt = self.conn.prepare(sql)?;
+        let mut rows = stmt.query([])?;
+        let mut entries = HashMap::new();
+        while let Some(row) = rows.next()? {
+            let hash_version: String = row.get(2)?;
+            let entry = ParseCacheEntry {
+                file_path: row.get(0)?,
+                content_hash: row.get(1)?,
+                hash_version: HashVersion::try_from(hash_version)?,
+                parser_version: row.get(3)?,
+                cards: row.get(4)?,
+            };
+            entries.insert(entry.file_path.clone(), entry);
+        }
+        Ok(entries)
+    }
+
+    /// Store the given entries in the parse cache, replacing any existing
+    /// entries for the same files, and drop the entries for files not in
+    /// `keep`. This runs in a single transaction.
+    pub fn update_parse_cache(
+        &mut self,
+        entries: &[ParseCacheEntry],
+        keep: &HashSet<String>,
+    ) -> Fallible<()> {
+        let tx = self.conn.transaction()?;
+        {
+            let mut stmt = tx.prepare("select file_path from parse_cache;")?;
+            let stale: Vec<String> = stmt
+                .query_map([], |row| row.get(0))?
+                .collect::<Result<_, _>>()?;
+            for file_path in stale {
+                if !keep.contains(&file_path) {
+                    tx.execute(
+                        "delete from parse_cache where file_path = ?;",
+                        params![file_path],
+                    )?;
+                }
+            }
+            let sql = "insert into parse_cache (file_path, content_hash, hash_version, parser_version, cards) values (?, ?, ?, ?, ?)
+                on conflict (file_path) do update set
+                    content_hash = excluded.content_hash,
+                    hash_version = excluded.hash_version,
+                    parser_version = excluded.parser_version,
+                    cards = excluded.cards;";
+            let mut stmt = tx.prepare(sql)?;
+            for entry in entries {
+                stmt.execute(params![
+                    entry.file_path,
+                    entry.content_hash,
+                    entry.hash_version.as_str(),
+                    entry.parser_version,
+                    entry.cards
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
     /// Does a card with the given hash exist?
-    fn card_exists(&self, card_hash: CardHash) -> Fallible<bool> {
+    pub fn card_exists(&self, card_hash: CardHash) -> Fallible<bool> {
         let sql = "select count(*) from cards where card_hash = ?;";
         let count: i64 = self.conn.query_row(sql, [card_hash], |row| row.get(0))?;
         Ok(count > 0)
@@ -304,41 +571,337 @@ impl Database {
 
     /// Get the list of all reviews for a given session.
     pub fn get_reviews_for_session(&self, session_id: i64) -> Fallible<Vec<ReviewRow>> {
-        let sql = "select review_id, card_hash, reviewed_at, grade, stability, difficulty, interval_raw, interval_days, due_date from reviews where session_id = ? order by reviewed_at;";
-        let mut stmt = self.conn.prepare(sql)?;
-        let review_iter = stmt.query_map(params![session_id], |row| {
-            Ok(ReviewRow {
-                review_id: row.get(0)?,
-                data: ReviewRecord {
-                    card_hash: row.get(1)?,
-                    reviewed_at: row.get(2)?,
-                    grade: row.get(3)?,
-                    stability: row.get(4)?,
-                    difficulty: row.get(5)?,
-                    interval_raw: row.get(6)?,
-                    interval_days: row.get(7)?,
-                    due_date: row.get(8)?,
-                },
-            })
-        })?;
+        let sql = format!(
+            "select {REVIEW_COLUMNS} from reviews where session_id = ? order by reviewed_at;"
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let review_iter = stmt.query_map(params![session_id], review_from_row)?;
         let mut reviews = Vec::new();
         for review in review_iter {
             reviews.push(review?);
         }
         Ok(reviews)
     }
+
+    /// Get the list of all reviews, oldest first.
+    pub fn get_all_reviews(&self) -> Fallible<Vec<ReviewRow>> {
+        let sql = format!("select {REVIEW_COLUMNS} from reviews order by reviewed_at, review_id;");
+        let mut stmt = self.conn.prepare(&sql)?;
+        let review_iter = stmt.query_map([], review_from_row)?;
+        let mut reviews = Vec::new();
+        for review in review_iter {
+            reviews.push(review?);
+        }
+        Ok(reviews)
+    }
+
+    /// Get the list of all reviews of a given card, oldest first.
+    pub fn get_reviews_for_card(&self, card_hash: CardHash) -> Fallible<Vec<ReviewRow>> {
+        let sql = format!(
+            "select {REVIEW_COLUMNS} from reviews where card_hash = ? order by reviewed_at, review_id;"
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let review_iter = stmt.query_map(params![card_hash], review_from_row)?;
+        let mut reviews = Vec::new();
+        for review in review_iter {
+            reviews.push(review?);
+        }
+        Ok(reviews)
+    }
+
+    /// The performance of every card in the database.
+    pub fn all_card_performance(&self) -> Fallible<HashMap<CardHash, Performance>> {
+        let sql = format!("select card_hash, {PERFORMANCE_COLUMNS} from cards;");
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map([], |row| {
+            let card_hash: CardHash = row.get(0)?;
+            Ok((card_hash, performance_from_row(row, 1)?))
+        })?;
+        let mut performance = HashMap::new();
+        for row in rows {
+            let (card_hash, p) = row?;
+            performance.insert(card_hash, p);
+        }
+        Ok(performance)
+    }
+
+    /// Return the set of the hashes of all suspended cards.
+    pub fn suspended_card_hashes(&self) -> Fallible<HashSet<CardHash>> {
+        let sql = "select card_hash from suspended_cards;";
+        let mut stmt = self.conn.prepare(sql)?;
+        let rows = stmt.query_map([], |row| row.get(0))?;
+        let mut card_hashes = HashSet::new();
+        for card_hash in rows {
+            card_hashes.insert(card_hash?);
+        }
+        Ok(card_hashes)
+    }
+}
+
+/// Read a review from a row with the `REVIEW_COLUMNS`.
+fn review_from_row(row: &Row) -> rusqlite::Result<ReviewRow> {
+    Ok(ReviewRow {
+        review_id: row.get(0)?,
+        data: ReviewRecord {
+            card_hash: row.get(1)?,
+            reviewed_at: row.get(2)?,
+            grade: row.get(3)?,
+            stability: row.get(4)?,
+            difficulty: row.get(5)?,
+            interval_raw: row.get(6)?,
+            interval_days: row.get(7)?,
+            due_date: row.get(8)?,
+            duration_ms: row.get(9)?,
+        },
+    })
+}
+
+/// Write a card's performance, on a connection or inside a transaction.
+fn set_card_performance(
+    conn: &Connection,
+    card_hash: CardHash,
+    performance: Performance,
+) -> Fallible<()> {
+    let (
+        last_reviewed_at,
+        stability,
+        difficulty,
+        interval_raw,
+        interval_days,
+        due_date,
+        review_count,
+    ) = match performance {
+        Performance::New => (None, None, None, None, None, None, 0),
+        Performance::Reviewed(rp) => (
+            Some(rp.last_reviewed_at),
+            Some(rp.stability),
+            Some(rp.difficulty),
+            Some(rp.interval_raw),
+            Some(rp.interval_days as i32),
+            Some(rp.due_date),
+            rp.review_count as i32,
+        ),
+    };
+    let sql = "update cards set last_reviewed_at = ?, stability = ?, difficulty = ?, interval_raw = ?, interval_days = ?, due_date = ?, review_count = ? where card_hash = ?;";
+    let params = params![
+        last_reviewed_at,
+        stability,
+        difficulty,
+        interval_raw,
+        interval_days,
+        due_date,
+        review_count,
+        card_hash
+    ];
+    conn.execute(sql, params)?;
+    Ok(())
+}
+
+/// Read a card's performance from a row with the `PERFORMANCE_COLUMNS`,
+/// starting at the given column.
+fn performance_from_row(row: &Row, offset: usize) -> rusqlite::Result<Performance> {
+    let last_reviewed_at: Option<Timestamp> = row.get(offset)?;
+    let stability: Option<Stability> = row.get(offset + 1)?;
+    let difficulty: Option<Difficulty> = row.get(offset + 2)?;
+    let interval_raw: Option<f64> = row.get(offset + 3)?;
+    let interval_days: Option<i64> = row.get(offset + 4)?;
+    let due_date: Option<Date> = row.get(offset + 5)?;
+    let review_count: i32 = row.get(offset + 6)?;
+    if let (
+        Some(last_reviewed_at),
+        Some(stability),
+        Some(difficulty),
+        Some(interval_raw),
+        Some(interval_days),
+        Some(due_date),
+    ) = (
+        last_reviewed_at,
+        stability,
+        difficulty,
+        interval_raw,
+        interval_days,
+        due_date,
+    ) {
+        Ok(Performance::Reviewed(ReviewedPerformance {
+            last_reviewed_at,
+            stability,
+            difficulty,
+            interval_raw,
+            interval_days,
+            due_date,
+            review_count: review_count as usize,
+        }))
+    } else {
+        Ok(Performance::New)
+    }
+}
+
+/// The reviews journaled in a pending session, in order.
+fn pending_reviews(conn: &Connection, pending_session_id: i64) -> Fallible<Vec<ReviewRecord>> {
+    let sql = "select card_hash, reviewed_at, grade, stability, difficulty, interval_raw, interval_days, due_date, duration_ms from pending_reviews where pending_session_id = ? order by pending_review_id;";
+    let mut stmt = conn.prepare(sql)?;
+    let rows = stmt.query_map(params![pending_session_id], |row| {
+        Ok(ReviewRecord {
+            card_hash: row.get(0)?,
+            reviewed_at: row.get(1)?,
+            grade: row.get(2)?,
+            stability: row.get(3)?,
+            difficulty: row.get(4)?,
+            interval_raw: row.get(5)?,
+            interval_days: row.get(6)?,
+            due_date: row.get(7)?,
+            duration_ms: row.get(8)?,
+        })
+    })?;
+    let mut reviews = Vec::new();
+    for row in rows {
+        reviews.push(row?);
+    }
+    Ok(reviews)
+}
+
+/// The performance of each card after its last review in the given reviews,
+/// which have not been recorded yet.
+fn performance_after(
+    conn: &Connection,
+    reviews: &[ReviewRecord],
+) -> Fallible<HashMap<CardHash, ReviewedPerformance>> {
+    let mut performance: HashMap<CardHash, ReviewedPerformance> = HashMap::new();
+    for review in reviews {
+        let review_count = match performance.get(&review.card_hash) {
+            Some(previous) => previous.review_count + 1,
+            None => {
+                let sql = "select review_count from cards where card_hash = ?;";
+                let previous: i64 =
+                    conn.query_row(sql, params![review.card_hash], |row| row.get(0))?;
+                previous as usize + 1
+            }
+        };
+        performance.insert(
+            review.card_hash,
+            ReviewedPerformance {
+                last_reviewed_at: review.reviewed_at,
+                stability: review.stability,
+                difficulty: review.difficulty,
+                interval_raw: review.interval_raw,
+                interval_days: review.interval_days,
+                due_date: review.due_date,
+                review_count,
+            },
+        );
+    }
+    Ok(performance)
+}
+
+fn tx_set_pending_queue(
+    tx: &Transaction,
+    pending_session_id: i64,
+    queue: &[CardHash],
+) -> Fallible<()> {
+    let sql = "update pending_sessions set queue = ? where pending_session_id = ?;";
+    tx.execute(sql, params![encode_queue(queue)?, pending_session_id])?;
+    Ok(())
+}
+
+/// Queues are stored as JSON arrays of card hashes.
+fn encode_queue(queue: &[CardHash]) -> Fallible<String> {
+    Ok(serde_json::to_string(queue)?)
+}
+
+fn decode_queue(queue: &str) -> Fallible<Vec<CardHash>> {
+    let hashes: Vec<String> = serde_json::from_str(queue)?;
+    hashes.iter().map(|hash| CardHash::from_hex(hash)).collect()
+}
+
+/// Move a pending session and its reviews to the `sessions` and `reviews`
+/// tables.
+fn tx_finish_pending_session(
+    tx: &Transaction,
+    pending_session_id: i64,
+    ended_at: Timestamp,
+) -> Fallible<()> {
+    let sql = "insert into sessions (started_at, ended_at) select started_at, ? from pending_sessions where pending_session_id = ? returning session_id;";
+    let session_id: i64 =
+        tx.query_row(sql, params![ended_at, pending_session_id], |row| row.get(0))?;
+    let sql = "insert into reviews (session_id, card_hash, reviewed_at, grade, stability, difficulty, interval_raw, interval_days, due_date, duration_ms)
+        select ?, card_hash, reviewed_at, grade, stability, difficulty, interval_raw, interval_days, due_date, duration_ms
+        from pending_reviews where pending_session_id = ? order by pending_review_id;";
+    tx.execute(sql, params![session_id, pending_session_id])?;
+    let sql = "delete from pending_sessions where pending_session_id = ?;";
+    tx.execute(sql, params![pending_session_id])?;
+    Ok(())
+}
+
+fn tx_card_exists(tx: &Transaction, card_hash: CardHash) -> Fallible<bool> {
+    let sql = "select count(*) from cards where card_hash = ?;";
+    let count: i64 = tx.query_row(sql, [card_hash], |row| row.get(0))?;
+    Ok(count > 0)
 }
 
-fn probe_schema_exists(tx: &Transaction) -> Fallible<bool> {
+fn probe_schema_exists(conn: &Connection) -> Fallible<bool> {
     let sql = "select count(*) from sqlite_master where type='table' AND name=?;";
-    let count: i64 = tx.query_row(sql, ["cards"], |row| row.get(0))?;
+    let count: i64 = conn.query_row(sql, ["cards"], |row| row.get(0))?;
     Ok(count > 0)
 }
 
+/// The number of migrations applied to the database.
+fn schema_version(conn: &Connection) -> Fallible<usize> {
+    let version: i64 = conn.query_row("pragma user_version;", [], |row| row.get(0))?;
+    if version == 0 && probe_schema_exists(conn)? {
+        // Databases created before migrations existed have the initial schema,
+        // but no version.
+        return Ok(1);
+    }
+    Ok(version as usize)
+}
+
+/// Bring the database schema up to date, backing up the database first if it
+/// has any data.
+fn migrate(conn: &mut Connection, database_path: &str) -> Fallible<()> {
+    let version = schema_version(conn)?;
+    let latest = MIGRATIONS.len();
+    if version > latest {
+        return fail(format!(
+            "The database has schema version {version}, but this version of hashcards only supports up to version {latest}. Please upgrade hashcards."
+        ));
+    }
+    if version == latest {
+        return Ok(());
+    }
+    if version > 0 && database_path != ":memory:" {
+        backup(conn, &backup_path(database_path, version))?;
+    }
+    let tx = conn.transaction()?;
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        log::debug!("Applying migration {}.", index + 1);
+        tx.execute_batch(migration)?;
+    }
+    tx.pragma_update(None, "user_version", latest as i64)?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Where the database is backed up before migrating from the given version.
+pub fn backup_path(database_path: &str, version: usize) -> String {
+    format!("{database_path}.v{version}.bak")
+}
+
+/// Copy the database to the given path, replacing any file there.
+fn backup(conn: &Connection, path: &str) -> Fallible<()> {
+    if Path::new(path).exists() {
+        remove_file(path)?;
+    }
+    conn.execute("vacuum into ?;", [path])?;
+    log::info!("Backed up the database to {path}.");
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::fsrs::Grade;
+    use crate::helper::create_tmp_directory;
+    use crate::helper::save_session;
     use crate::types::performance::ReviewedPerformance;
 
     #[test]
@@ -349,6 +912,65 @@ mod tests {
         Ok(())
     }
 
+    fn open_fixture(sql: &str) -> Fallible<(String, Connection)> {
+        let path = create_tmp_directory()?.join("hashcards.db");
+        let path = path.display().to_string();
+        let conn = Connection::open(&path)?;
+        conn.execute_batch(sql)?;
+        Ok((path, conn))
+    }
+
+    fn user_version(conn: &Connection) -> Fallible<i64> {
+        Ok(conn.query_row("pragma user_version;", [], |row| row.get(0))?)
+    }
+
+    /// New databases get every migration.
+    #[test]
+    fn test_migrate_new_database() -> Fallible<()> {
+        let db = Database::new(":memory:")?;
+        assert_eq!(user_version(&db.conn)?, MIGRATIONS.len() as i64);
+        Ok(())
+    }
+
+    /// A database created by hashcards 0.3.0 is migrated, keeping its data,
+    /// and backed up first.
+    #[test]
+    fn test_migrate_release_0_3_0() -> Fallible<()> {
+        let (path, conn) = open_fixture(include_str!("../tests/databases/0.3.0.sql"))?;
+        drop(conn);
+        let db = Database::new(&path)?;
+        assert_eq!(user_version(&db.conn)?, MIGRATIONS.len() as i64);
+        assert_eq!(db.card_hashes()?.len(), 2);
+        assert_eq!(db.get_all_sessions()?.len(), 1);
+        assert_eq!(db.get_reviews_for_session(1)?.len(), 1);
+        assert_eq!(db.hash_version()?, HashVersion::Raw);
+        let today = Date::try_from("2026-01-14".to_string())?;
+        assert_eq!(db.due_today(today)?.len(), 2);
+
+        // The backup is the database as it was.
+        let backup = Connection::open(backup_path(&path, 1))?;
+        assert_eq!(user_version(&backup)?, 0);
+        let count: i64 = backup.query_row("select count(*) from cards;", [], |row| row.get(0))?;
+        assert_eq!(count, 2);
+
+        // Opening it again doesn't migrate or back up.
+        drop(db);
+        remove_file(backup_path(&path, 1))?;
+        Database::new(&path)?;
+        assert!(!Path::new(&backup_path(&path, 1)).exists());
+        Ok(())
+    }
+
+    /// Databases from newer versions of hashcards are not opened.
+    #[test]
+    fn test_migrate_newer_database() -> Fallible<()> {
+        let (path, conn) = open_fixture("pragma user_version = 1000;")?;
+        drop(conn);
+        let result = Database::new(&path);
+        assert!(result.is_err());
+        Ok(())
+    }
+
     /// Insert a card, and see that its hash is returned by `card_hashes`, and
     /// that `get_card_performance` returns an initial empty performance, and
     /// `due_today` returns it since it's new.
@@ -363,7 +985,25 @@ mod tests {
         let performance = db.get_card_performance(card_hash)?;
         assert_eq!(performance, Performance::New);
         let due_today = db.due_today(now.date())?;
-        assert!(due_today.contains(&card_hash));
+        assert!(due_today.contains_key(&card_hash));
+        Ok(())
+    }
+
+    /// Suspended cards are not due, and are due again once unsuspended.
+    #[test]
+    fn test_suspend_card() -> Fallible<()> {
+        let db = Database::new(":memory:")?;
+        let card_hash = CardHash::hash_bytes(b"a");
+        let now = Timestamp::now();
+        assert!(db.suspend_card(card_hash, now).is_err());
+        db.insert_card(card_hash, now)?;
+        db.suspend_card(card_hash, now)?;
+        db.suspend_card(card_hash, now)?;
+        assert!(db.is_suspended(card_hash)?);
+        assert!(!db.due_today(now.date())?.contains_key(&card_hash));
+        db.unsuspend_card(card_hash)?;
+        assert!(!db.is_suspended(card_hash)?);
+        assert!(db.due_today(now.date())?.contains_key(&card_hash));
         Ok(())
     }
 
@@ -398,11 +1038,61 @@ mod tests {
             due_date: now.date(),
             review_count: 1,
         });
-        db.update_card_performance(card_hash, performance)?;
+        set_card_performance(&db.conn, card_hash, performance)?;
         let fetched_performance = db.get_card_performance(card_hash)?;
         assert_eq!(fetched_performance, performance);
         let due_today = db.due_today(now.date())?;
-        assert!(due_today.contains(&card_hash));
+        assert!(due_today.contains_key(&card_hash));
+        Ok(())
+    }
+
+    /// `insert_cards` inserts only the cards that are missing.
+    #[test]
+    fn test_insert_cards() -> Fallible<()> {
+        let mut db = Database::new(":memory:")?;
+        let a = CardHash::hash_bytes(b"a");
+        let b = CardHash::hash_bytes(b"b");
+        let now = Timestamp::now();
+        db.insert_card(a, now)?;
+        assert_eq!(db.insert_cards([a, b], now)?, 1);
+        assert_eq!(db.insert_cards([a, b], now)?, 0);
+        assert_eq!(db.card_hashes()?, HashSet::from([a, b]));
+        Ok(())
+    }
+
+    /// `due_today` returns new cards and cards due on or before the date,
+    /// with their performance.
+    #[test]
+    fn test_due_today() -> Fallible<()> {
+        let db = Database::new(":memory:")?;
+        let now = Timestamp::now();
+        let today = now.date();
+        let tomorrow = Date::new(today.into_inner().succ_opt().unwrap());
+        let new = CardHash::hash_bytes(b"new");
+        let due = CardHash::hash_bytes(b"due");
+        let later = CardHash::hash_bytes(b"later");
+        for (card_hash, due_date) in [(due, today), (later, tomorrow)] {
+            db.insert_card(card_hash, now)?;
+            set_card_performance(
+                &db.conn,
+                card_hash,
+                Performance::Reviewed(ReviewedPerformance {
+                    last_reviewed_at: now,
+                    stability: 2.0,
+                    difficulty: 2.0,
+                    interval_raw: 1.0,
+                    interval_days: 1,
+                    due_date,
+                    review_count: 1,
+                }),
+            )?;
+        }
+        db.insert_card(new, now)?;
+        let due_today = db.due_today(today)?;
+        assert_eq!(due_today.len(), 2);
+        assert_eq!(due_today[&new], Performance::New);
+        assert_eq!(due_today[&due], db.get_card_performance(due)?);
+        assert!(db.due_today(tomorrow)?.contains_key(&later));
         Ok(())
     }
 
@@ -421,13 +1111,15 @@ mod tests {
         Ok(())
     }
 
-    /// `update_card_performance` fails if the card does not exist.
+    /// Finishing a session fails if a card whose performance is written does
+    /// not exist.
     #[test]
-    fn test_update_performance_nonexistent() -> Fallible<()> {
-        let db = Database::new(":memory:")?;
+    fn test_finish_pending_session_nonexistent() -> Fallible<()> {
+        let mut db = Database::new(":memory:")?;
         let card_hash = CardHash::hash_bytes(b"a");
         let performance = Performance::New;
-        let result = db.update_card_performance(card_hash, performance);
+        let id = db.start_pending_session(Timestamp::now(), &[], 0)?;
+        let result = db.finish_pending_session(id, Timestamp::now(), &[(card_hash, performance)]);
         assert!(result.is_err());
         let err = result.err().unwrap();
         assert_eq!(err.to_string(), "error: Card not found");
@@ -450,8 +1142,9 @@ mod tests {
             interval_raw: 1.0,
             interval_days: 1,
             due_date: now.date(),
+            duration_ms: None,
         };
-        db.save_session(now, now, vec![review])?;
+        save_session(&mut db, now, now, &[review], &[])?;
 
         let sessions = db.get_all_sessions()?;
         assert_eq!(sessions.len(), 1);
@@ -472,6 +1165,157 @@ mod tests {
         Ok(())
     }
 
+    /// A card's reviews are listed across sessions, oldest first.
+    #[test]
+    fn test_get_reviews_for_card() -> Fallible<()> {
+        let mut db = Database::new(":memory:")?;
+        let a = CardHash::hash_bytes(b"a");
+        let b = CardHash::hash_bytes(b"b");
+        let now = Timestamp::now();
+        let earlier = Timestamp::new(now.into_inner() - chrono::Duration::days(1));
+        db.insert_cards([a, b], earlier)?;
+        save_session(&mut db, now, now, &[review(a, now, Grade::Good)], &[])?;
+        save_session(
+            &mut db,
+            earlier,
+            earlier,
+            &[
+                review(a, earlier, Grade::Forgot),
+                review(b, earlier, Grade::Easy),
+            ],
+            &[],
+        )?;
+        let reviews = db.get_reviews_for_card(a)?;
+        let grades: Vec<Grade> = reviews.iter().map(|r| r.data.grade).collect();
+        assert_eq!(grades, vec![Grade::Forgot, Grade::Good]);
+        assert!(reviews.iter().all(|r| r.data.card_hash == a));
+        assert_eq!(
+            db.get_reviews_for_card(CardHash::hash_bytes(b"c"))?.len(),
+            0
+        );
+        Ok(())
+    }
+
+    /// `all_card_performance` and `suspended_card_hashes` cover every card.
+    #[test]
+    fn test_all_card_performance() -> Fallible<()> {
+        let mut db = Database::new(":memory:")?;
+        let a = CardHash::hash_bytes(b"a");
+        let b = CardHash::hash_bytes(b"b");
+        let now = Timestamp::now();
+        db.insert_cards([a, b], now)?;
+        let performance = Performance::Reviewed(ReviewedPerformance {
+            last_reviewed_at: now,
+            stability: 2.0,
+            difficulty: 2.0,
+            interval_raw: 1.0,
+            interval_days: 1,
+            due_date: now.date(),
+            review_count: 1,
+        });
+        set_card_performance(&db.conn, a, performance)?;
+        db.suspend_card(b, now)?;
+        let all = db.all_card_performance()?;
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[&a], performance);
+        assert_eq!(all[&b], Performance::New);
+        assert_eq!(db.suspended_card_hashes()?, HashSet::from([b]));
+        Ok(())
+    }
+
+    fn review(card_hash: CardHash, reviewed_at: Timestamp, grade: Grade) -> ReviewRecord {
+        ReviewRecord {
+            card_hash,
+            reviewed_at,
+            grade,
+            stability: 2.0,
+            difficulty: 2.0,
+            interval_raw: 1.0,
+            interval_days: 1,
+            due_date: reviewed_at.date(),
+            duration_ms: None,
+        }
+    }
+
+    /// Reviews are journaled, undone, and moved to the session when it is
+    /// finished.
+    #[test]
+    fn test_finish_pending_session() -> Fallible<()> {
+        let mut db = Database::new(":memory:")?;
+        let a = CardHash::hash_bytes(b"a");
+        let b = CardHash::hash_bytes(b"b");
+        let now = Timestamp::now();
+        db.insert_cards([a, b], now)?;
+        let id = db.start_pending_session(now, &[a, b], 2)?;
+        db.add_pending_review(id, &review(a, now, Grade::Good), &[b])?;
+        db.add_pending_review(id, &review(b, now, Grade::Forgot), &[b])?;
+        db.remove_last_pending_review(id, &[b])?;
+        let pending = db.pending_sessions()?;
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].queue, vec![b]);
+        assert_eq!(pending[0].total_cards, 2);
+        assert_eq!(pending[0].reviews.len(), 1);
+
+        let performance = Performance::Reviewed(ReviewedPerformance {
+            last_reviewed_at: now,
+            stability: 2.0,
+            difficulty: 2.0,
+            interval_raw: 1.0,
+            interval_days: 1,
+            due_date: now.date(),
+            review_count: 1,
+        });
+        db.finish_pending_session(id, now, &[(a, performance), (b, Performance::New)])?;
+        let sessions = db.get_all_sessions()?;
+        assert_eq!(sessions.len(), 1);
+        let reviews = db.get_reviews_for_session(sessions[0].session_id)?;
+        assert_eq!(reviews.len(), 1);
+        assert_eq!(reviews[0].data.card_hash, a);
+        assert_eq!(db.get_card_performance(a)?, performance);
+        assert!(db.pending_sessions()?.is_empty());
+        assert_eq!(db.recover_pending_sessions(None)?, 0);
+        Ok(())
+    }
+
+    /// The reviews of an interrupted session are recorded, and each card gets
+    /// the performance from its last review.
+    #[test]
+    fn test_recover_pending_sessions() -> Fallible<()> {
+        let mut db = Database::new(":memory:")?;
+        let a = CardHash::hash_bytes(b"a");
+        let now = Timestamp::now();
+        db.insert_card(a, now)?;
+        let id = db.start_pending_session(now, &[a], 1)?;
+        db.add_pending_review(id, &review(a, now, Grade::Forgot), &[a])?;
+        let last = ReviewRecord {
+            stability: 5.0,
+            ..review(a, now, Grade::Good)
+        };
+        db.add_pending_review(id, &last, &[])?;
+        // A session with no reviews is dropped.
+        db.start_pending_session(now, &[], 0)?;
+
+        // A session that is kept is not recovered.
+        let kept = db.start_pending_session(now, &[a], 1)?;
+        db.add_pending_review(kept, &review(a, now, Grade::Good), &[])?;
+
+        assert_eq!(db.recover_pending_sessions(Some(kept))?, 2);
+        let sessions = db.get_all_sessions()?;
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(db.get_reviews_for_session(sessions[0].session_id)?.len(), 2);
+        match db.get_card_performance(a)? {
+            Performance::Reviewed(performance) => {
+                assert_eq!(performance.stability, 5.0);
+                assert_eq!(performance.review_count, 2);
+            }
+            Performance::New => panic!("card should have been reviewed"),
+        }
+        assert_eq!(db.pending_sessions()?.len(), 1);
+        assert_eq!(db.pending_performance(kept)?[&a].review_count, 3);
+        assert_eq!(db.recover_pending_sessions(None)?, 1);
+        Ok(())
+    }
+
     /// Trying to delete a non-existent card returns an error.
     #[test]
     fn test_delete_nonexistent_card() -> Fallible<()> {
@@ -501,4 +1345,77 @@ mod tests {
         );
         Ok(())
     }
+
+    /// New databases use raw hashes.
+    #[test]
+    fn test_default_hash_version() -> Fallible<()> {
+        let db = Database::new(":memory:")?;
+        assert_eq!(db.hash_version()?, HashVersion::Raw);
+        Ok(())
+    }
+
+    /// Re-keying a card moves its performance and reviews to the new hash,
+    /// and records the hash version.
+    #[test]
+    fn test_rehash_cards() -> Fallible<()> {
+        let mut db = Database::new(":memory:")?;
+        let old_hash = CardHash::hash_bytes(b"old");
+        let new_hash = CardHash::hash_bytes(b"new");
+        let now = Timestamp::now();
+        db.insert_card(old_hash, now)?;
+        let review = ReviewRecord {
+            card_hash: old_hash,
+            reviewed_at: now,
+            grade: Grade::Good,
+            stability: 2.0,
+            difficulty: 2.0,
+            interval_raw: 1.0,
+            interval_days: 1,
+            due_date: now.date(),
+            duration_ms: None,
+        };
+        save_session(&mut db, now, now, &[review], &[])?;
+        let count = db.rehash_cards(&[(old_hash, new_hash)], HashVersion::Normalized)?;
+        assert_eq!(count, 1);
+        assert_eq!(db.hash_version()?, HashVersion::Normalized);
+        let hashes = db.card_hashes()?;
+        assert!(hashes.contains(&new_hash));
+        assert!(!hashes.contains(&old_hash));
+        let sessions = db.get_all_sessions()?;
+        let reviews = db.get_reviews_for_session(sessions[0].session_id)?;
+        assert_eq!(reviews[0].data.card_hash, new_hash);
+        Ok(())
+    }
+
+    /// Cards that collide under the new hash are merged.
+    #[test]
+    fn test_rehash_cards_merges_collisions() -> Fallible<()> {
+        let mut db = Database::new(":memory:")?;
+        let a = CardHash::hash_bytes(b"a");
+        let b = CardHash::hash_bytes(b"b");
+        let merged = CardHash::hash_bytes(b"merged");
+        let now = Timestamp::now();
+        db.insert_card(a, now)?;
+        db.insert_card(b, now)?;
+        let review = |card_hash| ReviewRecord {
+            card_hash,
+            reviewed_at: now,
+            grade: Grade::Good,
+            stability: 2.0,
+            difficulty: 2.0,
+            interval_raw: 1.0,
+            interval_days: 1,
+            due_date: now.date(),
+            duration_ms: None,
+        };
+        save_session(&mut db, now, now, &[review(a), review(b)], &[])?;
+        let count = db.rehash_cards(&[(a, merged), (b, merged)], HashVersion::Normalized)?;
+        assert_eq!(count, 2);
+        assert_eq!(db.card_hashes()?, HashSet::from([merged]));
+        let sessions = db.get_all_sessions()?;
+        let reviews = db.get_reviews_for_session(sessions[0].session_id)?;
+        assert_eq!(reviews.len(), 2);
+        assert!(reviews.iter().all(|r| r.data.card_hash == merged));
+        Ok(())
+    }
 }
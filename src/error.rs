@@ -57,6 +57,14 @@ impl From<walkdir::Error> for ErrorReport {
     }
 }
 
+impl From<notify::Error> for ErrorReport {
+    fn from(value: notify::Error) -> Self {
+        ErrorReport {
+            message: format!("file watcher error: {value}"),
+        }
+    }
+}
+
 impl From<rusqlite::Error> for ErrorReport {
     fn from(value: rusqlite::Error) -> Self {
         ErrorReport {
@@ -82,6 +90,14 @@ impl From<FromUtf8Error> for ErrorReport {
     }
 }
 
+impl From<lsp_server::ProtocolError> for ErrorReport {
+    fn from(value: lsp_server::ProtocolError) -> Self {
+        ErrorReport {
+            message: format!("language server protocol error: {value}"),
+        }
+    }
+}
+
 impl From<serde_json::Error> for ErrorReport {
     fn from(value: serde_json::Error) -> Self {
         ErrorReport {
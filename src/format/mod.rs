@@ -0,0 +1,120 @@
+// Copyright 2025 Fernando Borretti
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Deck file formats. Each format knows how to read the cards in a file, how
+//! to render card text to HTML, and how to find the media a text refers to.
+
+pub mod org;
+
+use std::path::Path;
+
+use crate::error::Fallible;
+use crate::markdown::MarkdownRenderConfig;
+use crate::markdown::markdown_to_html;
+use crate::markdown::markdown_to_html_inline;
+use crate::markdown::normalize_markdown;
+use crate::media::validate::extract_media_paths;
+use crate::parser::ParserError;
+use crate::parser::check_markdown_deck;
+use crate::parser::lint_allow_directive;
+use crate::types::card::Card;
+use crate::types::card_hash::HashVersion;
+
+/// The format of a deck file, determined by its extension.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DeckFormat {
+    #[default]
+    Markdown,
+    Org,
+}
+
+impl DeckFormat {
+    /// The format of the file at the given path, or `None` if the file is not
+    /// a deck.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?;
+        match ext {
+            "md" => Some(Self::Markdown),
+            "org" => Some(Self::Org),
+            _ => None,
+        }
+    }
+
+    /// Parse the cards in a deck file, failing on the first error.
+    pub fn parse_file(
+        self,
+        path: &Path,
+        text: &str,
+        hash_version: HashVersion,
+    ) -> Fallible<Vec<Card>> {
+        let (cards, errors) = self.check_file(path, text, hash_version);
+        match errors.into_iter().next() {
+            Some(error) => Err(error.into()),
+            None => Ok(cards),
+        }
+    }
+
+    /// Parse the cards in a deck file, collecting every error.
+    pub fn check_file(
+        self,
+        path: &Path,
+        text: &str,
+        hash_version: HashVersion,
+    ) -> (Vec<Card>, Vec<ParserError>) {
+        match self {
+            Self::Markdown => check_markdown_deck(path, text, hash_version),
+            Self::Org => org::check_org_deck(path, text, hash_version),
+        }
+    }
+
+    /// Render card text to HTML.
+    pub fn to_html(self, config: &MarkdownRenderConfig, text: &str) -> Fallible<String> {
+        match self {
+            Self::Markdown => markdown_to_html(config, text),
+            Self::Org => org::org_to_html(config, text),
+        }
+    }
+
+    /// Render a fragment of card text to HTML, without an enclosing paragraph.
+    pub fn to_html_inline(self, config: &MarkdownRenderConfig, text: &str) -> Fallible<String> {
+        match self {
+            Self::Markdown => markdown_to_html_inline(config, text),
+            Self::Org => org::org_to_html_inline(config, text),
+        }
+    }
+
+    /// Reduce card text to a canonical form, for normalized hashes.
+    pub fn normalize(self, text: &str) -> String {
+        match self {
+            Self::Markdown => normalize_markdown(text),
+            Self::Org => org::normalize_org(text),
+        }
+    }
+
+    /// The paths of the media files the text refers to.
+    pub fn media_paths(self, text: &str) -> Vec<String> {
+        match self {
+            Self::Markdown => extract_media_paths(text),
+            Self::Org => org::extract_media_paths(text),
+        }
+    }
+
+    /// If the line suppresses lint rules, return the comma-separated rules.
+    pub fn lint_allow(self, line: &str) -> Option<&str> {
+        match self {
+            Self::Markdown => lint_allow_directive(line),
+            Self::Org => org::lint_allow_property(line),
+        }
+    }
+}
@@ -0,0 +1,980 @@
+// Copyright 2025 Fernando Borretti
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Org-mode decks.
+//!
+//! Cards are written either as headlines tagged `:card:`, where the headline
+//! is the question and the body is the answer, or with drawers:
+//!
+//! ```org
+//! :QUESTION:
+//! What is the order of a group?
+//! :END:
+//! :ANSWER:
+//! The cardinality of its underlying set.
+//! :END:
+//!
+//! :CLOZE:
+//! The {{order}} of a group is {{the cardinality of its underlying set}}.
+//! :END:
+//! :NOTE:
+//! An optional note, shown below the answer.
+//! :END:
+//! ```
+
+use std::path::Path;
+use std::path::PathBuf;
+
+use unicode_normalization::UnicodeNormalization;
+
+use crate::error::Fallible;
+use crate::markdown::IMAGE_EXTENSIONS;
+use crate::markdown::MarkdownRenderConfig;
+use crate::markdown::is_audio_file;
+use crate::markdown::modify_url;
+use crate::parser::ParserError;
+use crate::types::aliases::DeckName;
+use crate::types::card::Card;
+use crate::types::card::CardContent;
+use crate::types::card_hash::HashVersion;
+
+/// The tag that turns a headline into a card.
+const CARD_TAG: &str = "card";
+
+const LINT_ALLOW_PROPERTY: &str = ":LINT_ALLOW:";
+
+/// Parses the cards in an Org-mode deck file, collecting every error rather
+/// than stopping at the first one. The deck name is the file name, unless the
+/// file has a `#+DECK:` keyword.
+pub fn check_org_deck(
+    path: &Path,
+    text: &str,
+    hash_version: HashVersion,
+) -> (Vec<Card>, Vec<ParserError>) {
+    let deck_name: DeckName = text
+        .lines()
+        .find_map(|line| keyword(line, "DECK"))
+        .map(|name| name.to_string())
+        .unwrap_or_else(|| {
+            path.file_stem()
+                .and_then(|os_str| os_str.to_str())
+                .unwrap_or("None")
+                .to_string()
+        });
+    let reader = OrgReader {
+        deck_name,
+        file_path: path.to_path_buf(),
+        hash_version,
+    };
+    reader.parse_all(text)
+}
+
+struct OrgReader {
+    deck_name: DeckName,
+    file_path: PathBuf,
+    hash_version: HashVersion,
+}
+
+/// A drawer: a block of lines between `:NAME:` and `:END:`.
+struct Drawer {
+    /// The name of the drawer, in upper case.
+    name: String,
+    /// The lines between the delimiters, joined.
+    content: String,
+    /// The line of the `:END:` delimiter.
+    end_line: usize,
+}
+
+impl OrgReader {
+    /// Parse all the cards in the text. Parsing carries on after an error, so
+    /// every error in the text is returned, along with the cards that could be
+    /// read.
+    fn parse_all(&self, text: &str) -> (Vec<Card>, Vec<ParserError>) {
+        let lines: Vec<&str> = text.lines().collect();
+        let mut cards = Vec::new();
+        let mut errors = Vec::new();
+        // A question drawer waiting for its answer drawer.
+        let mut question: Option<(String, usize)> = None;
+        let mut i = 0;
+        while i < lines.len() {
+            if let Some((_, title)) = headline(lines[i]) {
+                if let Some((_, start_line)) = question.take() {
+                    errors.push(self.error(
+                        "Found a :QUESTION: drawer without an :ANSWER: drawer.",
+                        start_line,
+                    ));
+                }
+                let (title, tags) = split_tags(title);
+                if tags.contains(&CARD_TAG) {
+                    let end = (i + 1..lines.len())
+                        .find(|&j| headline(lines[j]).is_some())
+                        .unwrap_or(lines.len());
+                    match self.headline_card(title, &lines, i, end) {
+                        Ok(card) => cards.push(card),
+                        Err(error) => errors.push(error),
+                    }
+                    i = end;
+                } else {
+                    i += 1;
+                }
+            } else if drawer_name(lines[i]).is_some() {
+                match self.read_card_drawer(&lines, i, &mut question, &mut cards) {
+                    Ok(next) => i = next,
+                    Err(error) => {
+                        errors.push(error);
+                        question = None;
+                        i += 1;
+                    }
+                }
+            } else {
+                i += 1;
+            }
+        }
+        if let Some((_, start_line)) = question {
+            errors.push(self.error(
+                "Found a :QUESTION: drawer without an :ANSWER: drawer.",
+                start_line,
+            ));
+        }
+        (cards, errors)
+    }
+
+    /// Read the drawer that starts at the given line, and the note after it,
+    /// if it completes a card. Returns the line after the drawer.
+    fn read_card_drawer(
+        &self,
+        lines: &[&str],
+        start_line: usize,
+        question: &mut Option<(String, usize)>,
+        cards: &mut Vec<Card>,
+    ) -> Result<usize, ParserError> {
+        let drawer = self.read_drawer(lines, start_line)?;
+        let next = drawer.end_line + 1;
+        match drawer.name.as_str() {
+            "QUESTION" => {
+                if question.is_some() {
+                    return Err(self.error(
+                        "Found a :QUESTION: drawer while reading a question.",
+                        start_line,
+                    ));
+                }
+                *question = Some((drawer.content, start_line));
+                Ok(next)
+            }
+            "ANSWER" => {
+                let Some((question_text, question_line)) = question.take() else {
+                    return Err(self.error(
+                        "Found an :ANSWER: drawer without a :QUESTION: drawer.",
+                        start_line,
+                    ));
+                };
+                let (note, next) = self.read_note(lines, next)?;
+                cards.push(Card::new(
+                    self.deck_name.clone(),
+                    self.file_path.clone(),
+                    (question_line, next - 1),
+                    CardContent::new_basic(question_text, drawer.content),
+                    note,
+                    self.hash_version,
+                ));
+                Ok(next)
+            }
+            "CLOZE" => {
+                if question.is_some() {
+                    return Err(self.error(
+                        "Found a :CLOZE: drawer while reading a question.",
+                        start_line,
+                    ));
+                }
+                let (note, next) = self.read_note(lines, next)?;
+                cards.extend(self.cloze_cards(&drawer.content, note, start_line, next - 1)?);
+                Ok(next)
+            }
+            "NOTE" => Err(self.error("Found a :NOTE: drawer without a card.", start_line)),
+            // Other drawers, like `:PROPERTIES:`, are skipped.
+            _ => Ok(next),
+        }
+    }
+
+    /// Read a card from a tagged headline. The body, up to the next headline,
+    /// is the answer. A `:NOTE:` drawer in the body is the note, and other
+    /// drawers and planning lines are skipped.
+    fn headline_card(
+        &self,
+        title: &str,
+        lines: &[&str],
+        start: usize,
+        end: usize,
+    ) -> Result<Card, ParserError> {
+        let mut answer: Vec<&str> = Vec::new();
+        let mut note = None;
+        let mut i = start + 1;
+        while i < end {
+            if drawer_name(lines[i]).is_some() {
+                let drawer = self.read_drawer(&lines[..end], i)?;
+                if drawer.name == "NOTE" {
+                    note = Some(drawer.content);
+                }
+                i = drawer.end_line + 1;
+            } else {
+                let trimmed = lines[i].trim_start();
+                let planning = ["SCHEDULED:", "DEADLINE:", "CLOSED:"]
+                    .iter()
+                    .any(|p| trimmed.starts_with(p));
+                if !planning {
+                    answer.push(lines[i]);
+                }
+                i += 1;
+            }
+        }
+        let answer = answer.join("\n");
+        if answer.trim().is_empty() {
+            return Err(self.error("Headline card has no answer.", start));
+        }
+        Ok(Card::new(
+            self.deck_name.clone(),
+            self.file_path.clone(),
+            (start, end.saturating_sub(1)),
+            CardContent::new_basic(title, answer),
+            note.filter(|note| !note.is_empty()),
+            self.hash_version,
+        ))
+    }
+
+    /// Read the drawer that starts at the given line.
+    fn read_drawer(&self, lines: &[&str], start: usize) -> Result<Drawer, ParserError> {
+        let name = drawer_name(lines[start]).unwrap_or_default().to_uppercase();
+        let end_line = (start + 1..lines.len())
+            .find(|&j| drawer_name(lines[j]).is_some_and(|n| n.eq_ignore_ascii_case("END")))
+            .ok_or_else(|| self.error(format!("Drawer :{name}: is not closed."), start))?;
+        let content = lines[start + 1..end_line].join("\n").trim().to_string();
+        Ok(Drawer {
+            name,
+            content,
+            end_line,
+        })
+    }
+
+    /// If a `:NOTE:` drawer follows the given line (after blank lines), read
+    /// it. Returns the note and the line after it.
+    fn read_note(
+        &self,
+        lines: &[&str],
+        start: usize,
+    ) -> Result<(Option<String>, usize), ParserError> {
+        let next = (start..lines.len()).find(|&j| !lines[j].trim().is_empty());
+        match next {
+            Some(j) if drawer_name(lines[j]).is_some_and(|n| n.eq_ignore_ascii_case("NOTE")) => {
+                let drawer = self.read_drawer(lines, j)?;
+                let note = Some(drawer.content).filter(|note| !note.is_empty());
+                Ok((note, drawer.end_line + 1))
+            }
+            _ => Ok((None, start)),
+        }
+    }
+
+    /// Generate one card per `{{deletion}}` in the text.
+    fn cloze_cards(
+        &self,
+        text: &str,
+        note: Option<String>,
+        start_line: usize,
+        end_line: usize,
+    ) -> Result<Vec<Card>, ParserError> {
+        let (clean_text, deletions) =
+            parse_cloze(text).map_err(|message| self.error(message, start_line))?;
+        let cards = deletions
+            .into_iter()
+            .map(|(start, end)| {
+                Card::new(
+                    self.deck_name.clone(),
+                    self.file_path.clone(),
+                    (start_line, end_line),
+                    CardContent::new_cloze(clean_text.clone(), start, end),
+                    note.clone(),
+                    self.hash_version,
+                )
+            })
+            .collect();
+        Ok(cards)
+    }
+
+    fn error(&self, message: impl Into<String>, line_num: usize) -> ParserError {
+        ParserError::new(message, self.file_path.clone(), line_num)
+    }
+}
+
+/// The byte positions of the first and last character of a cloze deletion.
+type Deletion = (usize, usize);
+
+/// Remove the `{{...}}` markers from a cloze text. Returns the clean text and
+/// the deletions.
+fn parse_cloze(text: &str) -> Result<(String, Vec<Deletion>), &'static str> {
+    let mut clean = String::new();
+    let mut deletions = Vec::new();
+    let mut rest = text;
+    while let Some(open) = rest.find("{{") {
+        clean.push_str(&rest[..open]);
+        let after = &rest[open + 2..];
+        let close = after.find("}}").ok_or("Cloze deletion is not closed.")?;
+        let deleted = &after[..close];
+        if deleted.is_empty() {
+            return Err("Cloze deletion is empty.");
+        }
+        let start = clean.len();
+        clean.push_str(deleted);
+        deletions.push((start, clean.len() - 1));
+        rest = &after[close + 2..];
+    }
+    clean.push_str(rest);
+    if deletions.is_empty() {
+        return Err("Cloze card must contain at least one cloze deletion.");
+    }
+    Ok((clean, deletions))
+}
+
+/// If the line is a headline, return its level and text.
+fn headline(line: &str) -> Option<(usize, &str)> {
+    let level = line.len() - line.trim_start_matches('*').len();
+    if level == 0 {
+        return None;
+    }
+    let rest = &line[level..];
+    if rest.is_empty() {
+        Some((level, ""))
+    } else if rest.starts_with([' ', '\t']) {
+        Some((level, rest.trim()))
+    } else {
+        None
+    }
+}
+
+/// Split a headline's tags, like `:card:physics:`, from its title.
+fn split_tags(title: &str) -> (&str, Vec<&str>) {
+    if let Some((head, last)) = title.rsplit_once([' ', '\t']).or(Some(("", title))) {
+        if last.len() > 1 && last.starts_with(':') && last.ends_with(':') {
+            let tags = last.trim_matches(':').split(':').collect();
+            return (head.trim(), tags);
+        }
+    }
+    (title, Vec::new())
+}
+
+/// If the line is a drawer delimiter, like `:NAME:`, return the name.
+fn drawer_name(line: &str) -> Option<&str> {
+    let name = line.trim().strip_prefix(':')?.strip_suffix(':')?;
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    valid.then_some(name)
+}
+
+/// If the line is a `:LINT_ALLOW:` property, return the rules it lists.
+pub fn lint_allow_property(line: &str) -> Option<&str> {
+    let (tag, rules) = line.trim().split_at_checked(LINT_ALLOW_PROPERTY.len())?;
+    tag.eq_ignore_ascii_case(LINT_ALLOW_PROPERTY)
+        .then(|| rules.trim())
+}
+
+/// If the line is a keyword like `#+NAME: value`, return the value.
+fn keyword<'a>(line: &'a str, name: &str) -> Option<&'a str> {
+    let rest = line.trim().strip_prefix("#+")?;
+    let (key, value) = rest.split_once(':')?;
+    key.eq_ignore_ascii_case(name).then(|| value.trim())
+}
+
+/// If the line starts a block like `#+BEGIN_SRC rust`, return the block type,
+/// in upper case, and its parameters.
+fn block_start(line: &str) -> Option<(String, &str)> {
+    let rest = line.trim();
+    let prefix = rest.get(..8)?;
+    if !prefix.eq_ignore_ascii_case("#+begin_") {
+        return None;
+    }
+    let rest = &rest[8..];
+    let (kind, params) = rest.split_once(' ').unwrap_or((rest, ""));
+    Some((kind.to_uppercase(), params.trim()))
+}
+
+fn is_block_end(line: &str, kind: &str) -> bool {
+    line.trim().eq_ignore_ascii_case(&format!("#+end_{kind}"))
+}
+
+/// If the line is a list item, return whether it is numbered, and its text.
+fn list_item(line: &str) -> Option<(bool, &str)> {
+    let line = line.trim_start();
+    for marker in ["- ", "+ "] {
+        if let Some(text) = line.strip_prefix(marker) {
+            return Some((false, text));
+        }
+    }
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if digits > 0 {
+        let rest = &line[digits..];
+        if let Some(text) = rest.strip_prefix(". ").or_else(|| rest.strip_prefix(") ")) {
+            return Some((true, text));
+        }
+    }
+    None
+}
+
+/// Whether the line starts something other than a paragraph.
+fn starts_block(line: &str) -> bool {
+    let trimmed = line.trim();
+    trimmed.is_empty()
+        || headline(line).is_some()
+        || list_item(line).is_some()
+        || block_start(line).is_some()
+        || trimmed.starts_with('|')
+        || trimmed.starts_with("\\[")
+        || trimmed.starts_with("$$")
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Render Org-mode text to HTML. Supports paragraphs, headlines, lists,
+/// tables, source, example, and quote blocks, display math, and the inline
+/// markup handled by [`render_inline`].
+pub fn org_to_html(config: &MarkdownRenderConfig, text: &str) -> Fallible<String> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut html = String::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            i += 1;
+        } else if let Some((kind, params)) = block_start(line) {
+            let end = (i + 1..lines.len())
+                .find(|&j| is_block_end(lines[j], &kind))
+                .unwrap_or(lines.len());
+            let body = lines[i + 1..end].join("\n");
+            match kind.as_str() {
+                "QUOTE" => {
+                    html.push_str("<blockquote>\n");
+                    html.push_str(&org_to_html(config, &body)?);
+                    html.push_str("</blockquote>\n");
+                }
+                _ => {
+                    let lang = params.split_whitespace().next().filter(|_| kind == "SRC");
+                    match lang {
+                        Some(lang) => html
+                            .push_str(&format!("<pre><code class=\"language-{}\">", escape(lang))),
+                        None => html.push_str("<pre><code>"),
+                    }
+                    html.push_str(&escape(&body));
+                    html.push_str("\n</code></pre>\n");
+                }
+            }
+            i = end + 1;
+        } else if trimmed.starts_with("\\[") || trimmed.starts_with("$$") {
+            let (open, close) = if trimmed.starts_with("\\[") {
+                ("\\[", "\\]")
+            } else {
+                ("$$", "$$")
+            };
+            let mut math = trimmed[open.len()..].to_string();
+            let mut end = i;
+            while !math.trim_end().ends_with(close) && end + 1 < lines.len() {
+                end += 1;
+                math.push('\n');
+                math.push_str(lines[end]);
+            }
+            let math = math.trim_end().strip_suffix(close).unwrap_or(&math);
+            html.push_str(&format!(
+                "<p><span class=\"math math-display\">{}</span></p>\n",
+                escape(math.trim())
+            ));
+            i = end + 1;
+        } else if let Some((level, title)) = headline(line) {
+            let level = level.min(6);
+            let (title, _) = split_tags(title);
+            html.push_str(&format!(
+                "<h{level}>{}</h{level}>\n",
+                render_inline(config, title)?
+            ));
+            i += 1;
+        } else if trimmed.starts_with('|') {
+            let end = (i..lines.len())
+                .find(|&j| !lines[j].trim().starts_with('|'))
+                .unwrap_or(lines.len());
+            html.push_str(&render_table(config, &lines[i..end])?);
+            i = end;
+        } else if let Some((ordered, _)) = list_item(line) {
+            let mut items: Vec<String> = Vec::new();
+            while i < lines.len() {
+                let line = lines[i];
+                if let Some((_, text)) = list_item(line) {
+                    items.push(text.to_string());
+                } else if line.trim().is_empty() {
+                    let next = lines.get(i + 1).copied().unwrap_or("");
+                    if list_item(next).is_none() && !next.starts_with([' ', '\t']) {
+                        break;
+                    }
+                } else if line.starts_with([' ', '\t']) {
+                    if let Some(item) = items.last_mut() {
+                        item.push('\n');
+                        item.push_str(line.trim());
+                    }
+                } else {
+                    break;
+                }
+                i += 1;
+            }
+            let tag = if ordered { "ol" } else { "ul" };
+            html.push_str(&format!("<{tag}>\n"));
+            for item in items {
+                html.push_str(&format!("<li>{}</li>\n", render_inline(config, &item)?));
+            }
+            html.push_str(&format!("</{tag}>\n"));
+        } else if trimmed.starts_with("#+") || trimmed == "#" || trimmed.starts_with("# ") {
+            // Keywords and comments are not rendered.
+            i += 1;
+        } else {
+            let end = (i + 1..lines.len())
+                .find(|&j| starts_block(lines[j]))
+                .unwrap_or(lines.len());
+            let paragraph: Vec<&str> = lines[i..end].iter().map(|line| line.trim()).collect();
+            html.push_str(&format!(
+                "<p>{}</p>\n",
+                render_inline(config, &paragraph.join("\n"))?
+            ));
+            i = end;
+        }
+    }
+    Ok(html)
+}
+
+/// Like [`org_to_html`], but without the enclosing paragraph, for fragments
+/// like cloze deletions.
+pub fn org_to_html_inline(config: &MarkdownRenderConfig, text: &str) -> Fallible<String> {
+    let html = org_to_html(config, text)?;
+    if html.starts_with("<p>") && html.ends_with("</p>\n") {
+        let len = html.len();
+        Ok(html[3..len - 5].to_string())
+    } else {
+        Ok(html)
+    }
+}
+
+/// Reduce Org-mode text to a canonical form, for hashing.
+///
+/// The text is converted to Unicode NFC, runs of whitespace inside lines are
+/// collapsed, and runs of blank lines are reduced to one, except in blocks.
+/// Line breaks and indentation are kept, since they decide where lists and
+/// paragraphs end.
+pub fn normalize_org(text: &str) -> String {
+    let text: String = text.nfc().collect();
+    let mut lines: Vec<String> = Vec::new();
+    // The type of the block the line is in, if any.
+    let mut block: Option<String> = None;
+    for line in text.lines() {
+        if let Some(kind) = &block {
+            if is_block_end(line, kind) {
+                block = None;
+            }
+            lines.push(line.to_string());
+            continue;
+        }
+        block = block_start(line).map(|(kind, _)| kind);
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            if lines.last().is_some_and(|last| !last.is_empty()) {
+                lines.push(String::new());
+            }
+            continue;
+        }
+        let indent = &line[..line.len() - line.trim_start().len()];
+        let words: Vec<&str> = trimmed.split_whitespace().collect();
+        lines.push(format!("{indent}{}", words.join(" ")));
+    }
+    lines.join("\n").trim().to_string()
+}
+
+/// Render a table. A rule line, like `|---+---|`, after the first row makes
+/// the first row a header.
+fn render_table(config: &MarkdownRenderConfig, lines: &[&str]) -> Fallible<String> {
+    let is_rule = |line: &str| line.trim().starts_with("|-");
+    let rows: Vec<&str> = lines
+        .iter()
+        .copied()
+        .filter(|line| !is_rule(line))
+        .collect();
+    let has_header = lines.len() > 1 && !is_rule(lines[0]) && is_rule(lines[1]);
+    let cells = |line: &str| -> Vec<String> {
+        let line = line.trim().trim_start_matches('|');
+        let line = line.strip_suffix('|').unwrap_or(line);
+        line.split('|')
+            .map(|cell| cell.trim().to_string())
+            .collect()
+    };
+    let mut html = String::from("<table>");
+    for (n, row) in rows.iter().enumerate() {
+        let header = has_header && n == 0;
+        if header {
+            html.push_str("<thead>");
+        } else if n == 0 || (has_header && n == 1) {
+            html.push_str("<tbody>");
+        }
+        html.push_str("<tr>");
+        let tag = if header { "th" } else { "td" };
+        for cell in cells(row) {
+            html.push_str(&format!("<{tag}>{}</{tag}>", render_inline(config, &cell)?));
+        }
+        html.push_str("</tr>");
+        if header {
+            html.push_str("</thead>");
+        }
+    }
+    if rows.len() > usize::from(has_header) {
+        html.push_str("</tbody>");
+    }
+    html.push_str("</table>\n");
+    Ok(html)
+}
+
+/// Whether the character can come before an emphasis marker.
+fn is_pre(c: Option<&char>) -> bool {
+    c.is_none_or(|c| c.is_whitespace() || "-({'\"".contains(*c))
+}
+
+/// Whether the character can come after an emphasis marker.
+fn is_post(c: Option<&char>) -> bool {
+    c.is_none_or(|c| c.is_whitespace() || "-.,;:!?')}\"[".contains(*c))
+}
+
+/// Find the first occurrence of `pattern` in `chars` at or after `from`.
+fn find(chars: &[char], from: usize, pattern: &str) -> Option<usize> {
+    let pattern: Vec<char> = pattern.chars().collect();
+    (from..chars.len()).find(|&i| chars[i..].starts_with(&pattern))
+}
+
+/// Render inline markup: `*bold*`, `/italic/`, `_underline_`, `+strike+`,
+/// `=verbatim=`, `~code~`, links like `[[target][description]]`, and math
+/// like `$x$` or `\(x\)`. Links to local images and audio are embedded.
+fn render_inline(config: &MarkdownRenderConfig, text: &str) -> Fallible<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut html = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '[' && chars.get(i + 1) == Some(&'[') {
+            if let Some(end) = find(&chars, i + 2, "]]") {
+                let link: String = chars[i + 2..end].iter().collect();
+                html.push_str(&render_link(config, &link)?);
+                i = end + 2;
+                continue;
+            }
+        }
+        if c == '\\' && chars.get(i + 1) == Some(&'(') {
+            if let Some(end) = find(&chars, i + 2, "\\)") {
+                let math: String = chars[i + 2..end].iter().collect();
+                html.push_str(&format!(
+                    "<span class=\"math math-inline\">{}</span>",
+                    escape(&math)
+                ));
+                i = end + 2;
+                continue;
+            }
+        }
+        if c == '$' && chars.get(i + 1).is_some_and(|c| !c.is_whitespace()) {
+            let end = (i + 1..chars.len()).find(|&j| {
+                chars[j] == '$' && !chars[j - 1].is_whitespace() && is_post(chars.get(j + 1))
+            });
+            if let Some(end) = end {
+                let math: String = chars[i + 1..end].iter().collect();
+                html.push_str(&format!(
+                    "<span class=\"math math-inline\">{}</span>",
+                    escape(&math)
+                ));
+                i = end + 1;
+                continue;
+            }
+        }
+        let markup = match c {
+            '*' => Some(("strong", false)),
+            '/' => Some(("em", false)),
+            '_' => Some(("u", false)),
+            '+' => Some(("del", false)),
+            '=' | '~' => Some(("code", true)),
+            _ => None,
+        };
+        if let Some((tag, verbatim)) = markup {
+            let opens = is_pre(i.checked_sub(1).and_then(|p| chars.get(p)))
+                && chars.get(i + 1).is_some_and(|c| !c.is_whitespace());
+            let end = (i + 2..chars.len()).find(|&j| {
+                chars[j] == c && !chars[j - 1].is_whitespace() && is_post(chars.get(j + 1))
+            });
+            if let (true, Some(end)) = (opens, end) {
+                let inner: String = chars[i + 1..end].iter().collect();
+                let inner = if verbatim {
+                    escape(&inner)
+                } else {
+                    render_inline(config, &inner)?
+                };
+                html.push_str(&format!("<{tag}>{inner}</{tag}>"));
+                i = end + 1;
+                continue;
+            }
+        }
+        html.push_str(&escape(&c.to_string()));
+        i += 1;
+    }
+    Ok(html)
+}
+
+/// If a link target is a local media file, return its path.
+fn media_path(target: &str) -> Option<&str> {
+    let path = target.strip_prefix("file:").unwrap_or(target);
+    if path.contains("://") || path.starts_with("mailto:") {
+        return None;
+    }
+    let ext = path.rsplit_once('.')?.1.to_lowercase();
+    let is_media = IMAGE_EXTENSIONS.contains(&ext.as_str()) || is_audio_file(&ext);
+    is_media.then_some(path)
+}
+
+fn render_link(config: &MarkdownRenderConfig, link: &str) -> Fallible<String> {
+    let (target, description) = match link.split_once("][") {
+        Some((target, description)) => (target, Some(description)),
+        None => (link, None),
+    };
+    match media_path(target) {
+        Some(path) => {
+            let url = modify_url(path, config)?;
+            let description = escape(description.unwrap_or(""));
+            if is_audio_file(&url) {
+                Ok(format!(
+                    r#"<audio controls src="{url}" title="{description}"></audio>"#
+                ))
+            } else {
+                Ok(format!(r#"<img src="{url}" alt="{description}" />"#))
+            }
+        }
+        None => {
+            let text = match description {
+                Some(description) => render_inline(config, description)?,
+                None => escape(target),
+            };
+            Ok(format!(r#"<a href="{}">{text}</a>"#, escape(target)))
+        }
+    }
+}
+
+/// Extract the paths of the local media files linked from Org-mode text.
+pub fn extract_media_paths(text: &str) -> Vec<String> {
+    let mut paths = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("[[") {
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("]]") else {
+            break;
+        };
+        let link = &after[..end];
+        let target = link.split_once("][").map_or(link, |(target, _)| target);
+        if let Some(path) = media_path(target) {
+            paths.push(path.to_string());
+        }
+        rest = &after[end + 2..];
+    }
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::DeckFormat;
+    use crate::helper::create_tmp_directory;
+    use crate::media::resolve::MediaResolverBuilder;
+
+    fn parse(text: &str) -> Result<Vec<Card>, ParserError> {
+        let reader = OrgReader {
+            deck_name: "test_deck".to_string(),
+            file_path: PathBuf::from("test.org"),
+            hash_version: HashVersion::Raw,
+        };
+        let (cards, errors) = reader.parse_all(text);
+        match errors.into_iter().next() {
+            Some(error) => Err(error),
+            None => Ok(cards),
+        }
+    }
+
+    fn make_test_config() -> Fallible<MarkdownRenderConfig> {
+        let coll_path: PathBuf = create_tmp_directory()?;
+        std::fs::write(coll_path.join("deck.org"), "")?;
+        std::fs::write(coll_path.join("image.png"), "")?;
+        let config = MarkdownRenderConfig {
+            resolver: MediaResolverBuilder::new()
+                .with_collection_path(coll_path)?
+                .with_deck_path(PathBuf::from("deck.org"))?
+                .build()?,
+            port: 1234,
+        };
+        Ok(config)
+    }
+
+    #[test]
+    fn test_headline_card() -> Result<(), ParserError> {
+        let cards = parse(
+            "#+TITLE: Physics\n\n* Notes\nSome prose.\n* What is $c$? :card:\n:PROPERTIES:\n:ID: 1\n:END:\nThe speed of light.\n:NOTE:\nIn a vacuum.\n:END:\n* Next",
+        )?;
+        assert_eq!(cards.len(), 1);
+        match cards[0].content() {
+            CardContent::Basic { question, answer } => {
+                assert_eq!(question, "What is $c$?");
+                assert_eq!(answer, "The speed of light.");
+            }
+            _ => panic!("Expected basic card."),
+        }
+        assert_eq!(cards[0].note(), Some("In a vacuum."));
+        assert_eq!(cards[0].range(), (4, 11));
+        Ok(())
+    }
+
+    #[test]
+    fn test_drawer_cards() -> Result<(), ParserError> {
+        let cards = parse(
+            ":QUESTION:\nWhat is a group?\n:END:\n:ANSWER:\nA monoid with inverses.\n:END:\n\n:CLOZE:\nThe {{order}} of a group is its {{cardinality}}.\n:END:\n\n:NOTE:\nA note.\n:end:",
+        )?;
+        assert_eq!(cards.len(), 3);
+        match cards[0].content() {
+            CardContent::Basic { question, answer } => {
+                assert_eq!(question, "What is a group?");
+                assert_eq!(answer, "A monoid with inverses.");
+            }
+            _ => panic!("Expected basic card."),
+        }
+        assert_eq!(cards[0].note(), None);
+        assert!(matches!(
+            cards[1].content(),
+            CardContent::Cloze { text, start: 4, end: 8 } if text == "The order of a group is its cardinality."
+        ));
+        assert_eq!(cards[2].note(), Some("A note."));
+        assert_eq!(cards[2].range(), (7, 13));
+        Ok(())
+    }
+
+    #[test]
+    fn test_same_hash_as_markdown() -> Result<(), ParserError> {
+        let org = parse(":CLOZE:\nThe {{order}} of a group.\n:END:")?;
+        let markdown = crate::parser::Parser::new(
+            "test_deck".to_string(),
+            PathBuf::from("test.md"),
+            HashVersion::Raw,
+        )
+        .parse("C: The [order] of a group.")?;
+        assert_eq!(org[0].hash(), markdown[0].hash());
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_cards() {
+        assert!(parse(":QUESTION:\nfoo\n:END:").is_err());
+        assert!(parse(":ANSWER:\nfoo\n:END:").is_err());
+        assert!(parse(":QUESTION:\nfoo\n:END:\n:QUESTION:\nbar\n:END:").is_err());
+        assert!(parse(":NOTE:\nfoo\n:END:").is_err());
+        assert!(parse(":CLOZE:\nno deletions\n:END:").is_err());
+        assert!(parse(":CLOZE:\n{{unclosed\n:END:").is_err());
+        assert!(parse(":CLOZE:\n{{}}\n:END:").is_err());
+        assert!(parse(":QUESTION:\nfoo").is_err());
+        assert!(parse("* Empty :card:\n* Next").is_err());
+    }
+
+    #[test]
+    fn test_deck_name() -> Fallible<()> {
+        let text = "#+DECK: Physics\n* c? :card:\nLight speed.";
+        let cards = DeckFormat::Org.parse_file(Path::new("notes.org"), text, HashVersion::Raw)?;
+        assert_eq!(cards[0].deck_name(), "Physics");
+        let cards = DeckFormat::Org.parse_file(
+            Path::new("notes.org"),
+            "* c? :card:\nx",
+            HashVersion::Raw,
+        )?;
+        assert_eq!(cards[0].deck_name(), "notes");
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_inline_markup() -> Fallible<()> {
+        let config = make_test_config()?;
+        let html = org_to_html(
+            &config,
+            "Some *bold*, /italic/, =<code>=, and $x^2$ text.\nSee [[https://example.com][the site]] and [[file:image.png]].",
+        )?;
+        assert_eq!(
+            html,
+            "<p>Some <strong>bold</strong>, <em>italic</em>, <code>&lt;code&gt;</code>, and <span class=\"math math-inline\">x^2</span> text.\nSee <a href=\"https://example.com\">the site</a> and <img src=\"http://localhost:1234/file/image.png\" alt=\"\" />.</p>\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_blocks() -> Fallible<()> {
+        let config = make_test_config()?;
+        let html = org_to_html(
+            &config,
+            "- one\n- two\n\n#+BEGIN_SRC rust\nfn main() {}\n#+END_SRC\n\n\\[ e^{i\\pi} \\]\n\n| a | b |\n|---+---|\n| 1 | 2 |",
+        )?;
+        assert_eq!(
+            html,
+            "<ul>\n<li>one</li>\n<li>two</li>\n</ul>\n<pre><code class=\"language-rust\">fn main() {}\n</code></pre>\n<p><span class=\"math math-display\">e^{i\\pi}</span></p>\n<table><thead><tr><th>a</th><th>b</th></tr></thead><tbody><tr><td>1</td><td>2</td></tr></tbody></table>\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_inline_fragment() -> Fallible<()> {
+        let config = make_test_config()?;
+        assert_eq!(
+            org_to_html_inline(&config, "*deleted*")?,
+            "<strong>deleted</strong>"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_markers_inside_words() -> Fallible<()> {
+        let config = make_test_config()?;
+        let html = org_to_html_inline(&config, "a/b/c and snake_case_name and C++ and 1 + 2")?;
+        assert_eq!(html, "a/b/c and snake_case_name and C++ and 1 + 2");
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_media_paths() {
+        let text = "[[file:a.png]] [[https://example.com/b.png]] [[c.mp3][audio]] [[notes.org]]";
+        assert_eq!(extract_media_paths(text), vec!["a.png", "c.mp3"]);
+    }
+
+    #[test]
+    fn test_normalize_org() {
+        let text = "\n  Some   /words/ \n\n\n- an item\n  continued\n#+BEGIN_SRC python\ndef f():\n\n\n    return  1\n#+END_SRC\n";
+        let expected = "Some /words/\n\n- an item\n  continued\n#+BEGIN_SRC python\ndef f():\n\n\n    return  1\n#+END_SRC";
+        assert_eq!(normalize_org(text), expected);
+    }
+}
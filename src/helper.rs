@@ -12,15 +12,37 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::env::set_var;
 use std::fs::copy;
 use std::fs::create_dir_all;
 use std::path::PathBuf;
+use std::sync::Once;
 
 use tempfile::tempdir;
 
+use crate::db::Database;
+use crate::db::ReviewRecord;
 use crate::error::Fallible;
+use crate::types::card_hash::CardHash;
+use crate::types::performance::Performance;
+use crate::types::timestamp::Timestamp;
+
+/// Point `$XDG_STATE_HOME` at a temporary directory, so that the databases of
+/// the tests' collections are kept out of the user's state directory.
+pub fn isolate_state_directory() {
+    static ONCE: Once = Once::new();
+    ONCE.call_once(|| {
+        let state = tempdir()
+            .expect("Failed to create the state directory.")
+            .keep();
+        // SAFETY: the variable is set once, and the tests only read the
+        // environment through `std::env`, which synchronizes with this.
+        unsafe { set_var("XDG_STATE_HOME", state) };
+    });
+}
 
 pub fn create_tmp_directory() -> Fallible<PathBuf> {
+    isolate_state_directory();
     let dir: PathBuf = tempdir()?.path().to_path_buf().canonicalize()?;
     create_dir_all(&dir)?;
     Ok(dir)
@@ -36,6 +58,22 @@ pub fn create_tmp_copy_of_test_directory() -> Fallible<String> {
     Ok(target.display().to_string())
 }
 
+/// Record a drilling session with the given reviews, and write the given card
+/// performance, the way a drilling session is finished.
+pub fn save_session(
+    db: &mut Database,
+    started_at: Timestamp,
+    ended_at: Timestamp,
+    reviews: &[ReviewRecord],
+    performance: &[(CardHash, Performance)],
+) -> Fallible<()> {
+    let id = db.start_pending_session(started_at, &[], reviews.len())?;
+    for review in reviews {
+        db.add_pending_review(id, review, &[])?;
+    }
+    db.finish_pending_session(id, ended_at, performance)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
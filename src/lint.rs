@@ -0,0 +1,578 @@
+// Copyright 2025 Fernando Borretti
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Quality checks for decks. Unlike `check`, which reports cards that can't
+//! be read, the linter reports cards that can be read but are likely to be
+//! hard to learn.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::path::Component;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::config::CONFIG_FILE;
+use crate::config::CollectionConfig;
+use crate::config::LintConfig;
+use crate::error::Fallible;
+use crate::error::fail;
+use crate::format::DeckFormat;
+use crate::media::validate::media_references;
+use crate::parser::DeckFile;
+use crate::parser::read_deck_files;
+use crate::types::card::Card;
+use crate::types::card::CardContent;
+use crate::types::card_hash::CardHash;
+use crate::types::card_hash::HashVersion;
+use crate::types::diagnostic::Diagnostic;
+use crate::types::diagnostic::Severity;
+use crate::walk::media_files;
+
+/// A lint rule.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Rule {
+    /// An answer longer than `max-answer-length` characters.
+    LongAnswer,
+    /// A cloze deletion that hides more than `max-cloze-fraction` of the text.
+    LargeCloze,
+    /// Cards with different questions and the same answer.
+    SameAnswer,
+    /// Cards with the same hash, of which only one is drilled.
+    DuplicateCard,
+    /// A deck file without cards.
+    EmptyDeck,
+    /// An odd number of `$` delimiters, outside of code.
+    UnbalancedMath,
+    /// A media file that no card refers to.
+    UnusedMedia,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 7] = [
+        Rule::LongAnswer,
+        Rule::LargeCloze,
+        Rule::SameAnswer,
+        Rule::DuplicateCard,
+        Rule::EmptyDeck,
+        Rule::UnbalancedMath,
+        Rule::UnusedMedia,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Rule::LongAnswer => "long-answer",
+            Rule::LargeCloze => "large-cloze",
+            Rule::SameAnswer => "same-answer",
+            Rule::DuplicateCard => "duplicate-card",
+            Rule::EmptyDeck => "empty-deck",
+            Rule::UnbalancedMath => "unbalanced-math",
+            Rule::UnusedMedia => "unused-media",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Rule> {
+        Rule::ALL.into_iter().find(|rule| rule.name() == name)
+    }
+}
+
+/// Lint every deck file in the collection. The diagnostics are sorted by
+/// location.
+pub fn collect_lints(directory: &Path, hash_version: HashVersion) -> Fallible<Vec<Diagnostic>> {
+    let config = CollectionConfig::load(directory)?.lint;
+    let decks = read_deck_files(directory, hash_version)?;
+    let mut linter = Linter::new(config, &decks)?;
+
+    // Included cards are read once for every file that includes them, but
+    // they are only linted once.
+    let mut seen = HashSet::new();
+    let cards: Vec<Card> = decks
+        .iter()
+        .flat_map(|deck| &deck.cards)
+        .filter(|card| seen.insert((card.file_path().clone(), card.range(), card.hash())))
+        .cloned()
+        .collect();
+    linter.index_cards(&cards);
+
+    for deck in &decks {
+        linter.lint_deck(deck);
+    }
+    for card in &cards {
+        linter.lint_card(card);
+    }
+    linter.lint_answers(&cards);
+    linter.lint_duplicates(&cards);
+    linter.lint_media(directory, &cards)?;
+
+    let mut diagnostics = linter.diagnostics;
+    diagnostics.sort();
+    diagnostics.dedup();
+    Ok(diagnostics)
+}
+
+/// The line of a directive, and the rules it suppresses.
+type Directive = (usize, Vec<Rule>);
+
+/// Rules suppressed by a directive, and the first line of the card it applies
+/// to.
+struct Suppression {
+    card_start: Option<usize>,
+    rules: Vec<Rule>,
+}
+
+struct Linter {
+    config: LintConfig,
+    allowed: HashSet<Rule>,
+    denied: HashSet<Rule>,
+    /// The directives in each deck file, with the line they are on.
+    directives: HashMap<PathBuf, (DeckFormat, Vec<Directive>)>,
+    /// The suppressions in each deck file, once the cards are known.
+    suppressions: HashMap<PathBuf, Vec<Suppression>>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Linter {
+    fn new(config: LintConfig, decks: &[DeckFile]) -> Fallible<Self> {
+        let rules = |names: &[String]| -> Fallible<HashSet<Rule>> {
+            names
+                .iter()
+                .map(|name| match Rule::from_name(name) {
+                    Some(rule) => Ok(rule),
+                    None => fail(format!("Unknown lint rule '{name}' in {CONFIG_FILE}.")),
+                })
+                .collect()
+        };
+        let allowed = rules(&config.allow)?;
+        let denied = rules(&config.deny)?;
+        let mut linter = Self {
+            config,
+            allowed,
+            denied,
+            directives: HashMap::new(),
+            suppressions: HashMap::new(),
+            diagnostics: Vec::new(),
+        };
+        for deck in decks {
+            let mut directives = Vec::new();
+            for (line_num, line) in deck.text.lines().enumerate() {
+                let Some(names) = deck.format.lint_allow(line) else {
+                    continue;
+                };
+                let mut rules = Vec::new();
+                for name in names.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+                    match Rule::from_name(name) {
+                        Some(rule) => rules.push(rule),
+                        None => linter.diagnostics.push(Diagnostic::new(
+                            Severity::Warning,
+                            &deck.path,
+                            line_num + 1,
+                            1,
+                            format!("Unknown lint rule '{name}'."),
+                        )),
+                    }
+                }
+                directives.push((line_num, rules));
+            }
+            linter
+                .directives
+                .insert(deck.path.clone(), (deck.format, directives));
+        }
+        Ok(linter)
+    }
+
+    /// Work out which card each directive applies to. In Markdown, it is the
+    /// card that starts after the directive. In Org-mode, it is the card whose
+    /// lines contain the directive, as with a property in the card's headline,
+    /// or else the card that starts after it.
+    fn index_cards(&mut self, cards: &[Card]) {
+        let mut ranges: HashMap<&PathBuf, Vec<(usize, usize)>> = HashMap::new();
+        for card in cards {
+            ranges
+                .entry(card.file_path())
+                .or_default()
+                .push(card.range());
+        }
+        for (path, (format, directives)) in &self.directives {
+            let ranges = ranges.get(path).map(Vec::as_slice).unwrap_or_default();
+            let suppressions = directives
+                .iter()
+                .map(|(line_num, rules)| {
+                    let containing = ranges
+                        .iter()
+                        .filter(|(start, end)| start <= line_num && line_num <= end)
+                        .map(|(start, _)| *start)
+                        .min();
+                    let next = ranges
+                        .iter()
+                        .map(|(start, _)| *start)
+                        .filter(|start| start > line_num)
+                        .min();
+                    let card_start = match format {
+                        DeckFormat::Markdown => next,
+                        DeckFormat::Org => containing.or(next),
+                    };
+                    Suppression {
+                        card_start,
+                        rules: rules.clone(),
+                    }
+                })
+                .collect();
+            self.suppressions.insert(path.clone(), suppressions);
+        }
+    }
+
+    fn lint_deck(&mut self, deck: &DeckFile) {
+        if deck.cards.is_empty() && deck.diagnostics.is_empty() {
+            // A deck without cards has nothing for a directive to apply to,
+            // so a directive anywhere in the file suppresses the warning.
+            let suppressed = self
+                .suppressions
+                .get(&deck.path)
+                .is_some_and(|s| s.iter().any(|s| s.rules.contains(&Rule::EmptyDeck)));
+            if !suppressed {
+                self.report(
+                    Rule::EmptyDeck,
+                    &deck.path,
+                    1,
+                    "Deck file contains no cards.",
+                );
+            }
+        }
+    }
+
+    fn lint_card(&mut self, card: &Card) {
+        match card.content() {
+            CardContent::Basic { answer, .. } => {
+                let length = answer.chars().count();
+                let max = self.config.max_answer_length;
+                if length > max {
+                    self.report_card(
+                        Rule::LongAnswer,
+                        card,
+                        format!("Answer is {length} characters long, more than {max}."),
+                    );
+                }
+            }
+            CardContent::Cloze { text, start, end } => {
+                let deleted = String::from_utf8_lossy(&text.as_bytes()[*start..*end + 1])
+                    .chars()
+                    .count();
+                let fraction = deleted as f64 / text.chars().count() as f64;
+                let max = self.config.max_cloze_fraction;
+                if fraction > max {
+                    self.report_card(
+                        Rule::LargeCloze,
+                        card,
+                        format!(
+                            "Cloze deletion hides {:.0}% of the text, more than {:.0}%.",
+                            fraction * 100.0,
+                            max * 100.0
+                        ),
+                    );
+                }
+            }
+            CardContent::List { .. } => {}
+        }
+        let mut texts = card_texts(card);
+        texts.extend(card.note());
+        if texts.into_iter().any(has_unbalanced_math) {
+            self.report_card(
+                Rule::UnbalancedMath,
+                card,
+                "Card has an unbalanced `$` math delimiter.",
+            );
+        }
+    }
+
+    fn lint_answers(&mut self, cards: &[Card]) {
+        let mut first: HashMap<&str, &Card> = HashMap::new();
+        for card in cards {
+            let CardContent::Basic { question, answer } = card.content() else {
+                continue;
+            };
+            let Some(other) = first.get(answer.trim()).copied() else {
+                first.insert(answer.trim(), card);
+                continue;
+            };
+            let CardContent::Basic {
+                question: other_question,
+                ..
+            } = other.content()
+            else {
+                continue;
+            };
+            if question.trim() != other_question.trim() {
+                self.report_card(
+                    Rule::SameAnswer,
+                    card,
+                    format!(
+                        "Card has the same answer as the card at {}, with a different question.",
+                        location(other)
+                    ),
+                );
+            }
+        }
+    }
+
+    fn lint_duplicates(&mut self, cards: &[Card]) {
+        let mut first: HashMap<CardHash, &Card> = HashMap::new();
+        for card in cards {
+            match first.get(&card.hash()) {
+                Some(other) => self.report_card(
+                    Rule::DuplicateCard,
+                    card,
+                    format!(
+                        "Card is a duplicate of the card at {}, and is not drilled.",
+                        location(other)
+                    ),
+                ),
+                None => {
+                    first.insert(card.hash(), card);
+                }
+            }
+        }
+    }
+
+    fn lint_media(&mut self, directory: &Path, cards: &[Card]) -> Fallible<()> {
+        if self.allowed.contains(&Rule::UnusedMedia) {
+            return Ok(());
+        }
+        let used: HashSet<PathBuf> = media_references(cards, directory)?
+            .into_iter()
+            .filter_map(|reference| reference.resolved)
+            .map(|path| normalize(&path))
+            .collect();
+        for path in media_files(directory)? {
+            let relative = normalize(path.strip_prefix(directory).unwrap_or(&path));
+            if !used.contains(&relative) {
+                self.report(
+                    Rule::UnusedMedia,
+                    &path,
+                    1,
+                    "Media file is not used by any card.",
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn report_card(&mut self, rule: Rule, card: &Card, message: impl Into<String>) {
+        let (start, _) = card.range();
+        let suppressed = self.suppressions.get(card.file_path()).is_some_and(|s| {
+            s.iter()
+                .any(|s| s.card_start == Some(start) && s.rules.contains(&rule))
+        });
+        if !suppressed {
+            self.report(rule, card.file_path(), start + 1, message);
+        }
+    }
+
+    fn report(&mut self, rule: Rule, file: &Path, line: usize, message: impl Into<String>) {
+        if self.allowed.contains(&rule) {
+            return;
+        }
+        let severity = if self.denied.contains(&rule) {
+            Severity::Error
+        } else {
+            Severity::Warning
+        };
+        self.diagnostics
+            .push(Diagnostic::new(severity, file, line, 1, message).with_rule(rule.name()));
+    }
+}
+
+/// The texts of a card, without its note.
+fn card_texts(card: &Card) -> Vec<&str> {
+    match card.content() {
+        CardContent::Basic { question, answer } => vec![question.as_str(), answer.as_str()],
+        CardContent::Cloze { text, .. } => vec![text.as_str()],
+        CardContent::List { title, items, .. } => {
+            let mut texts = vec![title.as_str()];
+            texts.extend(items.iter().map(String::as_str));
+            texts
+        }
+    }
+}
+
+/// Whether the text has an odd number of `$` delimiters. Escaped dollar signs
+/// and code are skipped.
+fn has_unbalanced_math(text: &str) -> bool {
+    let mut in_block = false;
+    let mut count = 0;
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        let lowercase = trimmed.to_lowercase();
+        if trimmed.starts_with("```")
+            || trimmed.starts_with("~~~")
+            || lowercase.starts_with("#+begin_")
+            || lowercase.starts_with("#+end_")
+        {
+            in_block = !in_block;
+            continue;
+        }
+        if in_block {
+            continue;
+        }
+        // Outside of code spans, which are between pairs of backticks.
+        for span in line.split('`').step_by(2) {
+            let mut escaped = false;
+            for c in span.chars() {
+                match c {
+                    '\\' => {
+                        escaped = !escaped;
+                        continue;
+                    }
+                    '$' if !escaped => count += 1,
+                    _ => {}
+                }
+                escaped = false;
+            }
+        }
+    }
+    count % 2 == 1
+}
+
+/// The location of a card, for messages.
+fn location(card: &Card) -> String {
+    let name = card
+        .file_path()
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    format!("{name}:{}", card.range().0 + 1)
+}
+
+/// Remove `.` components, so paths can be compared.
+fn normalize(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|c| *c != Component::CurDir)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::write;
+
+    use tempfile::tempdir;
+
+    use super::*;
+
+    fn summary(directory: &Path) -> Fallible<Vec<(String, usize, Severity, &'static str)>> {
+        Ok(collect_lints(directory, HashVersion::Raw)?
+            .into_iter()
+            .map(|d| {
+                let file = d.file.strip_prefix(directory).unwrap();
+                let rule = d.rule.unwrap_or_default();
+                (file.display().to_string(), d.line, d.severity, rule)
+            })
+            .collect())
+    }
+
+    fn warning(
+        file: &str,
+        line: usize,
+        rule: &'static str,
+    ) -> (String, usize, Severity, &'static str) {
+        (file.to_string(), line, Severity::Warning, rule)
+    }
+
+    #[test]
+    fn test_rules() -> Fallible<()> {
+        let directory = tempdir()?;
+        let root = directory.path();
+        write(root.join(CONFIG_FILE), "[lint]\nmax-answer-length = 20\n")?;
+        write(
+            root.join("a.md"),
+            "Q: Short?\nA: Yes.\n\nQ: Long?\nA: This answer is far too long.\n\nC: [Most of this text] is hidden.\n\nQ: Also short?\nA: Yes.\n\nQ: Price?\nA: $5 or `$6`, and \\$7.\n\nQ: Math?\nA: $x$ and $$y$$\n\nQ: Cat?\nA: ![](cat.png)",
+        )?;
+        write(root.join("b.md"), "Q: Short?\nA: Yes.")?;
+        write(root.join("empty.md"), "Just prose.")?;
+        write(root.join("cat.png"), "")?;
+        write(root.join("dog.png"), "")?;
+        assert_eq!(
+            summary(root)?,
+            vec![
+                warning("a.md", 4, "long-answer"),
+                warning("a.md", 7, "large-cloze"),
+                warning("a.md", 9, "same-answer"),
+                warning("a.md", 12, "unbalanced-math"),
+                warning("b.md", 1, "duplicate-card"),
+                warning("dog.png", 1, "unused-media"),
+                warning("empty.md", 1, "empty-deck"),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_included_cards_are_not_duplicates() -> Fallible<()> {
+        let directory = tempdir()?;
+        let root = directory.path();
+        write(root.join("a.md"), "Q: a\nA: a")?;
+        write(root.join("b.md"), "!include a.md")?;
+        assert_eq!(summary(root)?, vec![]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_suppression() -> Fallible<()> {
+        let directory = tempdir()?;
+        let root = directory.path();
+        write(
+            root.join(CONFIG_FILE),
+            "[lint]\nmax-answer-length = 5\nallow = [\"unused-media\"]\ndeny = [\"same-answer\"]\n",
+        )?;
+        write(
+            root.join("a.md"),
+            "Q: a\nA: Too long.\n\n!lint-allow long-answer, large-cloze\nQ: b\nA: Too long.\n\n!lint-allow same-answer, no-such-rule\nQ: c\nA: Too long.",
+        )?;
+        write(
+            root.join("b.org"),
+            "* d? :card:\n:PROPERTIES:\n:LINT_ALLOW: long-answer\n:END:\nQuite long.\n* e? :card:\nAlso quite long.",
+        )?;
+        write(root.join("empty.md"), "!lint-allow empty-deck")?;
+        write(root.join("cat.png"), "")?;
+        assert_eq!(
+            summary(root)?,
+            vec![
+                warning("a.md", 1, "long-answer"),
+                ("a.md".to_string(), 5, Severity::Error, "same-answer"),
+                warning("a.md", 8, ""),
+                warning("a.md", 9, "long-answer"),
+                warning("b.org", 6, "long-answer"),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_rule_in_config() -> Fallible<()> {
+        let directory = tempdir()?;
+        write(
+            directory.path().join(CONFIG_FILE),
+            "[lint]\ndeny = [\"long-answers\"]\n",
+        )?;
+        assert!(collect_lints(directory.path(), HashVersion::Raw).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_unbalanced_math() {
+        assert!(!has_unbalanced_math("$x$ and $$y$$"));
+        assert!(has_unbalanced_math("$x"));
+        assert!(!has_unbalanced_math("It costs \\$5."));
+        assert!(!has_unbalanced_math("Run `echo $HOME`."));
+        assert!(!has_unbalanced_math("```sh\necho $HOME\n```"));
+        assert!(has_unbalanced_math("$a$ $b"));
+    }
+}
@@ -12,20 +12,25 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod cache;
 mod cli;
 mod cmd;
 mod collection;
+mod config;
 mod db;
 mod error;
+mod format;
 mod fsrs;
 #[cfg(test)]
 mod helper;
+mod lint;
 mod markdown;
 mod media;
 mod parser;
 mod rng;
 mod types;
 mod utils;
+mod walk;
 
 use std::process::ExitCode;
 
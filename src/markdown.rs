@@ -17,7 +17,9 @@ use pulldown_cmark::Event;
 use pulldown_cmark::Options;
 use pulldown_cmark::Parser;
 use pulldown_cmark::Tag;
+use pulldown_cmark::TagEnd;
 use pulldown_cmark::html::push_html;
+use unicode_normalization::UnicodeNormalization;
 
 use crate::error::ErrorReport;
 use crate::error::Fallible;
@@ -25,7 +27,9 @@ use crate::media::resolve::MediaResolver;
 
 const AUDIO_EXTENSIONS: [&str; 3] = ["mp3", "wav", "ogg"];
 
-fn is_audio_file(url: &str) -> bool {
+pub const IMAGE_EXTENSIONS: [&str; 8] = ["png", "jpg", "jpeg", "gif", "svg", "webp", "bmp", "avif"];
+
+pub fn is_audio_file(url: &str) -> bool {
     if let Some(ext) = url.split('.').next_back() {
         AUDIO_EXTENSIONS.contains(&ext)
     } else {
@@ -94,7 +98,58 @@ pub fn markdown_to_html_inline(config: &MarkdownRenderConfig, markdown: &str) ->
     }
 }
 
-fn modify_url(url: &str, config: &MarkdownRenderConfig) -> Fallible<String> {
+/// Reduce Markdown text to a canonical form, for hashing.
+///
+/// The text is converted to Unicode NFC and rendered to HTML, with soft line
+/// breaks turned into spaces and runs of whitespace collapsed everywhere except
+/// in code blocks. Edits that don't change how the text reads, like re-wrapping
+/// a paragraph or writing `_foo_` instead of `*foo*`, give the same output.
+pub fn normalize_markdown(markdown: &str) -> String {
+    let markdown: String = markdown.nfc().collect();
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_MATH);
+    let parser = Parser::new_ext(&markdown, options);
+    let mut in_code_block = false;
+    let events = parser.map(|event| match event {
+        Event::Start(Tag::CodeBlock(_)) => {
+            in_code_block = true;
+            event
+        }
+        Event::End(TagEnd::CodeBlock) => {
+            in_code_block = false;
+            event
+        }
+        Event::Text(text) if !in_code_block => Event::Text(collapse_whitespace(&text).into()),
+        Event::InlineMath(math) => Event::InlineMath(collapse_whitespace(&math).into()),
+        Event::DisplayMath(math) => Event::DisplayMath(collapse_whitespace(&math).into()),
+        Event::SoftBreak => Event::Text(" ".into()),
+        _ => event,
+    });
+    let mut html_output: String = String::new();
+    push_html(&mut html_output, events);
+    html_output.trim().to_string()
+}
+
+/// Replace every run of whitespace with a single space.
+fn collapse_whitespace(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut last_was_space = false;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            if !last_was_space {
+                result.push(' ');
+            }
+            last_was_space = true;
+        } else {
+            result.push(c);
+            last_was_space = false;
+        }
+    }
+    result
+}
+
+pub fn modify_url(url: &str, config: &MarkdownRenderConfig) -> Fallible<String> {
     let port = config.port;
     let path: String = config
         .resolver
@@ -160,4 +215,39 @@ mod tests {
         assert_eq!(html, "<h1>Foo</h1>\n");
         Ok(())
     }
+
+    #[test]
+    fn test_normalize_markdown_line_wrapping() {
+        let a = "The quick brown fox\njumps over the lazy dog.";
+        let b = "The quick brown fox jumps\n  over the   lazy dog.";
+        assert_eq!(normalize_markdown(a), normalize_markdown(b));
+        assert_ne!(
+            normalize_markdown(a),
+            normalize_markdown("The quick brown fox.\n\nJumps over the lazy dog.")
+        );
+    }
+
+    #[test]
+    fn test_normalize_markdown_emphasis() {
+        assert_eq!(normalize_markdown("*foo*"), normalize_markdown("_foo_"));
+        assert_eq!(normalize_markdown("**foo**"), normalize_markdown("__foo__"));
+        assert_ne!(normalize_markdown("*foo*"), normalize_markdown("**foo**"));
+    }
+
+    #[test]
+    fn test_normalize_markdown_unicode() {
+        // "é" as a single code point, and as "e" followed by a combining acute
+        // accent.
+        assert_eq!(
+            normalize_markdown("caf\u{e9}"),
+            normalize_markdown("cafe\u{301}")
+        );
+    }
+
+    #[test]
+    fn test_normalize_markdown_code_block() {
+        let a = "```\nfn  main() {}\n```";
+        let b = "```\nfn main() {}\n```";
+        assert_ne!(normalize_markdown(a), normalize_markdown(b));
+    }
 }
@@ -13,7 +13,11 @@
 // limitations under the License.
 
 use std::borrow::Cow;
+use std::collections::HashMap;
 use std::collections::HashSet;
+use std::collections::hash_map::Entry;
+use std::fs::read_to_string;
+use std::iter::once;
 use std::path::Path;
 use std::path::PathBuf;
 
@@ -28,6 +32,8 @@ use crate::media::resolve::MediaResolver;
 use crate::media::resolve::MediaResolverBuilder;
 use crate::types::card::Card;
 use crate::types::card::CardContent;
+use crate::types::diagnostic::Diagnostic;
+use crate::types::diagnostic::Severity;
 
 /// Represents a missing media file reference.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
@@ -35,10 +41,44 @@ pub struct MissingMedia {
     pub file_path: String,
     pub card_file: PathBuf,
     pub card_lines: (usize, usize),
+    /// The path as written in the card's text.
+    pub reference: String,
+}
+
+impl MissingMedia {
+    /// Turn the missing media into a diagnostic, pointing at the first place
+    /// the path appears in the card's lines.
+    pub fn to_diagnostic(&self) -> Fallible<Diagnostic> {
+        let text = read_to_string(&self.card_file)?;
+        Ok(self.to_diagnostic_in(&text))
+    }
+
+    /// Like `to_diagnostic`, but with the text of the card's file given, as
+    /// when it is being edited.
+    pub fn to_diagnostic_in(&self, text: &str) -> Diagnostic {
+        let (start, end) = self.card_lines;
+        let location = text
+            .lines()
+            .enumerate()
+            .skip(start)
+            .take(end + 1 - start)
+            .find_map(|(line_num, line)| {
+                line.find(&self.reference)
+                    .map(|byte| (line_num + 1, line[..byte].chars().count() + 1))
+            });
+        let (line, column) = location.unwrap_or((start + 1, 1));
+        Diagnostic::new(
+            Severity::Error,
+            &self.card_file,
+            line,
+            column,
+            format!("Missing media file '{}'.", self.file_path),
+        )
+    }
 }
 
 /// Extract all media file paths from markdown text.
-fn extract_media_paths(markdown: &str) -> Vec<String> {
+pub fn extract_media_paths(markdown: &str) -> Vec<String> {
     let parser = Parser::new(markdown);
     let mut paths = Vec::new();
 
@@ -53,63 +93,104 @@ fn extract_media_paths(markdown: &str) -> Vec<String> {
 
 /// Validate that all media files referenced in cards exist.
 pub fn validate_media_files(cards: &[Card], base_dir: &Path) -> Fallible<()> {
+    let missing = find_missing_media(cards, base_dir)?;
+    if !missing.is_empty() {
+        // Build error message.
+        let mut msg = String::from("Missing media files referenced in cards:\n");
+        for m in missing {
+            msg.push_str(&format!(
+                "  - {} (referenced in {}:{})\n",
+                m.file_path,
+                m.card_file.display(),
+                m.card_lines.0
+            ));
+        }
+
+        return Err(ErrorReport::new(&msg));
+    }
+
+    Ok(())
+}
+
+/// A media file referenced in a card.
+pub struct MediaReference<'a> {
+    pub card: &'a Card,
+    /// The path as written in the card's text.
+    pub path: String,
+    /// The file the path resolves to, or `None` if it doesn't exist.
+    pub resolved: Option<PathBuf>,
+}
+
+/// Find every media file referenced in the cards, in order.
+pub fn media_references<'a>(
+    cards: &'a [Card],
+    base_dir: &Path,
+) -> Fallible<Vec<MediaReference<'a>>> {
     let base_dir = base_dir.to_path_buf();
-    let mut missing = HashSet::new();
+    let mut references = Vec::new();
+    // Cards from the same file share a resolver.
+    let mut resolvers: HashMap<&PathBuf, MediaResolver> = HashMap::new();
 
     for card in cards {
-        let resolver: MediaResolver = MediaResolverBuilder::new()
-            .with_collection_path(base_dir.clone())?
-            .with_deck_path(card.relative_file_path(&base_dir)?)?
-            .build()?;
+        let resolver: &MediaResolver = match resolvers.entry(card.file_path()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(
+                MediaResolverBuilder::new()
+                    .with_collection_path(base_dir.clone())?
+                    .with_deck_path(card.relative_file_path(&base_dir)?)?
+                    .build()?,
+            ),
+        };
 
         // Extract markdown content from the card.
         //
         // TODO: perhaps this should be lifted to a method of the `CardContent`
         // enum.
-        let markdown_texts = match card.content() {
+        let mut markdown_texts = match card.content() {
             CardContent::Basic { question, answer } => vec![question.as_str(), answer.as_str()],
             CardContent::Cloze { text, .. } => vec![text.as_str()],
+            CardContent::List { title, items, .. } => once(title.as_str())
+                .chain(items.iter().map(String::as_str))
+                .collect(),
         };
+        markdown_texts.extend(card.note());
 
         for markdown in markdown_texts {
-            for path in extract_media_paths(markdown) {
-                // Try to resolve the path using MediaResolver.
-                match resolver.resolve(&path) {
-                    Ok(_) => {}
-                    Err(_) => {
-                        // Decode percent-encoded characters for better error display.
-                        let decoded_path: Cow<str> = percent_decode_str(&path).decode_utf8_lossy();
-                        missing.insert(MissingMedia {
-                            file_path: decoded_path.into_owned(),
-                            card_file: card.file_path().clone(),
-                            card_lines: card.range(),
-                        });
-                    }
-                }
+            for path in card.format().media_paths(markdown) {
+                let resolved = resolver.resolve(&path).ok();
+                references.push(MediaReference {
+                    card,
+                    path,
+                    resolved,
+                });
             }
         }
     }
 
-    if !missing.is_empty() {
-        // Sort missing files for consistent error messages.
-        let mut missing: Vec<MissingMedia> = missing.into_iter().collect();
-        missing.sort();
+    Ok(references)
+}
 
-        // Build error message.
-        let mut msg = String::from("Missing media files referenced in cards:\n");
-        for m in missing {
-            msg.push_str(&format!(
-                "  - {} (referenced in {}:{})\n",
-                m.file_path,
-                m.card_file.display(),
-                m.card_lines.0
-            ));
-        }
+/// Find the media files referenced in cards that don't exist, sorted.
+pub fn find_missing_media(cards: &[Card], base_dir: &Path) -> Fallible<Vec<MissingMedia>> {
+    let mut missing = HashSet::new();
 
-        return Err(ErrorReport::new(&msg));
+    for reference in media_references(cards, base_dir)? {
+        if reference.resolved.is_none() {
+            // Decode percent-encoded characters for better error display.
+            let decoded_path: Cow<str> = percent_decode_str(&reference.path).decode_utf8_lossy();
+            missing.insert(MissingMedia {
+                file_path: decoded_path.into_owned(),
+                card_file: reference.card.file_path().clone(),
+                card_lines: reference.card.range(),
+                reference: reference.path.clone(),
+            });
+        }
     }
 
-    Ok(())
+    // Sort missing files for consistent error messages.
+    let mut missing: Vec<MissingMedia> = missing.into_iter().collect();
+    missing.sort();
+    Ok(missing)
 }
 
 #[cfg(test)]
@@ -119,6 +200,7 @@ mod tests {
 
     use super::*;
     use crate::parser::Parser as CardParser;
+    use crate::types::card_hash::HashVersion;
 
     #[test]
     fn test_extract_media_paths() {
@@ -160,7 +242,7 @@ mod tests {
 
         // Parse cards from markdown with missing media references
         let markdown = "Q: What is this image?\n\n![](missing_image.jpg)\n\nA: Unknown\n\nQ: What is this audio?\nA: ![](missing_audio.mp3)";
-        let parser = CardParser::new("test_deck".to_string(), card_file.clone());
+        let parser = CardParser::new("test_deck".to_string(), card_file.clone(), HashVersion::Raw);
         let cards = parser.parse(markdown)?;
 
         // Validate media files - should return an error
@@ -197,7 +279,7 @@ mod tests {
 
         // Parse cards from markdown with existing media reference.
         let markdown = "Q: What is this image?\n\n![](existing_image.jpg)\n\nA: A test image";
-        let parser = CardParser::new("test_deck".to_string(), card_file.clone());
+        let parser = CardParser::new("test_deck".to_string(), card_file.clone(), HashVersion::Raw);
         let cards = parser.parse(markdown)?;
 
         // Validate media files - should succeed.
@@ -221,7 +303,7 @@ mod tests {
 
         // Parse cloze card with missing media reference.
         let markdown = "C: The capital of [France] is ![](@/paris.jpg)";
-        let parser = CardParser::new("test_deck".to_string(), card_file.clone());
+        let parser = CardParser::new("test_deck".to_string(), card_file.clone(), HashVersion::Raw);
         let cards: Vec<Card> = parser.parse(markdown)?;
 
         // Validate media files - should fail.
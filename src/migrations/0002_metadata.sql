@@ -0,0 +1,5 @@
+-- Collection-wide settings, as key-value pairs.
+create table metadata (
+    key text primary key,
+    value text not null
+) strict;
@@ -0,0 +1,8 @@
+-- Suspended cards are left out of drilling sessions.
+create table suspended_cards (
+    card_hash text primary key
+        references cards (card_hash)
+        on update cascade
+        on delete cascade,
+    suspended_at text not null
+) strict;
@@ -0,0 +1,9 @@
+-- The cards parsed from each deck file, keyed by the file's path, so unchanged
+-- files need not be parsed again.
+create table parse_cache (
+    file_path text primary key,
+    content_hash text not null,
+    hash_version text not null,
+    parser_version text not null,
+    cards text not null
+) strict;
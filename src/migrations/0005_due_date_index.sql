@@ -0,0 +1,2 @@
+-- Speeds up finding the cards due on a given date.
+create index cards_due_date on cards (due_date);
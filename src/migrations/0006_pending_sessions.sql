@@ -0,0 +1,30 @@
+-- A journal of the session in progress. Reviews are written here as they
+-- happen, and moved to `sessions` and `reviews` when the session finishes, so
+-- an interrupted session can be recovered or resumed.
+create table pending_sessions (
+    pending_session_id integer primary key,
+    started_at text not null,
+    -- The hashes of the cards left to drill, in order, as a JSON array.
+    queue text not null,
+    -- The number of cards the session started with.
+    total_cards integer not null
+) strict;
+
+create table pending_reviews (
+    pending_review_id integer primary key,
+    pending_session_id integer not null
+        references pending_sessions (pending_session_id)
+        on update cascade
+        on delete cascade,
+    card_hash text not null
+        references cards (card_hash)
+        on update cascade
+        on delete cascade,
+    reviewed_at text not null,
+    grade text not null,
+    stability real not null,
+    difficulty real not null,
+    interval_raw real not null,
+    interval_days integer not null,
+    due_date text not null
+) strict;
@@ -0,0 +1,4 @@
+-- How long it took to answer the card, in milliseconds, when the frontend
+-- reports it.
+alter table reviews add column duration_ms integer;
+alter table pending_reviews add column duration_ms integer;
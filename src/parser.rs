@@ -17,16 +17,22 @@ use std::error::Error;
 use std::fmt::Display;
 use std::fmt::Formatter;
 use std::fs::read_to_string;
+use std::path::Path;
 use std::path::PathBuf;
 
 use serde::Deserialize;
-use walkdir::WalkDir;
 
 use crate::error::ErrorReport;
 use crate::error::Fallible;
+use crate::format::DeckFormat;
 use crate::types::aliases::DeckName;
 use crate::types::card::Card;
 use crate::types::card::CardContent;
+use crate::types::card_hash::CardHash;
+use crate::types::card_hash::HashVersion;
+use crate::types::diagnostic::Diagnostic;
+use crate::types::diagnostic::Severity;
+use crate::walk::deck_files;
 
 /// Metadata that can be specified at the top of a deck file.
 #[derive(Debug, Deserialize)]
@@ -94,44 +100,175 @@ fn extract_frontmatter(text: &str) -> Fallible<(DeckMetadata, &str)> {
     Ok((metadata, content))
 }
 
-/// Parses all Markdown files in the given directory.
-pub fn parse_deck(directory: &PathBuf) -> Fallible<Vec<Card>> {
+/// Parses all the deck files in the given directory (see `deck_files`), and
+/// collects every problem as a diagnostic instead of stopping at the first.
+pub fn check_deck(
+    directory: &Path,
+    hash_version: HashVersion,
+) -> Fallible<(Vec<Card>, Vec<Diagnostic>)> {
     let mut all_cards = Vec::new();
-    for entry in WalkDir::new(directory) {
-        let entry = entry?;
-        let path = entry.path();
-        if path.is_file() && path.extension().is_some_and(|ext| ext == "md") {
-            let text = read_to_string(path)?;
-
-            // Extract frontmatter and get custom deck name if specified
-            let (metadata, content) = extract_frontmatter(&text)?;
-
-            let deck_name: DeckName = metadata.name.unwrap_or_else(|| {
-                path.file_stem()
-                    .and_then(|os_str| os_str.to_str())
-                    .unwrap_or("None")
-                    .to_string()
-            });
-
-            let parser = Parser::new(deck_name, path.to_path_buf());
-            let cards = parser.parse(content)?;
-            all_cards.extend(cards);
-        }
+    let mut diagnostics = Vec::new();
+    for deck in read_deck_files(directory, hash_version)? {
+        all_cards.extend(deck.cards);
+        diagnostics.extend(deck.diagnostics);
     }
+    sort_cards(&mut all_cards);
+    Ok((all_cards, diagnostics))
+}
+
+/// A deck file, and what was read from it.
+pub struct DeckFile {
+    pub path: PathBuf,
+    pub format: DeckFormat,
+    pub text: String,
+    /// The cards in the file, in order, including duplicates.
+    pub cards: Vec<Card>,
+    pub diagnostics: Vec<Diagnostic>,
+}
 
+/// Read every deck file in the given directory, collecting the problems in
+/// each one.
+pub fn read_deck_files(directory: &Path, hash_version: HashVersion) -> Fallible<Vec<DeckFile>> {
+    let mut decks = Vec::new();
+    for path in deck_files(directory)? {
+        let Some(format) = DeckFormat::from_path(&path) else {
+            continue;
+        };
+        let (text, cards, diagnostics) = match read_to_string(&path) {
+            Ok(text) => {
+                let (cards, errors) = format.check_file(&path, &text, hash_version);
+                let diagnostics = errors.into_iter().map(Diagnostic::from).collect();
+                (text, cards, diagnostics)
+            }
+            Err(e) => {
+                let message = format!("Failed to read deck file: {e}.");
+                let diagnostic = Diagnostic::new(Severity::Error, &path, 1, 1, message);
+                (String::new(), Vec::new(), vec![diagnostic])
+            }
+        };
+        decks.push(DeckFile {
+            path,
+            format,
+            text,
+            cards,
+            diagnostics,
+        });
+    }
+    Ok(decks)
+}
+
+/// Sort cards by hash and remove duplicates.
+pub fn sort_cards(cards: &mut Vec<Card>) {
     // Cards are sorted by their hash to make subsequent code more
     // deterministic.
-    all_cards.sort_by_key(|c| c.hash());
+    cards.sort_by_key(|c| c.hash());
 
     // Remove duplicates.
-    all_cards.dedup_by_key(|c| c.hash());
+    cards.dedup_by_key(|c| c.hash());
+}
 
-    Ok(all_cards)
+/// Parses the cards in a Markdown deck file, collecting every error rather
+/// than stopping at the first one.
+pub fn check_markdown_deck(
+    path: &Path,
+    text: &str,
+    hash_version: HashVersion,
+) -> (Vec<Card>, Vec<ParserError>) {
+    let (deck_name, content, first_line) = match markdown_content(path, text) {
+        Ok(result) => result,
+        Err(e) => {
+            let error = ParserError::new(e.to_string(), path.to_path_buf(), 0);
+            return (Vec::new(), vec![error]);
+        }
+    };
+    let parser = Parser::new(deck_name, path.to_path_buf(), hash_version);
+    parser.parse_all(content, first_line)
+}
+
+/// The cards in a Markdown deck file that the legacy grammar reads
+/// differently, because their text has a line that now starts a note or a
+/// list card. The cards are returned as the legacy grammar reads them, so
+/// they have their old hashes.
+pub fn reinterpreted_markdown_cards(
+    path: &Path,
+    text: &str,
+    hash_version: HashVersion,
+) -> Vec<Card> {
+    let Ok((deck_name, content, first_line)) = markdown_content(path, text) else {
+        return Vec::new();
+    };
+    let parser = Parser::new(deck_name, path.to_path_buf(), hash_version);
+    let (current, _) = parser.parse_all(content, first_line);
+    let current: HashSet<CardHash> = current.iter().map(|card| card.hash()).collect();
+    let (legacy, _) = parser
+        .with_grammar(Grammar::Legacy)
+        .parse_all(content, first_line);
+    legacy
+        .into_iter()
+        .filter(|card| !current.contains(&card.hash()))
+        .collect()
+}
+
+/// Split a Markdown deck file into its deck name and the content after the
+/// frontmatter, along with the line number the content starts on.
+fn markdown_content<'a>(path: &Path, text: &'a str) -> Fallible<(DeckName, &'a str, usize)> {
+    // Extract frontmatter and get custom deck name if specified
+    let (metadata, content) = extract_frontmatter(text)?;
+    // Line numbers count from the top of the file, frontmatter included.
+    let first_line = text[..text.len() - content.len()].lines().count();
+
+    let deck_name: DeckName = metadata.name.unwrap_or_else(|| {
+        path.file_stem()
+            .and_then(|os_str| os_str.to_str())
+            .unwrap_or("None")
+            .to_string()
+    });
+    Ok((deck_name, content, first_line))
+}
+
+/// The card syntax a parser accepts.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Grammar {
+    Current,
+    /// The grammar before notes (`N:`, `Extra:`) and list cards (`L:`, `L+:`),
+    /// where those lines are ordinary text. Cards written against it can read
+    /// differently now, with a different hash.
+    Legacy,
 }
 
 pub struct Parser {
     deck_name: DeckName,
     file_path: PathBuf,
+    hash_version: HashVersion,
+    grammar: Grammar,
+    /// The chain of files (and sections) that led to this one, ending with
+    /// this one. Used to detect include cycles.
+    include_stack: Vec<IncludeTarget>,
+}
+
+/// A file, or a section of a file, that can be included into a deck.
+#[derive(Clone, PartialEq, Eq)]
+struct IncludeTarget {
+    path: PathBuf,
+    section: Option<String>,
+}
+
+impl IncludeTarget {
+    fn new(path: &Path, section: Option<&str>) -> Self {
+        IncludeTarget {
+            path: path.canonicalize().unwrap_or_else(|_| path.to_path_buf()),
+            section: section.map(|s| s.to_string()),
+        }
+    }
+}
+
+impl Display for IncludeTarget {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.section {
+            Some(section) => write!(f, "{}#{section}", self.path.display()),
+            None => write!(f, "{}", self.path.display()),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -142,7 +279,7 @@ pub struct ParserError {
 }
 
 impl ParserError {
-    fn new(message: impl Into<String>, file_path: PathBuf, line_num: usize) -> Self {
+    pub fn new(message: impl Into<String>, file_path: PathBuf, line_num: usize) -> Self {
         ParserError {
             message: message.into(),
             file_path,
@@ -178,10 +315,40 @@ enum State {
     },
     /// Reading a cloze card (C:)
     ReadingCloze { text: String, start_line: usize },
+    /// Reading a list card (L: or L+:)
+    ReadingList {
+        list: PendingList,
+        start_line: usize,
+    },
+    /// Reading a note (N: or Extra:) attached to a card.
+    ReadingNote {
+        card: PendingCard,
+        note: String,
+        start_line: usize,
+    },
     /// End state.
     End,
 }
 
+/// A card whose text has been read, waiting for its note to end.
+enum PendingCard {
+    Basic { question: String, answer: String },
+    Cloze { text: String },
+    List(PendingList),
+}
+
+/// A list card being read.
+struct PendingList {
+    /// The text before the first item.
+    title: String,
+    /// The items read so far.
+    items: Vec<String>,
+    /// Whether the items are numbered. Set by the first item.
+    ordered: bool,
+    /// Whether to also generate a card that asks for the whole list.
+    whole: bool,
+}
+
 enum Line {
     /// A line like `Q: <text>`.
     StartQuestion(String),
@@ -189,6 +356,11 @@ enum Line {
     StartAnswer(String),
     /// A line like `C: <text>`.
     StartCloze(String),
+    /// A line like `N: <text>` or `Extra: <text>`.
+    StartNote(String),
+    /// A line like `L: <text>`, or `L+: <text>` for a list card that also asks
+    /// for the whole list.
+    StartList { text: String, whole: bool },
     /// A line that's just `---` (flashcard separator).
     Separator,
     /// Any other line.
@@ -198,13 +370,30 @@ enum Line {
 }
 
 impl Line {
-    fn read(line: &str) -> Self {
+    fn read(line: &str, grammar: Grammar) -> Self {
+        if grammar == Grammar::Legacy
+            && (is_note(line) || line.starts_with("L:") || line.starts_with("L+:"))
+        {
+            return Line::Text(line.to_string());
+        }
         if is_question(line) {
             Line::StartQuestion(trim(line))
         } else if is_answer(line) {
             Line::StartAnswer(trim(line))
         } else if is_cloze(line) {
             Line::StartCloze(trim(line))
+        } else if is_note(line) {
+            Line::StartNote(trim_note(line))
+        } else if let Some(text) = line.strip_prefix("L:") {
+            Line::StartList {
+                text: text.trim().to_string(),
+                whole: false,
+            }
+        } else if let Some(text) = line.strip_prefix("L+:") {
+            Line::StartList {
+                text: text.trim().to_string(),
+                whole: true,
+            }
         } else if is_separator(line) {
             Line::Separator
         } else {
@@ -225,7 +414,108 @@ fn is_cloze(line: &str) -> bool {
     line.starts_with("C:")
 }
 
-fn is_separator(line: &str) -> bool {
+fn is_note(line: &str) -> bool {
+    line.starts_with("N:") || line.starts_with("Extra:")
+}
+
+/// If the line is a Markdown list item, return whether it is numbered, and
+/// the text of the item.
+fn list_item(line: &str) -> Option<(bool, &str)> {
+    for marker in ["- ", "* ", "+ "] {
+        if let Some(text) = line.strip_prefix(marker) {
+            return Some((false, text));
+        }
+    }
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if digits > 0 {
+        let rest = &line[digits..];
+        if let Some(text) = rest.strip_prefix(". ").or_else(|| rest.strip_prefix(") ")) {
+            return Some((true, text));
+        }
+    }
+    None
+}
+
+/// If the line is an include directive, like `!include path.md#section`,
+/// return its target.
+pub fn include_directive(line: &str) -> Option<&str> {
+    line.strip_prefix("!include ").map(|target| target.trim())
+}
+
+/// If the line is a `!lint-allow` directive, return the rules it lists.
+pub fn lint_allow_directive(line: &str) -> Option<&str> {
+    line.strip_prefix("!lint-allow ").map(|rules| rules.trim())
+}
+
+/// If the line is a Markdown heading, return its level and text.
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.len() - line.trim_start_matches('#').len();
+    if (1..=6).contains(&level) {
+        line[level..]
+            .strip_prefix(' ')
+            .map(|text| (level, text.trim().trim_end_matches('#').trim()))
+    } else {
+        None
+    }
+}
+
+/// Turn a heading into an anchor, the way GitHub does: lowercase, with spaces
+/// replaced by hyphens and punctuation removed.
+fn slugify(text: &str) -> String {
+    text.to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Return the lines of the section with the given name, from the line after
+/// its heading to the next heading of the same or a higher level. The section
+/// can be named by its heading text or by its anchor.
+fn section_lines<'a>(lines: &[(usize, &'a str)], section: &str) -> Option<Vec<(usize, &'a str)>> {
+    let mut in_code = false;
+    let mut level = None;
+    let mut result = Vec::new();
+    for (line_num, line) in lines {
+        if line.starts_with("```") || line.starts_with("~~~") {
+            in_code = !in_code;
+        }
+        let heading = if in_code { None } else { heading(line) };
+        match (level, heading) {
+            (None, Some((l, text))) => {
+                if text.eq_ignore_ascii_case(section) || slugify(text) == section {
+                    level = Some(l);
+                }
+            }
+            (Some(current), Some((l, _))) if l <= current => break,
+            (Some(_), _) => result.push((*line_num, *line)),
+            (None, None) => {}
+        }
+    }
+    level.map(|_| result)
+}
+
+/// Whether the line starts a card, ends one, or includes others. Parsing can
+/// resume from such a line after an error.
+fn starts_card(line: &str, grammar: Grammar) -> bool {
+    include_directive(line).is_some()
+        || matches!(
+            Line::read(line, grammar),
+            Line::StartQuestion(_) | Line::StartCloze(_) | Line::StartList { .. } | Line::Separator
+        )
+}
+
+/// If the line starts with a card tag, like `Q:`, return the tag.
+pub fn card_tag(line: &str) -> Option<&'static str> {
+    ["Q:", "A:", "C:", "N:", "Extra:", "L:", "L+:"]
+        .into_iter()
+        .find(|tag| line.starts_with(tag))
+}
+
+pub fn is_separator(line: &str) -> bool {
     line.trim() == "---"
 }
 
@@ -233,27 +523,51 @@ fn trim(line: &str) -> String {
     line[2..].trim().to_string()
 }
 
+fn trim_note(line: &str) -> String {
+    let tag_len = if line.starts_with("N:") {
+        "N:".len()
+    } else {
+        "Extra:".len()
+    };
+    line[tag_len..].trim().to_string()
+}
+
+impl PendingList {
+    fn new(title: String, whole: bool) -> Self {
+        PendingList {
+            title,
+            items: Vec::new(),
+            ordered: false,
+            whole,
+        }
+    }
+}
+
 impl Parser {
-    pub fn new(deck_name: DeckName, file_path: PathBuf) -> Self {
+    pub fn new(deck_name: DeckName, file_path: PathBuf, hash_version: HashVersion) -> Self {
+        let include_stack = vec![IncludeTarget::new(&file_path, None)];
         Parser {
             deck_name,
             file_path,
+            hash_version,
+            grammar: Grammar::Current,
+            include_stack,
         }
     }
 
-    /// Parse all the cards in the given text.
+    pub fn with_grammar(self, grammar: Grammar) -> Self {
+        Parser { grammar, ..self }
+    }
+
+    /// Parse all the cards in the given text, failing on the first error.
+    #[cfg(test)]
     pub fn parse(&self, text: &str) -> Result<Vec<Card>, ParserError> {
-        let mut cards = Vec::new();
-        let mut state = State::Start;
-        let lines: Vec<&str> = text.lines().collect();
-        let last_line = if lines.is_empty() { 0 } else { lines.len() - 1 };
-        for (line_num, line) in lines.iter().enumerate() {
-            let line = Line::read(line);
-            state = self.parse_line(state, line, line_num, &mut cards)?;
+        let (cards, errors) = self.parse_all(text, 0);
+        if let Some(error) = errors.into_iter().next() {
+            return Err(error);
         }
-        self.parse_line(state, Line::Eof, last_line, &mut cards)?;
 
-        let mut seen = HashSet::new();
+        let mut seen = std::collections::HashSet::new();
         let mut unique_cards = Vec::new();
         for card in cards {
             if seen.insert(card.hash()) {
@@ -263,6 +577,175 @@ impl Parser {
         Ok(unique_cards)
     }
 
+    /// Parse all the cards in the given text, whose first line is line
+    /// `first_line` of the file. Parsing carries on after an error, so every
+    /// error in the text is returned, along with the cards that could be read.
+    /// Duplicate cards are kept.
+    pub fn parse_all(&self, text: &str, first_line: usize) -> (Vec<Card>, Vec<ParserError>) {
+        let lines: Vec<(usize, &str)> = text
+            .lines()
+            .enumerate()
+            .map(|(line_num, line)| (line_num + first_line, line))
+            .collect();
+        self.parse_lines(&lines)
+    }
+
+    /// Parse the given lines, which are tagged with their line numbers. After
+    /// an error, lines are skipped until the start of the next card.
+    fn parse_lines(&self, lines: &[(usize, &str)]) -> (Vec<Card>, Vec<ParserError>) {
+        let mut cards = Vec::new();
+        let mut errors = Vec::new();
+        let mut state = State::Start;
+        let mut recovering = false;
+        let last_line = lines.last().map(|(line_num, _)| *line_num).unwrap_or(0);
+        for (line_num, line) in lines {
+            // Lint directives are not part of any card.
+            if lint_allow_directive(line).is_some() {
+                continue;
+            }
+            if recovering {
+                if !starts_card(line, self.grammar) {
+                    continue;
+                }
+                recovering = false;
+            }
+            state = match self.parse_text_line(state, line, *line_num, &mut cards, &mut errors) {
+                Ok(state) => state,
+                Err(error) => {
+                    let (error_line, message) = (error.line_num, error.message.clone());
+                    errors.push(error);
+                    // The error may come from finishing the previous card, in
+                    // which case the card that starts on this line can still
+                    // be read.
+                    let retry = starts_card(line, self.grammar).then(|| {
+                        self.parse_text_line(State::Start, line, *line_num, &mut cards, &mut errors)
+                    });
+                    match retry {
+                        Some(Ok(state)) => state,
+                        Some(Err(error)) => {
+                            // Don't report the same error twice.
+                            if error.line_num != error_line || error.message != message {
+                                errors.push(error);
+                            }
+                            recovering = true;
+                            State::Start
+                        }
+                        None => {
+                            recovering = true;
+                            State::Start
+                        }
+                    }
+                }
+            };
+        }
+        if let Err(error) = self.parse_line(state, Line::Eof, last_line, &mut cards) {
+            errors.push(error);
+        }
+        (cards, errors)
+    }
+
+    /// Parse a line of text, which may be an include directive.
+    fn parse_text_line(
+        &self,
+        state: State,
+        line: &str,
+        line_num: usize,
+        cards: &mut Vec<Card>,
+        errors: &mut Vec<ParserError>,
+    ) -> Result<State, ParserError> {
+        match include_directive(line) {
+            Some(target) => self.include(state, target, line_num, cards, errors),
+            None => self.parse_line(state, Line::read(line, self.grammar), line_num, cards),
+        }
+    }
+
+    /// Handle an include directive: finish the current card, then parse the
+    /// cards in the target. Included cards belong to this deck, but keep the
+    /// path and line numbers of the file they come from.
+    fn include(
+        &self,
+        state: State,
+        target: &str,
+        line_num: usize,
+        cards: &mut Vec<Card>,
+        errors: &mut Vec<ParserError>,
+    ) -> Result<State, ParserError> {
+        let error = |message: String| ParserError::new(message, self.file_path.clone(), line_num);
+        if let State::ReadingQuestion { .. } = state {
+            return Err(error(
+                "Found include directive while reading a question.".to_string(),
+            ));
+        }
+        let state = self.parse_line(state, Line::Separator, line_num, cards)?;
+
+        let (path, section) = match target.split_once('#') {
+            Some((path, section)) => (path, Some(section)),
+            None => (target, None),
+        };
+        if path.is_empty() {
+            return Err(error("Include directive without a file.".to_string()));
+        }
+        if DeckFormat::from_path(Path::new(path)) != Some(DeckFormat::Markdown) {
+            return Err(error(format!(
+                "Only Markdown files can be included, but found '{path}'."
+            )));
+        }
+        let path = self.file_path.parent().unwrap_or(Path::new("")).join(path);
+        let include_target = IncludeTarget::new(&path, section);
+        if self.include_stack.contains(&include_target) {
+            let chain: Vec<String> = self
+                .include_stack
+                .iter()
+                .chain([&include_target])
+                .map(|t| t.to_string())
+                .collect();
+            return Err(error(format!("Include cycle: {}.", chain.join(" -> "))));
+        }
+
+        let text = read_to_string(&path).map_err(|e| {
+            error(format!(
+                "Failed to read included file '{}': {e}.",
+                path.display()
+            ))
+        })?;
+        let (_, content) = extract_frontmatter(&text).map_err(|e| {
+            error(format!(
+                "Failed to read included file '{}': {e}",
+                path.display()
+            ))
+        })?;
+        // Line numbers count from the top of the file, frontmatter included.
+        let first_line = text[..text.len() - content.len()].lines().count();
+        let lines: Vec<(usize, &str)> = content
+            .lines()
+            .enumerate()
+            .map(|(line_num, line)| (line_num + first_line, line))
+            .collect();
+        let lines = match section {
+            Some(section) => section_lines(&lines, section).ok_or_else(|| {
+                error(format!(
+                    "Section '{section}' not found in '{}'.",
+                    path.display()
+                ))
+            })?,
+            None => lines,
+        };
+
+        let mut include_stack = self.include_stack.clone();
+        include_stack.push(include_target);
+        let parser = Parser {
+            deck_name: self.deck_name.clone(),
+            file_path: path,
+            hash_version: self.hash_version,
+            grammar: self.grammar,
+            include_stack,
+        };
+        let (included, included_errors) = parser.parse_lines(&lines);
+        cards.extend(included);
+        errors.extend(included_errors);
+        Ok(state)
+    }
+
     fn parse_line(
         &self,
         state: State,
@@ -285,6 +768,15 @@ impl Parser {
                     text,
                     start_line: line_num,
                 }),
+                Line::StartNote(_) => Err(ParserError::new(
+                    "Found note tag without a card.",
+                    self.file_path.clone(),
+                    line_num,
+                )),
+                Line::StartList { text, whole } => Ok(State::ReadingList {
+                    list: PendingList::new(text, whole),
+                    start_line: line_num,
+                }),
                 Line::Separator => Ok(State::Start),
                 Line::Text(_) => Ok(State::Start),
                 Line::Eof => Ok(State::End),
@@ -308,6 +800,16 @@ impl Parser {
                     self.file_path.clone(),
                     line_num,
                 )),
+                Line::StartNote(_) => Err(ParserError::new(
+                    "Found note tag while reading a question.",
+                    self.file_path.clone(),
+                    line_num,
+                )),
+                Line::StartList { .. } => Err(ParserError::new(
+                    "Found list tag while reading a question.",
+                    self.file_path.clone(),
+                    line_num,
+                )),
                 Line::Separator => Err(ParserError::new(
                     "Found flashcard separator while reading a question.",
                     self.file_path.clone(),
@@ -336,6 +838,8 @@ impl Parser {
                             self.file_path.clone(),
                             (start_line, line_num),
                             CardContent::new_basic(question, answer),
+                            None,
+                            self.hash_version,
                         );
                         cards.push(card);
                         // Start a new question.
@@ -356,6 +860,8 @@ impl Parser {
                             self.file_path.clone(),
                             (start_line, line_num),
                             CardContent::new_basic(question, answer),
+                            None,
+                            self.hash_version,
                         );
                         cards.push(card);
                         // Start reading a new cloze card.
@@ -371,11 +877,35 @@ impl Parser {
                             self.file_path.clone(),
                             (start_line, line_num),
                             CardContent::new_basic(question, answer),
+                            None,
+                            self.hash_version,
                         );
                         cards.push(card);
                         // Return to start state.
                         Ok(State::Start)
                     }
+                    Line::StartList { text, whole } => {
+                        // Finalize the previous card.
+                        let card = Card::new(
+                            self.deck_name.clone(),
+                            self.file_path.clone(),
+                            (start_line, line_num),
+                            CardContent::new_basic(question, answer),
+                            None,
+                            self.hash_version,
+                        );
+                        cards.push(card);
+                        // Start reading a new list card.
+                        Ok(State::ReadingList {
+                            list: PendingList::new(text, whole),
+                            start_line: line_num,
+                        })
+                    }
+                    Line::StartNote(text) => Ok(State::ReadingNote {
+                        card: PendingCard::Basic { question, answer },
+                        note: text,
+                        start_line,
+                    }),
                     Line::Text(text) => Ok(State::ReadingAnswer {
                         question,
                         answer: format!("{answer}\n{text}"),
@@ -388,6 +918,8 @@ impl Parser {
                             self.file_path.clone(),
                             (start_line, line_num),
                             CardContent::new_basic(question, answer),
+                            None,
+                            self.hash_version,
                         );
                         cards.push(card);
                         Ok(State::End)
@@ -398,7 +930,7 @@ impl Parser {
                 match line {
                     Line::StartQuestion(new_text) => {
                         // Finalize the previous cloze card.
-                        cards.extend(self.parse_cloze_cards(text, start_line, line_num)?);
+                        cards.extend(self.parse_cloze_cards(text, None, start_line, line_num)?);
                         // Start a new question card
                         Ok(State::ReadingQuestion {
                             question: new_text,
@@ -412,7 +944,7 @@ impl Parser {
                     )),
                     Line::StartCloze(new_text) => {
                         // Finalize the previous card.
-                        cards.extend(self.parse_cloze_cards(text, start_line, line_num)?);
+                        cards.extend(self.parse_cloze_cards(text, None, start_line, line_num)?);
                         // Start reading a new cloze card.
                         Ok(State::ReadingCloze {
                             text: new_text,
@@ -421,17 +953,151 @@ impl Parser {
                     }
                     Line::Separator => {
                         // Finalize the current cloze card.
-                        cards.extend(self.parse_cloze_cards(text, start_line, line_num)?);
+                        cards.extend(self.parse_cloze_cards(text, None, start_line, line_num)?);
                         // Return to start state.
                         Ok(State::Start)
                     }
+                    Line::StartList {
+                        text: new_text,
+                        whole,
+                    } => {
+                        // Finalize the previous cloze card.
+                        cards.extend(self.parse_cloze_cards(text, None, start_line, line_num)?);
+                        // Start reading a new list card.
+                        Ok(State::ReadingList {
+                            list: PendingList::new(new_text, whole),
+                            start_line: line_num,
+                        })
+                    }
+                    Line::StartNote(note) => Ok(State::ReadingNote {
+                        card: PendingCard::Cloze { text },
+                        note,
+                        start_line,
+                    }),
                     Line::Text(new_text) => Ok(State::ReadingCloze {
                         text: format!("{text}\n{new_text}"),
                         start_line,
                     }),
                     Line::Eof => {
                         // Finalize the current cloze card.
-                        cards.extend(self.parse_cloze_cards(text, start_line, line_num)?);
+                        cards.extend(self.parse_cloze_cards(text, None, start_line, line_num)?);
+                        Ok(State::End)
+                    }
+                }
+            }
+            State::ReadingList { list, start_line } => {
+                match line {
+                    Line::StartQuestion(text) => {
+                        // Finalize the previous list card.
+                        cards.extend(self.parse_list_cards(list, None, start_line, line_num)?);
+                        // Start a new question.
+                        Ok(State::ReadingQuestion {
+                            question: text,
+                            start_line: line_num,
+                        })
+                    }
+                    Line::StartAnswer(_) => Err(ParserError::new(
+                        "Found answer tag while reading a list card.",
+                        self.file_path.clone(),
+                        line_num,
+                    )),
+                    Line::StartCloze(text) => {
+                        // Finalize the previous list card.
+                        cards.extend(self.parse_list_cards(list, None, start_line, line_num)?);
+                        // Start reading a new cloze card.
+                        Ok(State::ReadingCloze {
+                            text,
+                            start_line: line_num,
+                        })
+                    }
+                    Line::StartList { text, whole } => {
+                        // Finalize the previous list card.
+                        cards.extend(self.parse_list_cards(list, None, start_line, line_num)?);
+                        // Start reading a new list card.
+                        Ok(State::ReadingList {
+                            list: PendingList::new(text, whole),
+                            start_line: line_num,
+                        })
+                    }
+                    Line::StartNote(note) => Ok(State::ReadingNote {
+                        card: PendingCard::List(list),
+                        note,
+                        start_line,
+                    }),
+                    Line::Separator => {
+                        // Finalize the current list card.
+                        cards.extend(self.parse_list_cards(list, None, start_line, line_num)?);
+                        // Return to start state.
+                        Ok(State::Start)
+                    }
+                    Line::Text(text) => Ok(State::ReadingList {
+                        list: self.read_list_line(list, text, line_num)?,
+                        start_line,
+                    }),
+                    Line::Eof => {
+                        // Finalize the current list card.
+                        cards.extend(self.parse_list_cards(list, None, start_line, line_num)?);
+                        Ok(State::End)
+                    }
+                }
+            }
+            State::ReadingNote {
+                card,
+                note,
+                start_line,
+            } => {
+                match line {
+                    Line::StartQuestion(text) => {
+                        // Finalize the previous card.
+                        cards.extend(self.finish_card(card, note, start_line, line_num)?);
+                        // Start a new question.
+                        Ok(State::ReadingQuestion {
+                            question: text,
+                            start_line: line_num,
+                        })
+                    }
+                    Line::StartAnswer(_) => Err(ParserError::new(
+                        "Found answer tag while reading a note.",
+                        self.file_path.clone(),
+                        line_num,
+                    )),
+                    Line::StartCloze(text) => {
+                        // Finalize the previous card.
+                        cards.extend(self.finish_card(card, note, start_line, line_num)?);
+                        // Start reading a new cloze card.
+                        Ok(State::ReadingCloze {
+                            text,
+                            start_line: line_num,
+                        })
+                    }
+                    Line::StartList { text, whole } => {
+                        // Finalize the previous card.
+                        cards.extend(self.finish_card(card, note, start_line, line_num)?);
+                        // Start reading a new list card.
+                        Ok(State::ReadingList {
+                            list: PendingList::new(text, whole),
+                            start_line: line_num,
+                        })
+                    }
+                    Line::StartNote(_) => Err(ParserError::new(
+                        "Found note tag while reading a note.",
+                        self.file_path.clone(),
+                        line_num,
+                    )),
+                    Line::Separator => {
+                        // Finalize the current card.
+                        cards.extend(self.finish_card(card, note, start_line, line_num)?);
+                        // Return to start state.
+                        Ok(State::Start)
+                    }
+                    Line::Text(text) => Ok(State::ReadingNote {
+                        card,
+                        note: format!("{note}\n{text}"),
+                        start_line,
+                    }),
+                    Line::Eof => {
+                        // Finalize the current card.
+                        cards.extend(self.finish_card(card, note, start_line, line_num)?);
                         Ok(State::End)
                     }
                 }
@@ -440,9 +1106,108 @@ impl Parser {
         }
     }
 
+    /// Build the card (or cards, for cloze text) that a note is attached to.
+    fn finish_card(
+        &self,
+        card: PendingCard,
+        note: String,
+        start_line: usize,
+        end_line: usize,
+    ) -> Result<Vec<Card>, ParserError> {
+        let note: String = note.trim().to_string();
+        let note: Option<String> = if note.is_empty() { None } else { Some(note) };
+        match card {
+            PendingCard::Basic { question, answer } => Ok(vec![Card::new(
+                self.deck_name.clone(),
+                self.file_path.clone(),
+                (start_line, end_line),
+                CardContent::new_basic(question, answer),
+                note,
+                self.hash_version,
+            )]),
+            PendingCard::Cloze { text } => self.parse_cloze_cards(text, note, start_line, end_line),
+            PendingCard::List(list) => self.parse_list_cards(list, note, start_line, end_line),
+        }
+    }
+
+    /// Read a line of a list card. Lines before the first item are part of
+    /// the title, indented lines continue the previous item, and blank lines
+    /// are ignored.
+    fn read_list_line(
+        &self,
+        mut list: PendingList,
+        text: String,
+        line_num: usize,
+    ) -> Result<PendingList, ParserError> {
+        if let Some((ordered, item)) = list_item(&text) {
+            if list.items.is_empty() {
+                list.ordered = ordered;
+            }
+            list.items.push(item.trim().to_string());
+        } else if list.items.is_empty() {
+            list.title = format!("{}\n{text}", list.title);
+        } else if text.trim().is_empty() {
+            // Skip blank lines between items.
+        } else if text.starts_with([' ', '\t']) {
+            if let Some(item) = list.items.last_mut() {
+                item.push('\n');
+                item.push_str(text.trim());
+            }
+        } else {
+            return Err(ParserError::new(
+                "Found text after the items of a list card.",
+                self.file_path.clone(),
+                line_num,
+            ));
+        }
+        Ok(list)
+    }
+
+    /// Generate one card per list item, plus one for the whole list if it was
+    /// requested. All of them belong to the same family.
+    fn parse_list_cards(
+        &self,
+        list: PendingList,
+        note: Option<String>,
+        start_line: usize,
+        end_line: usize,
+    ) -> Result<Vec<Card>, ParserError> {
+        if list.items.is_empty() {
+            return Err(ParserError::new(
+                "List card must contain at least one item.",
+                self.file_path.clone(),
+                start_line,
+            ));
+        }
+        let mut indices: Vec<Option<usize>> = (0..list.items.len()).map(Some).collect();
+        if list.whole {
+            indices.push(None);
+        }
+        let cards = indices
+            .into_iter()
+            .map(|index| {
+                Card::new(
+                    self.deck_name.clone(),
+                    self.file_path.clone(),
+                    (start_line, end_line),
+                    CardContent::new_list(
+                        list.title.clone(),
+                        list.items.clone(),
+                        list.ordered,
+                        index,
+                    ),
+                    note.clone(),
+                    self.hash_version,
+                )
+            })
+            .collect();
+        Ok(cards)
+    }
+
     fn parse_cloze_cards(
         &self,
         text: String,
+        note: Option<String>,
         start_line: usize,
         end_line: usize,
     ) -> Result<Vec<Card>, ParserError> {
@@ -560,6 +1325,8 @@ impl Parser {
                         self.file_path.clone(),
                         (start_line, end_line),
                         content,
+                        note.clone(),
+                        self.hash_version,
                     );
                     cards.push(card);
                     start = None;
@@ -616,6 +1383,9 @@ mod tests {
     use std::fs::create_dir_all;
 
     use super::*;
+    use crate::cache::parse_deck_cached;
+    use crate::db::Database;
+    use crate::helper::create_tmp_directory;
 
     #[test]
     fn test_empty_string() -> Result<(), ParserError> {
@@ -870,7 +1640,7 @@ mod tests {
     #[test]
     fn test_parse_deck() -> Fallible<()> {
         let directory = PathBuf::from("./test");
-        let deck = parse_deck(&directory);
+        let deck = parse_deck_cached(&directory, &mut Database::new(":memory:")?);
 
         assert!(deck.is_ok());
         let cards = deck?;
@@ -907,14 +1677,305 @@ mod tests {
         let file2 = directory.join("file2.md");
         std::fs::write(&file1, "Q: foo\nA: bar").expect("Failed to write test file");
         std::fs::write(&file2, "Q: foo\nA: bar").expect("Failed to write test file");
-        let deck = parse_deck(&directory)?;
+        let deck = parse_deck_cached(&directory, &mut Database::new(":memory:")?)?;
 
         assert_eq!(deck.len(), 1);
         Ok(())
     }
 
+    #[test]
+    fn test_basic_card_with_note() -> Result<(), ParserError> {
+        let input = "Q: foo\nA: bar\nN: A mnemonic.\nA source.\n\nQ: baz\nA: quux";
+        let parser = make_test_parser();
+        let cards = parser.parse(input)?;
+
+        assert_eq!(cards.len(), 2);
+        assert!(matches!(
+            &cards[0].content(),
+            CardContent::Basic {
+                question,
+                answer,
+            } if question == "foo" && answer == "bar"
+        ));
+        assert_eq!(cards[0].note(), Some("A mnemonic.\nA source."));
+        assert_eq!(cards[1].note(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_cloze_card_with_note() -> Result<(), ParserError> {
+        let input = "C: [foo] [bar]\nExtra: An explanation.\n---\nC: [baz]";
+        let parser = make_test_parser();
+        let cards = parser.parse(input)?;
+
+        assert_eq!(cards.len(), 3);
+        assert_cloze(&cards[0..2], "foo bar", &[(0, 2), (4, 6)]);
+        assert_eq!(cards[0].note(), Some("An explanation."));
+        assert_eq!(cards[1].note(), Some("An explanation."));
+        assert_eq!(cards[2].note(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_note_does_not_change_hash() -> Result<(), ParserError> {
+        let parser = make_test_parser();
+        let a = parser.parse("Q: foo\nA: bar")?;
+        let b = parser.parse("Q: foo\nA: bar\nN: A note.")?;
+        assert_eq!(a[0].hash(), b[0].hash());
+        Ok(())
+    }
+
+    #[test]
+    fn test_note_without_card() {
+        let parser = make_test_parser();
+        assert!(parser.parse("N: A note.").is_err());
+        assert!(parser.parse("Q: foo\nN: A note.").is_err());
+        assert!(parser.parse("Q: foo\nA: bar\nN: A note.\nA: baz").is_err());
+        assert!(
+            parser
+                .parse("Q: foo\nA: bar\nN: A note.\nN: Another.")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_legacy_grammar() -> Result<(), ParserError> {
+        let parser = make_test_parser().with_grammar(Grammar::Legacy);
+        let cards = parser.parse("Q: foo\nA: bar\nN: baz\nL: quux")?;
+        assert_eq!(cards.len(), 1);
+        assert_eq!(cards[0].note(), None);
+        let CardContent::Basic { answer, .. } = cards[0].content() else {
+            panic!("Expected a basic card.");
+        };
+        assert_eq!(answer, "bar\nN: baz\nL: quux");
+        Ok(())
+    }
+
+    #[test]
+    fn test_reinterpreted_markdown_cards() {
+        let path = PathBuf::from("./test/Deck.md");
+        let text = "Q: foo\nA: bar\nN: baz\n\nC: [x]\n\nQ: quux\nA: a\nb";
+        let cards = reinterpreted_markdown_cards(&path, text, HashVersion::Raw);
+        assert_eq!(cards.len(), 1);
+        assert_eq!(cards[0].range().0, 0);
+    }
+
+    #[test]
+    fn test_list_card() -> Result<(), ParserError> {
+        let parser = make_test_parser();
+        let cards = parser.parse("L: Planets\n\n1. Mercury\n2. Venus\n3. Earth")?;
+        assert_eq!(cards.len(), 3);
+        for (i, card) in cards.iter().enumerate() {
+            match card.content() {
+                CardContent::List {
+                    title,
+                    items,
+                    ordered,
+                    index,
+                } => {
+                    assert_eq!(title, "Planets");
+                    assert_eq!(items, &["Mercury", "Venus", "Earth"]);
+                    assert!(*ordered);
+                    assert_eq!(*index, Some(i));
+                }
+                _ => panic!("Expected list card."),
+            }
+            assert_eq!(card.family_hash(), cards[0].family_hash());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_whole_list_card() -> Result<(), ParserError> {
+        let parser = make_test_parser();
+        let cards = parser.parse("L+: Colors\n- Red\n- Green\n")?;
+        assert_eq!(cards.len(), 3);
+        assert!(matches!(
+            cards[2].content(),
+            CardContent::List {
+                ordered: false,
+                index: None,
+                ..
+            }
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_item_continuation() -> Result<(), ParserError> {
+        let parser = make_test_parser();
+        let cards = parser.parse("L: Steps\n- First\n  more detail\n- Second")?;
+        match cards[0].content() {
+            CardContent::List { items, .. } => {
+                assert_eq!(items, &["First\nmore detail", "Second"]);
+            }
+            _ => panic!("Expected list card."),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_appending_list_item_keeps_hashes() -> Result<(), ParserError> {
+        let parser = make_test_parser();
+        let a = parser.parse("L: Planets\n1. Mercury\n2. Venus")?;
+        let b = parser.parse("L: Planets\n1. Mercury\n2. Venus\n3. Earth")?;
+        assert_eq!(a[0].hash(), b[0].hash());
+        assert_eq!(a[1].hash(), b[1].hash());
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_card_followed_by_cards() -> Result<(), ParserError> {
+        let parser = make_test_parser();
+        let cards = parser
+            .parse("L: Pair\n- a\n- b\nN: A note.\nQ: foo\nA: bar\nL: Single\n- c\n---\nC: [x]")?;
+        assert_eq!(cards.len(), 5);
+        assert_eq!(cards[0].note(), Some("A note."));
+        assert_eq!(cards[1].note(), Some("A note."));
+        assert_eq!(cards[3].note(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_list_cards() {
+        let parser = make_test_parser();
+        assert!(parser.parse("L: Empty").is_err());
+        assert!(parser.parse("L: Items\n- a\nTrailing text.").is_err());
+        assert!(parser.parse("L: Items\n- a\nA: answer").is_err());
+        assert!(parser.parse("Q: foo\nL: Items\n- a").is_err());
+    }
+
+    #[test]
+    fn test_include() -> Result<(), ParserError> {
+        let directory = create_tmp_directory().expect("Failed to create test directory");
+        create_dir_all(directory.join("shared")).expect("Failed to create test directory");
+        std::fs::write(
+            directory.join("shared/constants.md"),
+            "# Math\n\nQ: pi?\nA: 3.14\n\n# Physics\n\nQ: c?\nA: 299792458 m/s\n\n## Units\n\nC: [Joule] is energy.\n\n# Chemistry\n\nQ: N_A?\nA: 6.022e23",
+        )
+        .expect("Failed to write test file");
+        let deck = directory.join("deck.md");
+        let parser = Parser::new("deck".to_string(), deck.clone(), HashVersion::Raw);
+
+        let cards = parser
+            .parse("Q: foo\nA: bar\n!include shared/constants.md#physics\nQ: baz\nA: quux")?;
+        assert_eq!(cards.len(), 4);
+        assert_eq!(cards[0].file_path(), &deck);
+        assert_eq!(cards[1].file_path(), &directory.join("shared/constants.md"));
+        assert_eq!(cards[1].range(), (7, 12));
+        assert_eq!(cards[1].deck_name(), "deck");
+        assert!(matches!(cards[2].content(), CardContent::Cloze { .. }));
+        assert_eq!(cards[3].file_path(), &deck);
+
+        // Sections can also be named by their heading text.
+        let cards = parser.parse("!include shared/constants.md#Math")?;
+        assert_eq!(cards.len(), 1);
+
+        // Whole files can be included.
+        let cards = parser.parse("!include shared/constants.md")?;
+        assert_eq!(cards.len(), 4);
+
+        assert!(
+            parser
+                .parse("!include shared/constants.md#biology")
+                .is_err()
+        );
+        assert!(parser.parse("!include shared/missing.md").is_err());
+        assert!(
+            parser
+                .parse("Q: foo\n!include shared/constants.md")
+                .is_err()
+        );
+        Ok(())
+    }
+
+    /// Cards and errors in an included file with frontmatter have line
+    /// numbers counted from the top of that file.
+    #[test]
+    fn test_include_with_frontmatter() -> Result<(), ParserError> {
+        let directory = create_tmp_directory().expect("Failed to create test directory");
+        std::fs::write(
+            directory.join("shared.md"),
+            "---\nname = \"Shared\"\n---\n\nQ: pi?\nA: 3.14\n\nQ: unfinished",
+        )
+        .expect("Failed to write test file");
+        let parser = Parser::new(
+            "deck".to_string(),
+            directory.join("deck.md"),
+            HashVersion::Raw,
+        );
+        let (cards, errors) = parser.parse_all("!include shared.md", 0);
+        assert_eq!(cards.len(), 1);
+        assert_eq!(cards[0].range(), (4, 7));
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].file_path, directory.join("shared.md"));
+        assert_eq!(errors[0].line_num, 7);
+        Ok(())
+    }
+
+    #[test]
+    fn test_include_cycle() {
+        let directory = create_tmp_directory().expect("Failed to create test directory");
+        std::fs::write(directory.join("a.md"), "Q: a\nA: a\n!include b.md")
+            .expect("Failed to write test file");
+        std::fs::write(directory.join("b.md"), "# Section\n\n!include a.md")
+            .expect("Failed to write test file");
+        std::fs::write(
+            directory.join("c.md"),
+            "# One\n\nQ: c\nA: c\n\n---\n\n# Two\n\n!include c.md#one",
+        )
+        .expect("Failed to write test file");
+
+        let text = std::fs::read_to_string(directory.join("a.md")).unwrap();
+        let parser = Parser::new("a".to_string(), directory.join("a.md"), HashVersion::Raw);
+        let Err(error) = parser.parse(&text) else {
+            panic!("Expected an include cycle error.");
+        };
+        assert!(error.message.starts_with("Include cycle:"));
+        assert_eq!(error.file_path, directory.join("b.md"));
+
+        // Including another section of the same file is not a cycle.
+        let text = std::fs::read_to_string(directory.join("c.md")).unwrap();
+        let parser = Parser::new("c".to_string(), directory.join("c.md"), HashVersion::Raw);
+        assert_eq!(parser.parse(&text).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_parse_all_recovers_from_errors() {
+        let parser = make_test_parser();
+        let text = "A: stray answer\nmore text\n\nQ: foo\nA: bar\n\nC: no deletions\nQ: baz\nA: quux\nQ: unfinished";
+        let (cards, errors) = parser.parse_all(text, 0);
+        assert_eq!(cards.len(), 2);
+        let lines: Vec<usize> = errors.iter().map(|e| e.line_num).collect();
+        assert_eq!(lines, vec![0, 6, 9]);
+    }
+
+    #[test]
+    fn test_parse_all_line_offset() {
+        let parser = make_test_parser();
+        let (cards, errors) = parser.parse_all("Q: foo\nA: bar\nQ: baz", 3);
+        assert_eq!(cards[0].range(), (3, 5));
+        assert_eq!(errors[0].line_num, 5);
+    }
+
+    #[test]
+    fn test_lint_directives_are_skipped() -> Result<(), ParserError> {
+        let parser = make_test_parser();
+        let plain = parser.parse("Q: foo\nA: bar\n\nQ: baz\nA: quux")?;
+        let annotated =
+            parser.parse("Q: foo\nA: bar\n\n!lint-allow long-answer\nQ: baz\nA: quux")?;
+        assert_eq!(plain[0].hash(), annotated[0].hash());
+        assert_eq!(plain[1].hash(), annotated[1].hash());
+        assert_eq!(annotated[1].range().0, 4);
+        Ok(())
+    }
+
     fn make_test_parser() -> Parser {
-        Parser::new("test_deck".to_string(), PathBuf::from("test.md"))
+        Parser::new(
+            "test_deck".to_string(),
+            PathBuf::from("test.md"),
+            HashVersion::Raw,
+        )
     }
 
     fn assert_cloze(cards: &[Card], clean_text: &str, deletions: &[(usize, usize)]) {
@@ -1128,7 +2189,7 @@ A: Genetic material."#,
         )
         .expect("Failed to write test file");
 
-        let deck = parse_deck(&directory)?;
+        let deck = parse_deck_cached(&directory, &mut Database::new(":memory:")?)?;
 
         // Both cards should have the custom deck name "Cell Biology"
         assert_eq!(deck.len(), 2);
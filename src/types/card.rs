@@ -18,13 +18,15 @@ use std::path::PathBuf;
 use maud::Markup;
 use maud::PreEscaped;
 use maud::html;
+use serde::Deserialize;
+use serde::Serialize;
 
 use crate::error::Fallible;
+use crate::format::DeckFormat;
 use crate::markdown::MarkdownRenderConfig;
-use crate::markdown::markdown_to_html;
-use crate::markdown::markdown_to_html_inline;
 use crate::types::aliases::DeckName;
 use crate::types::card_hash::CardHash;
+use crate::types::card_hash::HashVersion;
 use crate::types::card_hash::Hasher;
 
 const CLOZE_TAG_BYTES: &[u8] = b"CLOZE_DELETION";
@@ -40,11 +42,14 @@ pub struct Card {
     range: (usize, usize),
     /// The card's content.
     content: CardContent,
+    /// An optional note shown below the answer. Not part of the hash, so it
+    /// can be edited without resetting the card's progress.
+    note: Option<String>,
     /// The cached hash of the card's content.
     hash: CardHash,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub enum CardContent {
     Basic {
         question: String,
@@ -58,12 +63,49 @@ pub enum CardContent {
         /// The position of the last character of the deletion.
         end: usize,
     },
+    List {
+        /// The text that introduces the list.
+        title: String,
+        /// The list items, in order.
+        items: Vec<String>,
+        /// Whether the list is numbered.
+        ordered: bool,
+        /// The index of the item this card asks for, given the items before
+        /// it. If `None`, the card asks for the whole list.
+        index: Option<usize>,
+    },
+}
+
+/// The cards defined at the given (zero-based) line of a deck file. A line
+/// between two cards belongs to the later one.
+pub fn cards_at_line(cards: Vec<Card>, file_path: &Path, line: usize) -> Vec<Card> {
+    let cards: Vec<Card> = cards
+        .into_iter()
+        .filter(|card| card.file_path() == file_path)
+        .filter(|card| card.range().0 <= line && line <= card.range().1)
+        .collect();
+    let start = cards.iter().map(|card| card.range().0).max();
+    cards
+        .into_iter()
+        .filter(|card| Some(card.range().0) == start)
+        .collect()
 }
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum CardType {
     Basic,
     Cloze,
+    List,
+}
+
+impl CardType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CardType::Basic => "basic",
+            CardType::Cloze => "cloze",
+            CardType::List => "list",
+        }
+    }
 }
 
 impl Card {
@@ -72,12 +114,16 @@ impl Card {
         file_path: PathBuf,
         range: (usize, usize),
         content: CardContent,
+        note: Option<String>,
+        hash_version: HashVersion,
     ) -> Self {
-        let hash = content.hash();
+        let format = DeckFormat::from_path(&file_path).unwrap_or_default();
+        let hash = content.hash(hash_version, format);
         Self {
             deck_name,
             file_path,
             content,
+            note,
             range,
             hash,
         }
@@ -91,6 +137,10 @@ impl Card {
         &self.content
     }
 
+    pub fn note(&self) -> Option<&str> {
+        self.note.as_deref()
+    }
+
     pub fn hash(&self) -> CardHash {
         self.hash
     }
@@ -124,15 +174,30 @@ impl Card {
         match &self.content {
             CardContent::Basic { .. } => CardType::Basic,
             CardContent::Cloze { .. } => CardType::Cloze,
+            CardContent::List { .. } => CardType::List,
         }
     }
 
+    /// Return the format of the file this card was parsed from.
+    pub fn format(&self) -> DeckFormat {
+        DeckFormat::from_path(&self.file_path).unwrap_or_default()
+    }
+
     pub fn html_front(&self, config: &MarkdownRenderConfig) -> Fallible<Markup> {
-        self.content.html_front(config)
+        self.content.html_front(config, self.format())
     }
 
     pub fn html_back(&self, config: &MarkdownRenderConfig) -> Fallible<Markup> {
-        self.content.html_back(config)
+        self.content.html_back(config, self.format())
+    }
+
+    pub fn html_note(&self, config: &MarkdownRenderConfig) -> Fallible<Option<Markup>> {
+        match &self.note {
+            Some(note) => Ok(Some(html! {
+                (PreEscaped(self.format().to_html(config, note)?))
+            })),
+            None => Ok(None),
+        }
     }
 }
 
@@ -152,7 +217,29 @@ impl CardContent {
         }
     }
 
-    pub fn hash(&self) -> CardHash {
+    pub fn new_list(
+        title: impl Into<String>,
+        items: Vec<String>,
+        ordered: bool,
+        index: Option<usize>,
+    ) -> Self {
+        Self::List {
+            title: title.into().trim().to_string(),
+            items,
+            ordered,
+            index,
+        }
+    }
+
+    /// Hash the card, whose text is in the given format.
+    pub fn hash(&self, version: HashVersion, format: DeckFormat) -> CardHash {
+        match version {
+            HashVersion::Raw => self.raw_hash(),
+            HashVersion::Normalized => self.normalized_hash(format),
+        }
+    }
+
+    fn raw_hash(&self) -> CardHash {
         let mut hasher = Hasher::new();
         match &self {
             CardContent::Basic { question, answer } => {
@@ -166,11 +253,71 @@ impl CardContent {
                 hasher.update(&start.to_le_bytes());
                 hasher.update(&end.to_le_bytes());
             }
+            CardContent::List {
+                title,
+                items,
+                ordered,
+                index,
+            } => {
+                hasher.update(b"List");
+                hasher.update(title.as_bytes());
+                hasher.update(&[*ordered as u8]);
+                for item in list_items_shown(items, *index) {
+                    hasher.update(b"\0");
+                    hasher.update(item.as_bytes());
+                }
+                hasher.update(list_index_tag(*index));
+            }
+        }
+        hasher.finalize()
+    }
+
+    /// Hash the canonical form of the card's text, as normalized for its
+    /// format. Fields are separated by a null byte. For cloze cards, the deletion is replaced by a placeholder
+    /// before normalizing, so that the position of the deletion survives
+    /// changes to the surrounding whitespace.
+    fn normalized_hash(&self, format: DeckFormat) -> CardHash {
+        let mut hasher = Hasher::new();
+        match &self {
+            CardContent::Basic { question, answer } => {
+                hasher.update(b"Basic");
+                hasher.update(format.normalize(question).as_bytes());
+                hasher.update(b"\0");
+                hasher.update(format.normalize(answer).as_bytes());
+            }
+            CardContent::Cloze { text, start, end } => {
+                let bytes: &[u8] = text.as_bytes();
+                let deleted: String =
+                    String::from_utf8_lossy(&bytes[*start..*end + 1]).into_owned();
+                let mut masked: Vec<u8> = bytes.to_owned();
+                masked.splice(*start..*end + 1, CLOZE_TAG_BYTES.iter().copied());
+                let masked: String = String::from_utf8_lossy(&masked).into_owned();
+                hasher.update(b"Cloze");
+                hasher.update(format.normalize(&masked).as_bytes());
+                hasher.update(b"\0");
+                hasher.update(format.normalize(&deleted).as_bytes());
+            }
+            CardContent::List {
+                title,
+                items,
+                ordered,
+                index,
+            } => {
+                hasher.update(b"List");
+                hasher.update(format.normalize(title).as_bytes());
+                hasher.update(&[*ordered as u8]);
+                for item in list_items_shown(items, *index) {
+                    hasher.update(b"\0");
+                    hasher.update(format.normalize(item).as_bytes());
+                }
+                hasher.update(list_index_tag(*index));
+            }
         }
         hasher.finalize()
     }
 
-    /// All cloze cards derived from the same text have the same family hash.
+    /// All cloze cards derived from the same text have the same family hash,
+    /// as do all cards derived from the same list.
     ///
     /// For basic cards, this is `None`.
     pub fn family_hash(&self) -> Option<CardHash> {
@@ -182,46 +329,79 @@ impl CardContent {
                 hasher.update(text.as_bytes());
                 Some(hasher.finalize())
             }
+            CardContent::List { title, items, .. } => {
+                let mut hasher = Hasher::new();
+                hasher.update(b"List");
+                hasher.update(title.as_bytes());
+                for item in items {
+                    hasher.update(b"\0");
+                    hasher.update(item.as_bytes());
+                }
+                Some(hasher.finalize())
+            }
         }
     }
 
-    pub fn html_front(&self, config: &MarkdownRenderConfig) -> Fallible<Markup> {
+    pub fn html_front(
+        &self,
+        config: &MarkdownRenderConfig,
+        format: DeckFormat,
+    ) -> Fallible<Markup> {
         let html = match self {
             CardContent::Basic { question, .. } => {
                 html! {
-                    (PreEscaped(markdown_to_html(config, question)?))
+                    (PreEscaped(format.to_html(config, question)?))
                 }
             }
             CardContent::Cloze { text, start, end } => {
                 let mut text_bytes: Vec<u8> = text.as_bytes().to_owned();
                 text_bytes.splice(*start..*end + 1, CLOZE_TAG_BYTES.iter().copied());
                 let text: String = String::from_utf8(text_bytes)?;
-                let text: String = markdown_to_html(config, &text)?;
+                let text: String = format.to_html(config, &text)?;
                 let text: String =
                     text.replace(CLOZE_TAG, "<span class='cloze'>.............</span>");
                 html! {
                     (PreEscaped(text))
                 }
             }
+            CardContent::List {
+                title,
+                items,
+                ordered,
+                index,
+            } => {
+                let text: String = match index {
+                    Some(index) => {
+                        let text =
+                            list_markdown(title, &items[..*index], *ordered, Some(CLOZE_TAG));
+                        let text = format.to_html(config, &text)?;
+                        text.replace(CLOZE_TAG, "<span class='cloze'>.............</span>")
+                    }
+                    None => format.to_html(config, title)?,
+                };
+                html! {
+                    (PreEscaped(text))
+                }
+            }
         };
         Ok(html)
     }
 
-    pub fn html_back(&self, config: &MarkdownRenderConfig) -> Fallible<Markup> {
+    pub fn html_back(&self, config: &MarkdownRenderConfig, format: DeckFormat) -> Fallible<Markup> {
         let html = match self {
             CardContent::Basic { answer, .. } => {
                 html! {
-                    (PreEscaped(markdown_to_html(config, answer)?))
+                    (PreEscaped(format.to_html(config, answer)?))
                 }
             }
             CardContent::Cloze { text, start, end } => {
                 let mut text_bytes: Vec<u8> = text.as_bytes().to_owned();
                 let deleted_text: Vec<u8> = text_bytes[*start..*end + 1].to_owned();
                 let deleted_text: String = String::from_utf8(deleted_text)?;
-                let deleted_text: String = markdown_to_html_inline(config, &deleted_text)?;
+                let deleted_text: String = format.to_html_inline(config, &deleted_text)?;
                 text_bytes.splice(*start..*end + 1, CLOZE_TAG_BYTES.iter().copied());
                 let text: String = String::from_utf8(text_bytes)?;
-                let text = markdown_to_html(config, &text)?;
+                let text = format.to_html(config, &text)?;
                 let text = text.replace(
                     CLOZE_TAG,
                     &format!("<span class='cloze-reveal'>{}</span>", deleted_text),
@@ -230,11 +410,72 @@ impl CardContent {
                     (PreEscaped(text))
                 }
             }
+            CardContent::List {
+                title,
+                items,
+                ordered,
+                index,
+            } => {
+                let text: String = match index {
+                    Some(index) => {
+                        let item: String = format.to_html_inline(config, &items[*index])?;
+                        let text =
+                            list_markdown(title, &items[..*index], *ordered, Some(CLOZE_TAG));
+                        let text = format.to_html(config, &text)?;
+                        text.replace(
+                            CLOZE_TAG,
+                            &format!("<span class='cloze-reveal'>{}</span>", item),
+                        )
+                    }
+                    None => {
+                        let text = list_markdown(title, items, *ordered, None);
+                        format.to_html(config, &text)?
+                    }
+                };
+                html! {
+                    (PreEscaped(text))
+                }
+            }
         };
         Ok(html)
     }
 }
 
+/// The items that a list card shows or asks for: up to and including the
+/// asked-for item, or all of them for the whole-list card. Items after the
+/// asked-for item don't affect the card, so appending items to a list doesn't
+/// reset the cards for the existing items.
+fn list_items_shown(items: &[String], index: Option<usize>) -> &[String] {
+    match index {
+        Some(index) => &items[..index + 1],
+        None => items,
+    }
+}
+
+fn list_index_tag(index: Option<usize>) -> &'static [u8] {
+    match index {
+        Some(_) => b"Item",
+        None => b"Whole",
+    }
+}
+
+/// Build the Markdown for a list card: the title, followed by the given items,
+/// followed by `last` as one more item if present.
+fn list_markdown(title: &str, items: &[String], ordered: bool, last: Option<&str>) -> String {
+    let mut markdown: String = format!("{title}\n\n");
+    for (i, item) in items.iter().map(String::as_str).chain(last).enumerate() {
+        let marker: String = if ordered {
+            format!("{}.", i + 1)
+        } else {
+            "-".to_string()
+        };
+        // Indent continuation lines so they stay inside the item.
+        let item: String = item.replace('\n', "\n    ");
+        markdown.push_str(&format!("{marker} {item}\n"));
+    }
+    markdown
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -244,8 +485,74 @@ mod tests {
         let card1 = CardContent::new_basic("What is 2+2?", "4");
         let card2 = CardContent::new_basic("What is 2+2?", "4");
         let card3 = CardContent::new_basic("What is 3+3?", "6");
-        assert_eq!(card1.hash(), card2.hash());
-        assert_ne!(card1.hash(), card3.hash());
+        assert_eq!(
+            card1.hash(HashVersion::Raw, DeckFormat::Markdown),
+            card2.hash(HashVersion::Raw, DeckFormat::Markdown)
+        );
+        assert_ne!(
+            card1.hash(HashVersion::Raw, DeckFormat::Markdown),
+            card3.hash(HashVersion::Raw, DeckFormat::Markdown)
+        );
+    }
+
+    #[test]
+    fn test_normalized_basic_card_hash() {
+        let a = CardContent::new_basic("What is\n*Rust*?", "A  language.");
+        let b = CardContent::new_basic("What is _Rust_?", "A\nlanguage.");
+        let c = CardContent::new_basic("What is _Rust_?", "A library.");
+        assert_ne!(
+            a.hash(HashVersion::Raw, DeckFormat::Markdown),
+            b.hash(HashVersion::Raw, DeckFormat::Markdown)
+        );
+        assert_eq!(
+            a.hash(HashVersion::Normalized, DeckFormat::Markdown),
+            b.hash(HashVersion::Normalized, DeckFormat::Markdown)
+        );
+        assert_ne!(
+            a.hash(HashVersion::Normalized, DeckFormat::Markdown),
+            c.hash(HashVersion::Normalized, DeckFormat::Markdown)
+        );
+    }
+
+    #[test]
+    fn test_normalized_cloze_card_hash() {
+        let a = CardContent::new_cloze("The capital of\nFrance is Paris.", 25, 29);
+        let b = CardContent::new_cloze("The capital of France is Paris.", 25, 29);
+        let c = CardContent::new_cloze("The capital of France is Paris.", 4, 10);
+        assert_ne!(
+            a.hash(HashVersion::Raw, DeckFormat::Markdown),
+            b.hash(HashVersion::Raw, DeckFormat::Markdown)
+        );
+        assert_eq!(
+            a.hash(HashVersion::Normalized, DeckFormat::Markdown),
+            b.hash(HashVersion::Normalized, DeckFormat::Markdown)
+        );
+        assert_ne!(
+            b.hash(HashVersion::Normalized, DeckFormat::Markdown),
+            c.hash(HashVersion::Normalized, DeckFormat::Markdown)
+        );
+    }
+
+    #[test]
+    fn test_normalized_org_card_hash() {
+        // In Org, an indented line continues a list item, and an unindented one
+        // starts a paragraph after the list. In Markdown, both continue it.
+        let a = CardContent::new_basic("Items?", "- one\n  two");
+        let b = CardContent::new_basic("Items?", "- one\ntwo");
+        let c = CardContent::new_basic("Items?", "- one   \n\n\n  two");
+        let d = CardContent::new_basic("Items?", "- one\n\n  two");
+        assert_eq!(
+            a.hash(HashVersion::Normalized, DeckFormat::Markdown),
+            b.hash(HashVersion::Normalized, DeckFormat::Markdown)
+        );
+        assert_ne!(
+            a.hash(HashVersion::Normalized, DeckFormat::Org),
+            b.hash(HashVersion::Normalized, DeckFormat::Org)
+        );
+        assert_eq!(
+            c.hash(HashVersion::Normalized, DeckFormat::Org),
+            d.hash(HashVersion::Normalized, DeckFormat::Org)
+        );
     }
 
     #[test]
@@ -255,6 +562,52 @@ mod tests {
         assert_eq!(a.family_hash(), b.family_hash());
     }
 
+    #[test]
+    fn test_list_card_hash() {
+        let items = |items: &[&str]| items.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let a = CardContent::new_list("Planets", items(&["Mercury", "Venus"]), true, Some(1));
+        let b = CardContent::new_list(
+            "Planets",
+            items(&["Mercury", "Venus", "Earth"]),
+            true,
+            Some(1),
+        );
+        let c = CardContent::new_list("Planets", items(&["Mercury", "Venus"]), true, None);
+        let d = CardContent::new_list("Planets", items(&["Mercury", "Venus", "Earth"]), true, None);
+        // Appending an item doesn't change the cards for earlier items.
+        assert_eq!(
+            a.hash(HashVersion::Raw, DeckFormat::Markdown),
+            b.hash(HashVersion::Raw, DeckFormat::Markdown)
+        );
+        assert_eq!(
+            a.hash(HashVersion::Normalized, DeckFormat::Markdown),
+            b.hash(HashVersion::Normalized, DeckFormat::Markdown)
+        );
+        // But it does change the whole-list card.
+        assert_ne!(
+            c.hash(HashVersion::Raw, DeckFormat::Markdown),
+            d.hash(HashVersion::Raw, DeckFormat::Markdown)
+        );
+        assert_ne!(
+            a.hash(HashVersion::Raw, DeckFormat::Markdown),
+            c.hash(HashVersion::Raw, DeckFormat::Markdown)
+        );
+        assert_eq!(b.family_hash(), d.family_hash());
+    }
+
+    #[test]
+    fn test_list_markdown() {
+        let items = vec!["Mercury".to_string(), "Venus\nsecond line".to_string()];
+        assert_eq!(
+            list_markdown("Planets", &items, true, Some("Earth")),
+            "Planets\n\n1. Mercury\n2. Venus\n    second line\n3. Earth\n"
+        );
+        assert_eq!(
+            list_markdown("Planets", &items[..1], false, None),
+            "Planets\n\n- Mercury\n"
+        );
+    }
+
     #[test]
     fn test_family_hash() {
         let a = CardContent::new_cloze("The capital of France is Paris", 0, 1);
@@ -93,6 +93,48 @@ impl Serialize for CardHash {
     }
 }
 
+/// The scheme used to compute card hashes.
+///
+/// The hash version of a collection is stored in its database, and every card
+/// parsed from the collection is hashed using that version.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HashVersion {
+    /// Hash the trimmed text of the card exactly as written. This is the
+    /// default, for compatibility with existing databases.
+    Raw,
+    /// Hash a canonical form of the card's Markdown, so that whitespace, line
+    /// wrapping, choice of emphasis markers, and Unicode normalization form do
+    /// not affect the hash.
+    Normalized,
+}
+
+impl HashVersion {
+    pub fn as_str(&self) -> &str {
+        match self {
+            HashVersion::Raw => "raw",
+            HashVersion::Normalized => "normalized",
+        }
+    }
+}
+
+impl TryFrom<String> for HashVersion {
+    type Error = ErrorReport;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "raw" => Ok(HashVersion::Raw),
+            "normalized" => Ok(HashVersion::Normalized),
+            _ => Err(ErrorReport::new(format!("invalid hash version: {value}"))),
+        }
+    }
+}
+
+impl Display for HashVersion {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
 pub struct Hasher {
     inner: blake3::Hasher,
 }
@@ -128,6 +170,15 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_hash_version_roundtrip() -> Fallible<()> {
+        for version in [HashVersion::Raw, HashVersion::Normalized] {
+            assert_eq!(HashVersion::try_from(version.to_string())?, version);
+        }
+        assert!(HashVersion::try_from("v3".to_string()).is_err());
+        Ok(())
+    }
+
     #[test]
     fn test_ordering() -> Fallible<()> {
         let a =
@@ -0,0 +1,158 @@
+// Copyright 2025 Fernando Borretti
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::path::Path;
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use crate::parser::ParserError;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A problem found in a collection, pointing to a place in a file. Lines and
+/// columns start at one.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct Diagnostic {
+    pub file: PathBuf,
+    pub line: usize,
+    pub column: usize,
+    pub severity: Severity,
+    pub message: String,
+    /// The lint rule that produced the diagnostic, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rule: Option<&'static str>,
+}
+
+impl Diagnostic {
+    pub fn new(
+        severity: Severity,
+        file: impl Into<PathBuf>,
+        line: usize,
+        column: usize,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            file: file.into(),
+            line,
+            column,
+            severity,
+            message: message.into(),
+            rule: None,
+        }
+    }
+
+    /// Attribute the diagnostic to a lint rule.
+    pub fn with_rule(self, rule: &'static str) -> Self {
+        Self {
+            rule: Some(rule),
+            ..self
+        }
+    }
+
+    /// Format the diagnostic the way compilers do, with the path relative to
+    /// the given directory if the file is inside it.
+    pub fn display_relative_to(&self, directory: &Path) -> String {
+        let file = self.file.strip_prefix(directory).unwrap_or(&self.file);
+        let mut text = format!(
+            "{}:{}:{}: {}: {}",
+            file.display(),
+            self.line,
+            self.column,
+            self.severity,
+            self.message
+        );
+        if let Some(rule) = self.rule {
+            text.push_str(&format!(" [{rule}]"));
+        }
+        text
+    }
+}
+
+impl From<ParserError> for Diagnostic {
+    fn from(error: ParserError) -> Self {
+        Self::new(
+            Severity::Error,
+            error.file_path,
+            error.line_num + 1,
+            1,
+            error.message,
+        )
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.display_relative_to(Path::new("")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let diagnostic = Diagnostic::new(
+            Severity::Error,
+            "/cards/deck.md",
+            3,
+            1,
+            "Found answer tag without a question.",
+        );
+        assert_eq!(
+            diagnostic.to_string(),
+            "/cards/deck.md:3:1: error: Found answer tag without a question."
+        );
+        assert_eq!(
+            diagnostic.display_relative_to(Path::new("/cards")),
+            "deck.md:3:1: error: Found answer tag without a question."
+        );
+    }
+
+    #[test]
+    fn test_display_rule() {
+        let diagnostic = Diagnostic::new(Severity::Warning, "deck.md", 1, 1, "Answer is long.")
+            .with_rule("long-answer");
+        assert_eq!(
+            diagnostic.to_string(),
+            "deck.md:1:1: warning: Answer is long. [long-answer]"
+        );
+    }
+
+    #[test]
+    fn test_from_parser_error() {
+        let error = ParserError::new("Oops.", PathBuf::from("deck.md"), 0);
+        let diagnostic = Diagnostic::from(error);
+        assert_eq!(diagnostic.line, 1);
+        assert_eq!(diagnostic.column, 1);
+        assert_eq!(diagnostic.severity, Severity::Error);
+    }
+}
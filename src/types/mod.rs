@@ -16,5 +16,6 @@ pub mod aliases;
 pub mod card;
 pub mod card_hash;
 pub mod date;
+pub mod diagnostic;
 pub mod performance;
 pub mod timestamp;
@@ -30,7 +30,7 @@ use crate::types::date::Date;
 use crate::types::timestamp::Timestamp;
 
 /// The desired recall probability.
-const TARGET_RECALL: f64 = 0.9;
+pub const TARGET_RECALL: f64 = 0.9;
 
 /// The minimum review interval in days.
 const MIN_INTERVAL: f64 = 1.0;
@@ -0,0 +1,320 @@
+// Copyright 2025 Fernando Borretti
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::Path;
+use std::path::PathBuf;
+
+use globset::GlobBuilder;
+use globset::GlobSet;
+use globset::GlobSetBuilder;
+use ignore::gitignore::Gitignore;
+use ignore::gitignore::GitignoreBuilder;
+use walkdir::DirEntry;
+use walkdir::WalkDir;
+
+use crate::config::CONFIG_FILE;
+use crate::config::CollectionConfig;
+use crate::error::ErrorReport;
+use crate::error::Fallible;
+use crate::format::DeckFormat;
+use crate::markdown::IMAGE_EXTENSIONS;
+use crate::markdown::is_audio_file;
+
+/// The name of the file that lists the paths to ignore, in gitignore syntax.
+pub const IGNORE_FILE: &str = ".hashcardsignore";
+
+/// Return the paths of the deck files in the collection, sorted by name.
+///
+/// Hidden files and directories are skipped, as are the paths matched by the
+/// `.hashcardsignore` file or by the `exclude` globs in the configuration. If
+/// there are `include` globs, only the deck files they match are returned.
+///
+/// Symbolic links to files are followed. Symbolic links to directories are
+/// only followed if `follow_symlinks` is set, in which case link cycles are
+/// reported as errors.
+pub fn deck_files(directory: &Path) -> Fallible<Vec<PathBuf>> {
+    let config = CollectionConfig::load(directory)?;
+    let filter = DeckFilter::new(directory, &config)?;
+    let walker = WalkDir::new(directory)
+        .follow_links(config.follow_symlinks)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| entry.depth() == 0 || !filter.is_pruned(entry));
+    let mut paths = Vec::new();
+    for entry in walker {
+        let entry = entry?;
+        let path = entry.path();
+        if path.is_file() && is_deck_file(path) && filter.is_selected(path) {
+            paths.push(path.to_path_buf());
+        }
+    }
+    Ok(paths)
+}
+
+/// Return the paths of the media files in the collection, sorted by name.
+///
+/// Hidden files and directories are skipped, as are the paths matched by the
+/// `.hashcardsignore` file or by the `exclude` globs in the configuration.
+pub fn media_files(directory: &Path) -> Fallible<Vec<PathBuf>> {
+    let config = CollectionConfig::load(directory)?;
+    let filter = DeckFilter::new(directory, &config)?;
+    let walker = WalkDir::new(directory)
+        .follow_links(config.follow_symlinks)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| entry.depth() == 0 || !filter.is_pruned(entry));
+    let mut paths = Vec::new();
+    for entry in walker {
+        let entry = entry?;
+        let path = entry.path();
+        if path.is_file() && is_media_file(path) {
+            paths.push(path.to_path_buf());
+        }
+    }
+    Ok(paths)
+}
+
+/// Whether the file is in a format that contains cards.
+fn is_deck_file(path: &Path) -> bool {
+    DeckFormat::from_path(path).is_some()
+}
+
+/// Whether the file is an image or audio file.
+pub fn is_media_file(path: &Path) -> bool {
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    IMAGE_EXTENSIONS.contains(&extension.as_str()) || is_audio_file(&extension)
+}
+
+struct DeckFilter {
+    root: PathBuf,
+    ignore: Gitignore,
+    include: Option<GlobSet>,
+    exclude: GlobSet,
+}
+
+impl DeckFilter {
+    fn new(root: &Path, config: &CollectionConfig) -> Fallible<Self> {
+        let mut builder = GitignoreBuilder::new(root);
+        let ignore_path = root.join(IGNORE_FILE);
+        if ignore_path.exists() {
+            if let Some(e) = builder.add(&ignore_path) {
+                return Err(ErrorReport::new(format!(
+                    "Failed to read {IGNORE_FILE}: {e}"
+                )));
+            }
+        }
+        let ignore = builder
+            .build()
+            .map_err(|e| ErrorReport::new(format!("Failed to read {IGNORE_FILE}: {e}")))?;
+        let include = if config.include.is_empty() {
+            None
+        } else {
+            Some(build_globs(&config.include)?)
+        };
+        let exclude = build_globs(&config.exclude)?;
+        Ok(Self {
+            root: root.to_path_buf(),
+            ignore,
+            include,
+            exclude,
+        })
+    }
+
+    /// Whether to skip this entry, and everything under it.
+    fn is_pruned(&self, entry: &DirEntry) -> bool {
+        let hidden = entry.file_name().to_string_lossy().starts_with('.');
+        let is_dir = entry.file_type().is_dir();
+        hidden
+            || self.ignore.matched(entry.path(), is_dir).is_ignore()
+            || self.exclude.is_match(self.relative(entry.path()))
+    }
+
+    /// Whether a deck file is matched by the `include` globs.
+    fn is_selected(&self, path: &Path) -> bool {
+        match &self.include {
+            Some(include) => include.is_match(self.relative(path)),
+            None => true,
+        }
+    }
+
+    fn relative<'a>(&self, path: &'a Path) -> &'a Path {
+        path.strip_prefix(&self.root).unwrap_or(path)
+    }
+}
+
+fn build_globs(globs: &[String]) -> Fallible<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for glob in globs {
+        let glob = GlobBuilder::new(glob)
+            .literal_separator(true)
+            .build()
+            .map_err(|e| ErrorReport::new(format!("Invalid glob in {CONFIG_FILE}: {e}")))?;
+        builder.add(glob);
+    }
+    builder
+        .build()
+        .map_err(|e| ErrorReport::new(format!("Invalid glob in {CONFIG_FILE}: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::create_dir_all;
+    use std::fs::write;
+
+    use tempfile::tempdir;
+
+    use super::*;
+
+    fn names(directory: &Path) -> Fallible<Vec<String>> {
+        Ok(deck_files(directory)?
+            .iter()
+            .map(|path| {
+                path.strip_prefix(directory)
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string()
+            })
+            .collect())
+    }
+
+    fn make_collection() -> Fallible<tempfile::TempDir> {
+        let directory = tempdir()?;
+        let root = directory.path();
+        create_dir_all(root.join("decks/drafts"))?;
+        create_dir_all(root.join(".obsidian"))?;
+        write(root.join("README.md"), "Not a deck.")?;
+        write(root.join("decks/a.md"), "Q: a\nA: a")?;
+        write(root.join("decks/notes.txt"), "Not markdown.")?;
+        write(root.join("decks/drafts/b.md"), "Q: b")?;
+        write(root.join(".obsidian/c.md"), "Q: c\nA: c")?;
+        Ok(directory)
+    }
+
+    #[test]
+    fn test_default_walk_skips_hidden() -> Fallible<()> {
+        let directory = make_collection()?;
+        assert_eq!(
+            names(directory.path())?,
+            vec!["README.md", "decks/a.md", "decks/drafts/b.md"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_org_files() -> Fallible<()> {
+        let directory = make_collection()?;
+        write(directory.path().join("decks/c.org"), "* c? :card:\nc")?;
+        assert_eq!(
+            names(directory.path())?,
+            vec![
+                "README.md",
+                "decks/a.md",
+                "decks/c.org",
+                "decks/drafts/b.md"
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_media_files() -> Fallible<()> {
+        let directory = make_collection()?;
+        let root = directory.path();
+        write(root.join("decks/cat.PNG"), "")?;
+        write(root.join("decks/drafts/meow.mp3"), "")?;
+        write(root.join(".obsidian/icon.png"), "")?;
+        let media: Vec<PathBuf> = media_files(root)?
+            .iter()
+            .map(|path| path.strip_prefix(root).unwrap().to_path_buf())
+            .collect();
+        assert_eq!(
+            media,
+            vec![
+                PathBuf::from("decks/cat.PNG"),
+                PathBuf::from("decks/drafts/meow.mp3")
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_ignore_file() -> Fallible<()> {
+        let directory = make_collection()?;
+        write(directory.path().join(IGNORE_FILE), "/README.md\ndrafts/\n")?;
+        assert_eq!(names(directory.path())?, vec!["decks/a.md"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_ignore_file_negation() -> Fallible<()> {
+        let directory = make_collection()?;
+        write(directory.path().join(IGNORE_FILE), "*.md\n!decks/a.md\n")?;
+        assert_eq!(names(directory.path())?, vec!["decks/a.md"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_include_and_exclude_globs() -> Fallible<()> {
+        let directory = make_collection()?;
+        write(
+            directory.path().join(CONFIG_FILE),
+            "include = [\"decks/**\"]\nexclude = [\"decks/drafts\"]\n",
+        )?;
+        assert_eq!(names(directory.path())?, vec!["decks/a.md"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_glob_does_not_cross_directories() -> Fallible<()> {
+        let directory = make_collection()?;
+        write(directory.path().join(CONFIG_FILE), "include = [\"*.md\"]\n")?;
+        assert_eq!(names(directory.path())?, vec!["README.md"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_glob() -> Fallible<()> {
+        let directory = make_collection()?;
+        write(directory.path().join(CONFIG_FILE), "exclude = [\"[\"]\n")?;
+        assert!(deck_files(directory.path()).is_err());
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinked_directories() -> Fallible<()> {
+        let directory = make_collection()?;
+        let root = directory.path();
+        std::os::unix::fs::symlink(root.join("decks"), root.join("linked"))?;
+        assert_eq!(
+            names(root)?,
+            vec!["README.md", "decks/a.md", "decks/drafts/b.md"]
+        );
+        write(root.join(CONFIG_FILE), "follow_symlinks = true\n")?;
+        assert_eq!(
+            names(root)?,
+            vec![
+                "README.md",
+                "decks/a.md",
+                "decks/drafts/b.md",
+                "linked/a.md",
+                "linked/drafts/b.md"
+            ]
+        );
+        Ok(())
+    }
+}
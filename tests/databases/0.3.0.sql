@@ -0,0 +1,51 @@
+-- A database as created by hashcards 0.3.0, which predates schema migrations.
+
+pragma foreign_keys = on;
+
+create table cards (
+    card_hash text primary key,
+    added_at text not null,
+    last_reviewed_at text,
+    stability real,
+    difficulty real,
+    interval_raw real,
+    interval_days integer,
+    due_date text,
+    review_count integer not null
+) strict;
+
+create table sessions (
+    session_id integer primary key,
+    started_at text not null,
+    ended_at text not null
+) strict;
+
+create table reviews (
+    review_id integer primary key,
+    session_id integer not null
+        references sessions (session_id)
+        on update cascade
+        on delete cascade,
+    card_hash text not null
+        references cards (card_hash)
+        on update cascade
+        on delete cascade,
+    reviewed_at text not null,
+    grade text not null,
+    stability real not null,
+    difficulty real not null,
+    interval_raw real not null,
+    interval_days integer not null,
+    due_date text not null
+) strict;
+
+insert into cards (card_hash, added_at, last_reviewed_at, stability, difficulty, interval_raw, interval_days, due_date, review_count)
+values
+    ('2c7d3b9b8b8e4a1f9a3e5a7c3b1e2d4f6a8c0e2b4d6f8a0c2e4b6d8f0a2c4e6b', '2026-01-10T09:00:00.000', '2026-01-11T09:30:00.000', 3.17, 5.28, 3.0, 3, '2026-01-14', 1),
+    ('9f1e3d5c7b9a1e3d5c7b9a1e3d5c7b9a1e3d5c7b9a1e3d5c7b9a1e3d5c7b9a1e', '2026-01-10T09:00:00.000', null, null, null, null, null, null, 0);
+
+insert into sessions (session_id, started_at, ended_at)
+values (1, '2026-01-11T09:29:00.000', '2026-01-11T09:31:00.000');
+
+insert into reviews (review_id, session_id, card_hash, reviewed_at, grade, stability, difficulty, interval_raw, interval_days, due_date)
+values (1, 1, '2c7d3b9b8b8e4a1f9a3e5a7c3b1e2d4f6a8c0e2b4d6f8a0c2e4b6d8f0a2c4e6b', '2026-01-11T09:30:00.000', 'good', 3.17, 5.28, 3.0, 3, '2026-01-14');